@@ -0,0 +1,1039 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use crate::results::{parse_atuin_output, parse_duration_ms, parse_timestamp, HistoryEntry};
+use crate::{
+    atuin_search_all, load_config, normalize_filters, search_all_output_with_backend,
+    search_backend, CommandBackend, HistoryBackend, SearchError, SearchFilters,
+};
+
+/// A command that has both succeeded and failed in history
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct FlakyCommand {
+    /// The command line
+    pub command: String,
+    /// Number of runs with a recorded exit code
+    pub total_runs: u32,
+    /// Number of runs with a non-zero exit code
+    pub failure_count: u32,
+    /// `failure_count / total_runs`, between 0 and 1
+    pub failure_rate: f64,
+}
+
+/// Find commands with mixed outcomes, ranked by failure rate.
+///
+/// Runs without a recorded exit code (negative exit) are ignored. Commands
+/// that only ever succeeded or only ever failed (including single runs) are
+/// not considered flaky. Ties are broken by total runs, then by command.
+pub fn compute_flaky_commands(results: &[HistoryEntry], limit: usize) -> Vec<FlakyCommand> {
+    let mut counts: HashMap<&str, (u32, u32)> = HashMap::new();
+    for result in results.iter().filter(|r| r.exit >= 0) {
+        let entry = counts.entry(result.command.as_str()).or_default();
+        entry.0 += 1;
+        if result.exit != 0 {
+            entry.1 += 1;
+        }
+    }
+
+    let mut flaky: Vec<FlakyCommand> = counts
+        .into_iter()
+        .filter(|(_, (total, failures))| *failures > 0 && failures < total)
+        .map(|(command, (total_runs, failure_count))| FlakyCommand {
+            command: command.to_string(),
+            total_runs,
+            failure_count,
+            failure_rate: failure_count as f64 / total_runs as f64,
+        })
+        .collect();
+
+    flaky.sort_by(|a, b| {
+        b.failure_rate
+            .total_cmp(&a.failure_rate)
+            .then(b.total_runs.cmp(&a.total_runs))
+            .then(a.command.cmp(&b.command))
+    });
+    flaky.truncate(limit);
+    flaky
+}
+
+/// Maximum number of values returned per facet dimension
+pub const MAX_FACET_VALUES: usize = 8;
+
+/// A distinct value in the current results and how often it occurs
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct FacetValue {
+    pub value: String,
+    pub count: u32,
+}
+
+/// Distinct values in a result set, used to render quick-filter chips
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct ResultFacets {
+    pub directories: Vec<FacetValue>,
+    pub exit_codes: Vec<FacetValue>,
+    /// First word of each command, e.g. "git" for "git push"
+    pub programs: Vec<FacetValue>,
+}
+
+/// Count values, most frequent first (ties by value), keeping at most `cap`
+fn top_values<'a>(values: impl Iterator<Item = &'a str>, cap: usize) -> Vec<FacetValue> {
+    let mut counts: HashMap<&str, u32> = HashMap::new();
+    for value in values.filter(|v| !v.is_empty()) {
+        *counts.entry(value).or_default() += 1;
+    }
+
+    let mut facets: Vec<FacetValue> = counts
+        .into_iter()
+        .map(|(value, count)| FacetValue {
+            value: value.to_string(),
+            count,
+        })
+        .collect();
+    facets.sort_by(|a, b| b.count.cmp(&a.count).then(a.value.cmp(&b.value)));
+    facets.truncate(cap);
+    facets
+}
+
+/// Compute directory, exit code and program facets for a result set
+pub fn compute_result_facets(entries: &[HistoryEntry], cap: usize) -> ResultFacets {
+    let exit_codes: Vec<String> = entries.iter().map(|e| e.exit.to_string()).collect();
+
+    ResultFacets {
+        directories: top_values(entries.iter().map(|e| e.directory.as_str()), cap),
+        exit_codes: top_values(exit_codes.iter().map(String::as_str), cap),
+        programs: top_values(
+            entries
+                .iter()
+                .filter_map(|e| e.command.split_whitespace().next()),
+            cap,
+        ),
+    }
+}
+
+/// Average duration of a command's runs within a time window
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct DurationBucket {
+    /// Start of the window in seconds since the Unix epoch (inclusive)
+    pub start: i64,
+    /// End of the window in seconds since the Unix epoch (inclusive)
+    pub end: i64,
+    pub run_count: u32,
+    pub average_duration_ms: u64,
+}
+
+/// Split `(timestamp, duration_ms)` runs into up to `buckets` equal-width
+/// time windows and average the durations in each.
+///
+/// Empty windows are omitted, so fewer buckets are returned when there are
+/// fewer runs than requested buckets or runs are clustered in time.
+pub fn bucket_durations(runs: &[(i64, u64)], buckets: usize) -> Vec<DurationBucket> {
+    let (Some(first), Some(last)) = (
+        runs.iter().map(|(time, _)| *time).min(),
+        runs.iter().map(|(time, _)| *time).max(),
+    ) else {
+        return Vec::new();
+    };
+    let buckets = buckets.clamp(1, runs.len()) as i64;
+    // Round up so the last run falls inside the final bucket
+    let width = ((last - first) / buckets + 1).max(1);
+
+    let mut totals: Vec<(u32, u64)> = vec![(0, 0); buckets as usize];
+    for (time, duration) in runs {
+        let index = ((time - first) / width).min(buckets - 1) as usize;
+        totals[index].0 += 1;
+        totals[index].1 += duration;
+    }
+
+    totals
+        .into_iter()
+        .enumerate()
+        .filter(|(_, (count, _))| *count > 0)
+        .map(|(index, (count, total))| {
+            let start = first + index as i64 * width;
+            DurationBucket {
+                start,
+                end: start + width - 1,
+                run_count: count,
+                average_duration_ms: total / u64::from(count),
+            }
+        })
+        .collect()
+}
+
+/// Average duration over time for exact runs of `command`
+pub fn command_duration_trend(command: &str, buckets: u32) -> Result<Vec<DurationBucket>, String> {
+    let output = atuin_search_all(command, None)?;
+    let runs: Vec<(i64, u64)> = parse_atuin_output(&output)
+        .into_iter()
+        .filter(|entry| entry.command == command)
+        .filter_map(|entry| {
+            Some((
+                parse_timestamp(&entry.time)?,
+                parse_duration_ms(&entry.duration)?,
+            ))
+        })
+        .collect();
+    Ok(bucket_durations(&runs, buckets as usize))
+}
+
+/// Search history and return the `limit` flakiest commands
+pub fn flaky_commands(
+    limit: u32,
+    filters: Option<SearchFilters>,
+) -> Result<Vec<FlakyCommand>, String> {
+    let output = atuin_search_all("", filters)?;
+    let results = parse_atuin_output(&output);
+    Ok(compute_flaky_commands(&results, limit as usize))
+}
+
+/// Number of programs listed in a history summary
+pub const SUMMARY_TOP_PROGRAMS: usize = 5;
+
+/// Digest of the history in a time range
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+pub struct HistorySummary {
+    /// Time range summarized, e.g. "7d", or "all"
+    pub range: String,
+    pub total_commands: u32,
+    pub unique_commands: u32,
+    /// Most used programs (first word of each command)
+    pub top_programs: Vec<FacetValue>,
+    pub success_count: u32,
+    pub failure_count: u32,
+    /// `success_count / (success_count + failure_count)`, 0 when there are
+    /// no runs with a recorded exit code
+    pub success_ratio: f64,
+    /// Directory with the most commands, if any
+    pub busiest_directory: Option<String>,
+    /// Sum of all parsed durations in milliseconds
+    pub total_duration_ms: u64,
+}
+
+/// Format milliseconds as e.g. "1h 2m 3s" (or "250ms" below a second)
+fn format_duration_ms(ms: u64) -> String {
+    if ms < 1000 {
+        return format!("{}ms", ms);
+    }
+    let secs = ms / 1000;
+    let (hours, minutes, seconds) = (secs / 3600, secs % 3600 / 60, secs % 60);
+    [(hours, "h"), (minutes, "m"), (seconds, "s")]
+        .iter()
+        .filter(|(value, _)| *value > 0)
+        .map(|(value, unit)| format!("{}{}", value, unit))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl HistorySummary {
+    /// Render the summary as Markdown for pasting into notes
+    pub fn to_markdown(&self) -> String {
+        let mut md = format!("## Shell history summary ({})\n\n", self.range);
+        md.push_str(&format!("- Total commands: {}\n", self.total_commands));
+        md.push_str(&format!("- Unique commands: {}\n", self.unique_commands));
+        md.push_str(&format!(
+            "- Success rate: {:.0}% ({} succeeded, {} failed)\n",
+            self.success_ratio * 100.0,
+            self.success_count,
+            self.failure_count
+        ));
+        md.push_str(&format!(
+            "- Busiest directory: {}\n",
+            self.busiest_directory
+                .as_deref()
+                .map_or("none".to_string(), |d| format!("`{}`", d))
+        ));
+        md.push_str(&format!(
+            "- Time spent: {}\n",
+            format_duration_ms(self.total_duration_ms)
+        ));
+
+        if !self.top_programs.is_empty() {
+            md.push_str("\n### Top programs\n\n");
+            for (i, program) in self.top_programs.iter().enumerate() {
+                md.push_str(&format!(
+                    "{}. `{}` ({})\n",
+                    i + 1,
+                    program.value,
+                    program.count
+                ));
+            }
+        }
+        md
+    }
+}
+
+/// Summarize a set of history entries
+pub fn compute_history_summary(range: &str, entries: &[HistoryEntry]) -> HistorySummary {
+    let unique: HashSet<&str> = entries.iter().map(|e| e.command.as_str()).collect();
+    let success_count = entries.iter().filter(|e| e.exit == 0).count() as u32;
+    let failure_count = entries.iter().filter(|e| e.exit > 0).count() as u32;
+    let recorded = success_count + failure_count;
+
+    HistorySummary {
+        range: range.to_string(),
+        total_commands: entries.len() as u32,
+        unique_commands: unique.len() as u32,
+        top_programs: top_values(
+            entries
+                .iter()
+                .filter_map(|e| e.command.split_whitespace().next()),
+            SUMMARY_TOP_PROGRAMS,
+        ),
+        success_count,
+        failure_count,
+        success_ratio: if recorded == 0 {
+            0.0
+        } else {
+            success_count as f64 / recorded as f64
+        },
+        busiest_directory: top_values(entries.iter().map(|e| e.directory.as_str()), 1)
+            .pop()
+            .map(|facet| facet.value),
+        total_duration_ms: entries
+            .iter()
+            .filter_map(|e| parse_duration_ms(&e.duration))
+            .sum(),
+    }
+}
+
+/// Filters for a history summary's time range, with the range's name
+fn summary_filters(range: &str) -> Result<(SearchFilters, String), String> {
+    let filters = normalize_filters(SearchFilters {
+        time_range: Some(range.to_string()),
+        ..Default::default()
+    })?;
+    let range = filters
+        .time_range
+        .clone()
+        .unwrap_or_else(|| "all".to_string());
+    Ok((filters, range))
+}
+
+/// Summarize all of the history in a time range through `backend`
+pub fn history_summary_with_backend(
+    range: &str,
+    backend: &dyn HistoryBackend,
+) -> Result<HistorySummary, String> {
+    let (filters, range) = summary_filters(range)?;
+    let output = search_all_output_with_backend("", Some(filters), backend)?;
+    Ok(compute_history_summary(
+        &range,
+        &parse_atuin_output(&output),
+    ))
+}
+
+/// Search history in a time range ("1h", "24h", "7d", "30d" or "all") and
+/// summarize it
+pub fn history_summary(range: &str) -> Result<HistorySummary, String> {
+    let (filters, range) = summary_filters(range)?;
+    let output = atuin_search_all("", Some(filters))?;
+    Ok(compute_history_summary(
+        &range,
+        &parse_atuin_output(&output),
+    ))
+}
+
+/// Number of history entries sampled for directory completion
+const DIRECTORY_SAMPLE_LIMIT: usize = 2000;
+
+/// Whether the characters of `needle` appear in order in `haystack`
+fn is_subsequence(needle: &str, haystack: &str) -> bool {
+    let mut haystack = haystack.chars();
+    needle.chars().all(|c| haystack.any(|h| h == c))
+}
+
+/// Distinct directories matching `prefix`, most used first.
+///
+/// Prefix matches rank above fuzzy (in-order subsequence) matches; within
+/// each group directories are ordered by how often they occur, then by
+/// path. Matching is case-insensitive and `"unknown"` is excluded.
+pub fn compute_directory_completions<'a>(
+    directories: impl Iterator<Item = &'a str>,
+    prefix: &str,
+    limit: usize,
+) -> Vec<String> {
+    let mut counts: HashMap<&str, u32> = HashMap::new();
+    for dir in directories.filter(|d| !d.is_empty() && *d != "unknown") {
+        *counts.entry(dir).or_default() += 1;
+    }
+
+    let prefix = prefix.to_lowercase();
+    let mut matches: Vec<(bool, u32, &str)> = counts
+        .into_iter()
+        .filter_map(|(dir, count)| {
+            let lower = dir.to_lowercase();
+            if lower.starts_with(&prefix) {
+                Some((true, count, dir))
+            } else if is_subsequence(&prefix, &lower) {
+                Some((false, count, dir))
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    matches.sort_by(|a, b| b.0.cmp(&a.0).then(b.1.cmp(&a.1)).then(a.2.cmp(b.2)));
+    matches
+        .into_iter()
+        .take(limit)
+        .map(|(_, _, dir)| dir.to_string())
+        .collect()
+}
+
+/// Complete a directory filter from the directories in history
+pub fn complete_directory(prefix: &str, limit: u32) -> Result<Vec<String>, String> {
+    let config = load_config();
+    let backend = CommandBackend::new(
+        &config.atuin_path,
+        config.use_login_shell,
+        config.atuin_env,
+        config.search_timeout_ms,
+    )?
+    .with_max_output_bytes(config.max_output_bytes);
+    let output = backend
+        .run(&[
+            "search".to_string(),
+            "--limit".to_string(),
+            DIRECTORY_SAMPLE_LIMIT.to_string(),
+            "--format".to_string(),
+            "{directory}".to_string(),
+            String::new(),
+        ])
+        .map_err(|e| e.to_string())?;
+    Ok(compute_directory_completions(
+        output.lines(),
+        prefix.trim(),
+        limit as usize,
+    ))
+}
+
+/// Number of directories listed when no limit is given
+const DIRECTORY_LIST_LIMIT: u32 = 50;
+
+/// A directory from history for the directory filter
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct DirectoryOption {
+    /// Absolute path, as filters match it
+    pub path: String,
+    /// Path shown to the user, with the home directory shortened to `~`
+    pub display: String,
+}
+
+/// `path` with a leading `home` replaced by `~`
+pub fn display_directory(path: &str, home: Option<&Path>) -> String {
+    match home.and_then(|home| Path::new(path).strip_prefix(home).ok()) {
+        Some(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+        Some(rest) => format!("~/{}", rest.display()),
+        None => path.to_string(),
+    }
+}
+
+/// Up to `limit` distinct directories in history through `backend`, most
+/// used first. Empty history yields no directories rather than an error.
+pub fn list_directories_with_backend(
+    backend: &dyn HistoryBackend,
+    limit: u32,
+    home: Option<&Path>,
+) -> Result<Vec<DirectoryOption>, SearchError> {
+    let output = backend.run(&[
+        "search".to_string(),
+        "--limit".to_string(),
+        DIRECTORY_SAMPLE_LIMIT.to_string(),
+        "--format".to_string(),
+        "{directory}".to_string(),
+        String::new(),
+    ]);
+    let output = match output {
+        Ok(output) => output,
+        // atuin exits unsuccessfully, without a message, when nothing matches
+        Err(SearchError::CommandFailed { stderr }) if stderr.trim().is_empty() => String::new(),
+        // A sample of the directories is enough
+        Err(SearchError::OutputTruncated { output, .. }) => output,
+        Err(e) => return Err(e),
+    };
+    Ok(
+        compute_directory_completions(output.lines(), "", limit as usize)
+            .into_iter()
+            .map(|path| DirectoryOption {
+                display: display_directory(&path, home),
+                path,
+            })
+            .collect(),
+    )
+}
+
+/// Directories in history, for the directory filter (default: up to
+/// `DIRECTORY_LIST_LIMIT`)
+pub fn list_directories(limit: Option<u32>) -> Result<Vec<DirectoryOption>, SearchError> {
+    let backend = search_backend(&load_config())?;
+    list_directories_with_backend(
+        backend.as_ref(),
+        limit.unwrap_or(DIRECTORY_LIST_LIMIT),
+        dirs::home_dir().as_deref(),
+    )
+}
+
+/// Number of recent history entries scanned for hostnames
+const HOST_SAMPLE_LIMIT: usize = 10_000;
+
+/// Distinct hostnames, most used first, then by name
+pub fn compute_hosts<'a>(hosts: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut counts: HashMap<&str, u32> = HashMap::new();
+    for host in hosts.map(str::trim).filter(|h| !h.is_empty()) {
+        *counts.entry(host).or_default() += 1;
+    }
+
+    let mut hosts: Vec<(&str, u32)> = counts.into_iter().collect();
+    hosts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    hosts
+        .into_iter()
+        .map(|(host, _)| host.to_string())
+        .collect()
+}
+
+/// Hostnames seen in history through `backend`. Empty history yields no
+/// hosts rather than an error.
+pub fn list_hosts_with_backend(backend: &dyn HistoryBackend) -> Result<Vec<String>, SearchError> {
+    let output = backend.run(&[
+        "search".to_string(),
+        "--limit".to_string(),
+        HOST_SAMPLE_LIMIT.to_string(),
+        "--format".to_string(),
+        "{host}".to_string(),
+        String::new(),
+    ]);
+    match output {
+        Ok(output) | Err(SearchError::OutputTruncated { output, .. }) => {
+            Ok(compute_hosts(output.lines()))
+        }
+        // atuin exits unsuccessfully, without a message, when nothing matches
+        Err(SearchError::CommandFailed { stderr }) if stderr.trim().is_empty() => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Hostnames seen in history, for the host filter
+pub fn list_hosts() -> Result<Vec<String>, String> {
+    let config = load_config();
+    let backend = CommandBackend::new(
+        &config.atuin_path,
+        config.use_login_shell,
+        config.atuin_env,
+        config.search_timeout_ms,
+    )?
+    .with_max_output_bytes(config.max_output_bytes);
+    Ok(list_hosts_with_backend(&backend)?)
+}
+
+/// Number of top commands `atuin stats` reports when no count is given
+pub const STATS_COUNT: u32 = 10;
+
+/// Parse the top-commands table printed by `atuin stats` into command/count
+/// pairs, e.g. `[▮▮▮▮▮▮    ]  312 git status`. The totals that follow the
+/// table, and anything else that isn't a row, are skipped.
+pub fn parse_stats_output(output: &str) -> Vec<(String, u64)> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            let row = match line.strip_prefix('[') {
+                Some(rest) => rest.split_once(']')?.1.trim_start(),
+                None => line,
+            };
+            let (count, command) = row.split_once(char::is_whitespace)?;
+            let command = command.trim();
+            if command.is_empty() {
+                return None;
+            }
+            Some((command.to_string(), count.parse().ok()?))
+        })
+        .collect()
+}
+
+/// The `count` most used commands according to `atuin stats`, run through
+/// `backend`
+pub fn atuin_stats_with_backend(
+    backend: &dyn HistoryBackend,
+    count: u32,
+) -> Result<Vec<(String, u64)>, SearchError> {
+    let output = backend.run(&[
+        "stats".to_string(),
+        "--count".to_string(),
+        count.to_string(),
+    ])?;
+    Ok(parse_stats_output(&output))
+}
+
+/// The most used commands according to `atuin stats` (default: `STATS_COUNT`)
+pub fn atuin_stats(count: Option<u32>) -> Result<Vec<(String, u64)>, SearchError> {
+    let config = load_config();
+    let backend = CommandBackend::new(
+        &config.atuin_path,
+        config.use_login_shell,
+        config.atuin_env,
+        config.search_timeout_ms,
+    )
+    .map_err(|message| SearchError::InvalidConfig { message })?
+    .with_max_output_bytes(config.max_output_bytes);
+    atuin_stats_with_backend(&backend, count.unwrap_or(STATS_COUNT).max(1))
+}
+
+/// Smallest duration at or above the given percentile (0-100) using the
+/// nearest-rank method. Every entry tied with the threshold is at the
+/// percentile, and a small sample always yields at least its slowest value.
+/// Returns None for an empty sample.
+pub fn duration_percentile_threshold(durations: &[u64], percentile: f32) -> Option<u64> {
+    let mut sorted = durations.to_vec();
+    sorted.sort_unstable();
+
+    let rank = ((percentile as f64 / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted.get(rank.clamp(1, sorted.len().max(1)) - 1).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::MatchSource;
+    use std::cell::RefCell;
+
+    fn run(command: &str, exit: i32) -> HistoryEntry {
+        run_in(command, exit, "/tmp")
+    }
+
+    fn run_in(command: &str, exit: i32, directory: &str) -> HistoryEntry {
+        HistoryEntry {
+            command: command.to_string(),
+            exit,
+            exit_info: None,
+            duration: "1ms".to_string(),
+            directory: directory.to_string(),
+            time: "2024-01-01 10:00:00".to_string(),
+            match_ranges: Vec::new(),
+            match_source: MatchSource::Local,
+            is_new: false,
+            is_favorite: false,
+            relative_time: None,
+        }
+    }
+
+    #[test]
+    fn test_flaky_commands_ranked_by_failure_rate() {
+        let results = vec![
+            // 1 of 4 failed
+            run("cargo test", 0),
+            run("cargo test", 0),
+            run("cargo test", 101),
+            run("cargo test", 0),
+            // 2 of 3 failed
+            run("npm install", 1),
+            run("npm install", 0),
+            run("npm install", 1),
+            // always succeeds
+            run("ls", 0),
+            run("ls", 0),
+            // always fails
+            run("make deploy", 2),
+            run("make deploy", 2),
+            // single run
+            run("git push", 1),
+            // unknown exit codes are ignored
+            run("sleep 100", -1),
+            run("sleep 100", 0),
+        ];
+
+        let flaky = compute_flaky_commands(&results, 10);
+        let commands: Vec<&str> = flaky.iter().map(|f| f.command.as_str()).collect();
+        assert_eq!(commands, vec!["npm install", "cargo test"]);
+
+        assert_eq!(flaky[0].total_runs, 3);
+        assert_eq!(flaky[0].failure_count, 2);
+        assert!((flaky[0].failure_rate - 2.0 / 3.0).abs() < f64::EPSILON);
+        assert_eq!(flaky[1].total_runs, 4);
+        assert_eq!(flaky[1].failure_count, 1);
+        assert!((flaky[1].failure_rate - 0.25).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_flaky_commands_respects_limit() {
+        let results = vec![
+            run("a", 0),
+            run("a", 1),
+            run("b", 0),
+            run("b", 1),
+            run("b", 1),
+        ];
+
+        let flaky = compute_flaky_commands(&results, 1);
+        assert_eq!(flaky.len(), 1);
+        assert_eq!(flaky[0].command, "b");
+    }
+
+    #[test]
+    fn test_result_facets_counts_values() {
+        let entries = vec![
+            run_in("git push", 0, "/repo"),
+            run_in("git pull", 1, "/repo"),
+            run_in("git status", 0, "/other"),
+            run_in("cargo build", 101, "/repo"),
+            run_in("ls -la", 0, "/tmp"),
+        ];
+
+        let facets = compute_result_facets(&entries, MAX_FACET_VALUES);
+        let pairs = |values: &[FacetValue]| -> Vec<(String, u32)> {
+            values.iter().map(|f| (f.value.clone(), f.count)).collect()
+        };
+
+        assert_eq!(
+            pairs(&facets.directories),
+            vec![
+                ("/repo".to_string(), 3),
+                ("/other".to_string(), 1),
+                ("/tmp".to_string(), 1)
+            ]
+        );
+        assert_eq!(
+            pairs(&facets.exit_codes),
+            vec![
+                ("0".to_string(), 3),
+                ("1".to_string(), 1),
+                ("101".to_string(), 1)
+            ]
+        );
+        assert_eq!(
+            pairs(&facets.programs),
+            vec![
+                ("git".to_string(), 3),
+                ("cargo".to_string(), 1),
+                ("ls".to_string(), 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_result_facets_are_capped() {
+        let entries: Vec<HistoryEntry> = (0..20)
+            .map(|i| run_in(&format!("cmd{}", i), i, &format!("/dir{}", i)))
+            .collect();
+
+        let facets = compute_result_facets(&entries, 5);
+        assert_eq!(facets.directories.len(), 5);
+        assert_eq!(facets.exit_codes.len(), 5);
+        assert_eq!(facets.programs.len(), 5);
+    }
+
+    #[test]
+    fn test_bucket_durations_averages_each_window() {
+        // Ten runs one hour apart, getting slower over time
+        let runs: Vec<(i64, u64)> = (0..10).map(|i| (i * 3600, 100 + i as u64 * 10)).collect();
+
+        let buckets = bucket_durations(&runs, 5);
+        assert_eq!(buckets.len(), 5);
+        assert!(buckets.iter().all(|b| b.run_count == 2));
+        let averages: Vec<u64> = buckets.iter().map(|b| b.average_duration_ms).collect();
+        assert_eq!(averages, vec![105, 125, 145, 165, 185]);
+        assert_eq!(buckets[0].start, 0);
+        assert!(buckets[4].end >= 9 * 3600);
+    }
+
+    #[test]
+    fn test_bucket_durations_with_few_runs() {
+        let runs = vec![(1_000, 50), (5_000, 150)];
+        let buckets = bucket_durations(&runs, 10);
+        assert_eq!(buckets.len(), 2);
+        assert_eq!(buckets[0].average_duration_ms, 50);
+        assert_eq!(buckets[1].average_duration_ms, 150);
+
+        let single = bucket_durations(&[(1_000, 42)], 4);
+        assert_eq!(single.len(), 1);
+        assert_eq!(single[0].run_count, 1);
+
+        assert!(bucket_durations(&[], 4).is_empty());
+    }
+
+    #[test]
+    fn test_bucket_durations_skips_empty_windows() {
+        // Two clusters far apart leave the middle buckets empty
+        let runs = vec![(0, 10), (10, 20), (10_000, 30), (10_010, 40)];
+        let buckets = bucket_durations(&runs, 4);
+        assert_eq!(buckets.len(), 2);
+        assert_eq!(buckets[0].average_duration_ms, 15);
+        assert_eq!(buckets[1].average_duration_ms, 35);
+    }
+
+    #[test]
+    fn test_duration_percentile_threshold() {
+        let durations: Vec<u64> = (1..=10).map(|n| n * 100).collect();
+
+        assert_eq!(duration_percentile_threshold(&durations, 90.0), Some(900));
+        assert_eq!(duration_percentile_threshold(&durations, 95.0), Some(1000));
+        assert_eq!(duration_percentile_threshold(&durations, 100.0), Some(1000));
+        assert_eq!(duration_percentile_threshold(&durations, 0.0), Some(100));
+        assert_eq!(duration_percentile_threshold(&[], 90.0), None);
+    }
+
+    #[test]
+    fn test_duration_percentile_threshold_small_samples_and_ties() {
+        assert_eq!(duration_percentile_threshold(&[42], 99.0), Some(42));
+        assert_eq!(duration_percentile_threshold(&[5, 5, 5, 1], 90.0), Some(5));
+    }
+
+    fn timed(command: &str, exit: i32, directory: &str, duration: &str) -> HistoryEntry {
+        HistoryEntry {
+            duration: duration.to_string(),
+            ..run_in(command, exit, directory)
+        }
+    }
+
+    #[test]
+    fn test_compute_history_summary() {
+        let entries = vec![
+            timed("git status", 0, "/repo", "20ms"),
+            timed("git push", 1, "/repo", "2s"),
+            timed("git status", 0, "/repo", "30ms"),
+            timed("cargo build", 0, "/repo", "1m 5s"),
+            timed("ls", 0, "/tmp", "5ms"),
+            timed("vim notes.md", -1, "/tmp", "10m"),
+        ];
+
+        let summary = compute_history_summary("7d", &entries);
+        assert_eq!(summary.range, "7d");
+        assert_eq!(summary.total_commands, 6);
+        assert_eq!(summary.unique_commands, 5);
+        assert_eq!(summary.success_count, 4);
+        assert_eq!(summary.failure_count, 1);
+        assert!((summary.success_ratio - 0.8).abs() < f64::EPSILON);
+        assert_eq!(summary.busiest_directory.as_deref(), Some("/repo"));
+        assert_eq!(
+            summary.total_duration_ms,
+            20 + 2_000 + 30 + 65_000 + 5 + 600_000
+        );
+
+        let programs: Vec<(&str, u32)> = summary
+            .top_programs
+            .iter()
+            .map(|p| (p.value.as_str(), p.count))
+            .collect();
+        assert_eq!(
+            programs,
+            vec![("git", 3), ("cargo", 1), ("ls", 1), ("vim", 1)]
+        );
+
+        let markdown = summary.to_markdown();
+        assert!(markdown.starts_with("## Shell history summary (7d)"));
+        assert!(markdown.contains("- Success rate: 80% (4 succeeded, 1 failed)"));
+        assert!(markdown.contains("- Time spent: 11m 7s"));
+        assert!(markdown.contains("1. `git` (3)"));
+    }
+
+    #[test]
+    fn test_compute_history_summary_empty_range() {
+        let summary = compute_history_summary("1h", &[]);
+        assert_eq!(
+            summary,
+            HistorySummary {
+                range: "1h".to_string(),
+                ..Default::default()
+            }
+        );
+        assert!(summary.to_markdown().contains("- Busiest directory: none"));
+    }
+
+    #[test]
+    fn test_directory_completions_match_prefix_and_dedup() {
+        let dirs = [
+            "/home/me/repo",
+            "/home/me/repo",
+            "/home/me/notes",
+            "/tmp",
+            "unknown",
+            "",
+        ];
+        let completions = compute_directory_completions(dirs.into_iter(), "/home", 10);
+        assert_eq!(completions, vec!["/home/me/repo", "/home/me/notes"]);
+
+        assert!(compute_directory_completions(dirs.into_iter(), "unk", 10).is_empty());
+    }
+
+    #[test]
+    fn test_directory_completions_ranked_by_frequency() {
+        let dirs = [
+            "/srv/a",
+            "/srv/b",
+            "/srv/b",
+            "/srv/c",
+            "/srv/c",
+            "/srv/c",
+            "/data/srv",
+        ];
+        let completions = compute_directory_completions(dirs.into_iter(), "/SRV", 3);
+        assert_eq!(completions, vec!["/srv/c", "/srv/b", "/srv/a"]);
+    }
+
+    #[test]
+    fn test_directory_completions_fall_back_to_fuzzy_matches() {
+        let dirs = ["/home/me/atuin-bar", "/home/me/atuin-bar", "/abc", "/etc"];
+        let completions = compute_directory_completions(dirs.into_iter(), "/ab", 10);
+        // The prefix match comes first even though it is used less
+        assert_eq!(completions, vec!["/abc", "/home/me/atuin-bar"]);
+    }
+
+    /// Answers every invocation with the same result
+    struct FixedBackend(Result<String, SearchError>);
+
+    /// Answers every invocation with `output`, recording the arguments
+    struct RecordingBackend {
+        output: String,
+        calls: RefCell<Vec<Vec<String>>>,
+    }
+
+    impl HistoryBackend for RecordingBackend {
+        fn run(&self, args: &[String]) -> Result<String, SearchError> {
+            self.calls.borrow_mut().push(args.to_vec());
+            Ok(self.output.clone())
+        }
+    }
+
+    impl HistoryBackend for FixedBackend {
+        fn run(&self, _args: &[String]) -> Result<String, SearchError> {
+            self.0.clone()
+        }
+    }
+
+    #[test]
+    fn test_history_summary_covers_every_entry_in_range() {
+        let output: String = (0..120)
+            .map(|i| format!("cmd{}|{}|1s|/repo|2024-01-01 10:00:00\n", i % 7, i % 3))
+            .collect();
+        let backend = RecordingBackend {
+            output,
+            calls: RefCell::new(Vec::new()),
+        };
+
+        let summary = history_summary_with_backend("7d", &backend).unwrap();
+        assert_eq!(summary.total_commands, 120);
+        assert_eq!(summary.unique_commands, 7);
+        assert_eq!(summary.success_count, 40);
+        assert_eq!(summary.failure_count, 80);
+
+        let calls = backend.calls.borrow();
+        assert!(!calls[0].contains(&"--limit".to_string()));
+        assert!(calls[0].contains(&"7 days ago".to_string()));
+    }
+
+    #[test]
+    fn test_compute_hosts_lists_distinct_hosts_most_used_first() {
+        let hosts = [
+            "laptop", "server", "laptop", "", "desktop", "server", "laptop",
+        ];
+        assert_eq!(
+            compute_hosts(hosts.into_iter()),
+            vec!["laptop", "server", "desktop"]
+        );
+    }
+
+    #[test]
+    fn test_list_hosts_with_no_history_is_empty() {
+        let backend = FixedBackend(Ok(String::new()));
+        assert_eq!(list_hosts_with_backend(&backend), Ok(Vec::new()));
+
+        let backend = FixedBackend(Err(SearchError::CommandFailed {
+            stderr: String::new(),
+        }));
+        assert_eq!(list_hosts_with_backend(&backend), Ok(Vec::new()));
+
+        let backend = FixedBackend(Err(SearchError::CommandFailed {
+            stderr: "database is locked".to_string(),
+        }));
+        assert!(list_hosts_with_backend(&backend).is_err());
+    }
+
+    #[test]
+    fn test_display_directory_shortens_home() {
+        let home = Path::new("/home/me");
+        assert_eq!(display_directory("/home/me", Some(home)), "~");
+        assert_eq!(
+            display_directory("/home/me/src/app", Some(home)),
+            "~/src/app"
+        );
+        // Only whole path components count as the home directory
+        assert_eq!(display_directory("/home/meg", Some(home)), "/home/meg");
+        assert_eq!(display_directory("/tmp", Some(home)), "/tmp");
+        assert_eq!(display_directory("/home/me/src", None), "/home/me/src");
+    }
+
+    #[test]
+    fn test_list_directories_most_used_first() {
+        let backend = FixedBackend(Ok(
+            "/tmp\n/home/me/src\nunknown\n/home/me/src\n/home/me\n".to_string()
+        ));
+        let directories =
+            list_directories_with_backend(&backend, 2, Some(Path::new("/home/me"))).unwrap();
+        assert_eq!(
+            directories,
+            vec![
+                DirectoryOption {
+                    path: "/home/me/src".to_string(),
+                    display: "~/src".to_string(),
+                },
+                DirectoryOption {
+                    path: "/home/me".to_string(),
+                    display: "~".to_string(),
+                },
+            ]
+        );
+
+        let backend = FixedBackend(Err(SearchError::CommandFailed {
+            stderr: String::new(),
+        }));
+        assert_eq!(
+            list_directories_with_backend(&backend, 10, None),
+            Ok(Vec::new())
+        );
+
+        let backend = FixedBackend(Err(SearchError::OutputTruncated {
+            max_bytes: 10,
+            output: "/tmp\n".to_string(),
+        }));
+        assert_eq!(
+            list_directories_with_backend(&backend, 10, None)
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_parse_stats_output() {
+        let output = "[▮▮▮▮▮▮▮▮▮▮] 2034 git status\n\
+                      [▮▮▮▮      ]  812 cargo test --workspace\n\
+                      [▮         ]    9 ls\n\
+                      Total commands:   3412\n\
+                      Unique commands:  655\n";
+        assert_eq!(
+            parse_stats_output(output),
+            vec![
+                ("git status".to_string(), 2034),
+                ("cargo test --workspace".to_string(), 812),
+                ("ls".to_string(), 9),
+            ]
+        );
+        assert!(parse_stats_output("").is_empty());
+    }
+
+    #[test]
+    fn test_atuin_stats_propagates_backend_errors() {
+        let backend = FixedBackend(Err(SearchError::AtuinNotInstalled {
+            path: "atuin".to_string(),
+        }));
+        assert!(matches!(
+            atuin_stats_with_backend(&backend, 5),
+            Err(SearchError::AtuinNotInstalled { .. })
+        ));
+
+        let backend = FixedBackend(Ok("[▮▮] 3 make\n".to_string()));
+        assert_eq!(
+            atuin_stats_with_backend(&backend, 5),
+            Ok(vec![("make".to_string(), 3)])
+        );
+    }
+}
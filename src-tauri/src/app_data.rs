@@ -0,0 +1,164 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::get_config_path;
+
+/// Directory for atuin-bar's own state (~/.local/share/atuin-bar on Linux)
+pub fn get_data_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|p| p.join("atuin-bar"))
+}
+
+/// Directory holding saved result snapshots
+pub fn get_snapshots_dir() -> Option<PathBuf> {
+    get_data_dir().map(|p| p.join("snapshots"))
+}
+
+/// Persisted search cache file
+pub fn get_cache_path() -> Option<PathBuf> {
+    get_data_dir().map(|p| p.join("search_cache.json"))
+}
+
+/// Persisted window position/size
+pub fn get_window_state_path() -> Option<PathBuf> {
+    get_data_dir().map(|p| p.join("window_state.json"))
+}
+
+/// A category of data the app stores on disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AppDataTarget {
+    Config,
+    Snapshots,
+    Cache,
+    WindowState,
+}
+
+impl AppDataTarget {
+    /// Location of this data on disk, if it can be determined
+    pub fn path(self) -> Option<PathBuf> {
+        match self {
+            AppDataTarget::Config => get_config_path(),
+            AppDataTarget::Snapshots => get_snapshots_dir(),
+            AppDataTarget::Cache => get_cache_path(),
+            AppDataTarget::WindowState => get_window_state_path(),
+        }
+    }
+}
+
+/// Size in bytes of each kind of data the app stores
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct AppDataUsage {
+    pub config_bytes: u64,
+    pub snapshots_bytes: u64,
+    pub cache_bytes: u64,
+    pub window_state_bytes: u64,
+    pub total_bytes: u64,
+}
+
+/// Total size of a file, or of all files below a directory.
+/// Missing paths count as zero; symlinks are not followed.
+pub fn path_size(path: &Path) -> u64 {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .map(|entry| path_size(&entry.path()))
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+/// Report how much disk space the app's own data uses
+pub fn app_data_usage() -> AppDataUsage {
+    let size_of = |target: AppDataTarget| target.path().map(|p| path_size(&p)).unwrap_or(0);
+
+    let mut usage = AppDataUsage {
+        config_bytes: size_of(AppDataTarget::Config),
+        snapshots_bytes: size_of(AppDataTarget::Snapshots),
+        cache_bytes: size_of(AppDataTarget::Cache),
+        window_state_bytes: size_of(AppDataTarget::WindowState),
+        total_bytes: 0,
+    };
+    usage.total_bytes =
+        usage.config_bytes + usage.snapshots_bytes + usage.cache_bytes + usage.window_state_bytes;
+    usage
+}
+
+/// Delete a file or directory; missing paths are not an error
+pub fn remove_path(path: &Path) -> io::Result<()> {
+    let result = match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(path),
+        Ok(_) => fs::remove_file(path),
+        Err(e) => Err(e),
+    };
+    match result {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+/// Delete the selected kinds of app data and return the remaining usage
+pub fn clear_app_data(targets: &[AppDataTarget]) -> Result<AppDataUsage, String> {
+    for target in targets {
+        if let Some(path) = target.path() {
+            remove_path(&path)
+                .map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+        }
+    }
+    Ok(app_data_usage())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("atuin-bar-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_path_size_sums_nested_files() {
+        let dir = temp_dir("path-size");
+        fs::write(dir.join("a.json"), vec![0u8; 100]).unwrap();
+        fs::create_dir_all(dir.join("nested/deeper")).unwrap();
+        fs::write(dir.join("nested/b.json"), vec![0u8; 20]).unwrap();
+        fs::write(dir.join("nested/deeper/c.json"), vec![0u8; 3]).unwrap();
+
+        assert_eq!(path_size(&dir), 123);
+        assert_eq!(path_size(&dir.join("a.json")), 100);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_path_size_missing_path_is_zero() {
+        let dir = temp_dir("missing");
+        assert_eq!(path_size(&dir.join("does-not-exist")), 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_remove_path_handles_files_dirs_and_missing() {
+        let dir = temp_dir("remove");
+        fs::write(dir.join("state.json"), "{}").unwrap();
+        fs::create_dir_all(dir.join("snapshots")).unwrap();
+        fs::write(dir.join("snapshots/one.json"), "[]").unwrap();
+
+        remove_path(&dir.join("state.json")).unwrap();
+        remove_path(&dir.join("snapshots")).unwrap();
+        remove_path(&dir.join("never-existed")).unwrap();
+
+        assert_eq!(path_size(&dir), 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,30 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Temporary file next to `path` that new contents are written to
+fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+/// Replace the file at `path` with what `write` produces. The contents go to
+/// a temporary file in the same directory that is then renamed over `path`,
+/// so dying mid-write never leaves a truncated file behind. On failure the
+/// temporary file is removed and `path` is left as it was.
+pub fn write_atomically(
+    path: &Path,
+    write: impl FnOnce(&mut fs::File) -> io::Result<()>,
+) -> io::Result<()> {
+    let temp = temp_path(path);
+    let written = fs::File::create(&temp).and_then(|mut file| {
+        write(&mut file)?;
+        file.sync_all()
+    });
+    let result = written.and_then(|_| fs::rename(&temp, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
@@ -0,0 +1,652 @@
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::io::{self, Read};
+use std::process::{Child, Command, Output, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Quote an argument for a POSIX shell command line
+pub fn shell_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+/// Whether `word` can go into a shell command line unquoted: nothing but
+/// letters, digits and `_./-`
+fn is_bare_word(word: &str) -> bool {
+    !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '/' | '-'))
+}
+
+/// Build a command that runs `cmd` through an interactive login shell
+/// (`$SHELL -ilc "..."`), so shell functions and aliases such as an `atuin`
+/// wrapper defined in the user's rc files are available. The program is
+/// left unquoted when it is a plain name, as a quoted word is never
+/// expanded as an alias.
+pub fn login_shell_command(shell: &str, cmd: &Command) -> Command {
+    let program = cmd.get_program().to_string_lossy();
+    let mut script = if is_bare_word(&program) {
+        program.into_owned()
+    } else {
+        shell_quote(&program)
+    };
+    for arg in cmd.get_args() {
+        script.push(' ');
+        script.push_str(&shell_quote(&arg.to_string_lossy()));
+    }
+
+    let mut shell_cmd = Command::new(shell);
+    shell_cmd.arg("-ilc").arg(script);
+    for (key, value) in cmd.get_envs() {
+        match value {
+            Some(value) => shell_cmd.env(key, value),
+            None => shell_cmd.env_remove(key),
+        };
+    }
+    if let Some(dir) = cmd.get_current_dir() {
+        shell_cmd.current_dir(dir);
+    }
+    shell_cmd
+}
+
+/// How often a command with a timeout is checked for having exited
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Read all of a child's pipe on its own thread, so a chatty child can't
+/// block on a full pipe while we wait for it to exit. With a `limit`, only
+/// one byte more than that is kept (enough to tell it was exceeded) and the
+/// rest is read and discarded.
+fn drain<R: Read + Send + 'static>(
+    pipe: Option<R>,
+    limit: Option<usize>,
+) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(mut pipe) = pipe {
+            match limit {
+                Some(limit) => {
+                    let _ = pipe.by_ref().take(limit as u64 + 1).read_to_end(&mut buf);
+                    let _ = io::copy(&mut pipe, &mut io::sink());
+                }
+                None => {
+                    let _ = pipe.read_to_end(&mut buf);
+                }
+            }
+        }
+        buf
+    })
+}
+
+/// Wait for `child` to exit, killing it after `timeout` (never when None). A
+/// killed child is still waited on so it doesn't linger as a zombie.
+fn wait_with_timeout(
+    mut child: Child,
+    timeout: Option<Duration>,
+    max_stdout: Option<usize>,
+) -> io::Result<Output> {
+    let stdout = drain(child.stdout.take(), max_stdout);
+    let stderr = drain(child.stderr.take(), None);
+
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            let _ = child.kill();
+            child.wait()?;
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!(
+                    "no response after {}ms",
+                    timeout.unwrap_or_default().as_millis()
+                ),
+            ));
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+/// Run `cmd` and collect its output, giving up after `timeout` (never when
+/// None). Times out with `io::ErrorKind::TimedOut`. Stdout past `max_stdout`
+/// bytes (plus one) is discarded rather than collected; see
+/// `truncate_output`.
+pub fn output_with_timeout(
+    cmd: &mut Command,
+    timeout: Option<Duration>,
+    max_stdout: Option<usize>,
+) -> io::Result<Output> {
+    if timeout.is_none() && max_stdout.is_none() {
+        return cmd.output();
+    }
+    let child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    wait_with_timeout(child, timeout, max_stdout)
+}
+
+/// Cut `output` down to at most `max_bytes`, after the last complete line
+/// that fits, so a record is never parsed half-cut. When not even the first
+/// line fits, nothing is kept. Returns whether anything was cut.
+pub fn truncate_output(output: &mut Vec<u8>, max_bytes: usize) -> bool {
+    if output.len() <= max_bytes {
+        return false;
+    }
+    let end = output[..max_bytes]
+        .iter()
+        .rposition(|&b| b == b'\n')
+        .map_or(0, |newline| newline + 1);
+    output.truncate(end);
+    true
+}
+
+/// Run an atuin command, retrying through the login shell from `$SHELL`
+/// when the executable isn't on PATH and `use_login_shell` is enabled
+pub fn output_with_fallback(
+    cmd: &mut Command,
+    use_login_shell: bool,
+    timeout: Option<Duration>,
+    max_stdout: Option<usize>,
+) -> io::Result<Output> {
+    match output_with_timeout(cmd, timeout, max_stdout) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound && use_login_shell => {
+            match env::var("SHELL").ok().filter(|shell| !shell.is_empty()) {
+                Some(shell) => {
+                    output_with_timeout(&mut login_shell_command(&shell, cmd), timeout, max_stdout)
+                }
+                None => Err(e),
+            }
+        }
+        result => result,
+    }
+}
+
+/// Decode atuin output line by line, dropping lines that aren't valid UTF-8
+/// (e.g. a command recorded in another encoding) rather than failing on
+/// them. Returns the text and the number of lines dropped.
+pub fn decode_output(output: &[u8]) -> (String, usize) {
+    let mut text = String::with_capacity(output.len());
+    let mut skipped = 0;
+    for line in output.split_inclusive(|&b| b == b'\n') {
+        match std::str::from_utf8(line) {
+            Ok(line) => text.push_str(line),
+            Err(_) => skipped += 1,
+        }
+    }
+    (text, skipped)
+}
+
+/// What was lost reading atuin's output, reported with the results so the
+/// UI can say they're incomplete
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize)]
+pub struct OutputLoss {
+    /// atuin printed more than `max_output_bytes`, so only the results that
+    /// fit are included
+    pub truncated: bool,
+    /// Lines dropped for not being valid UTF-8
+    pub skipped_lines: usize,
+}
+
+/// Default for `Config::max_output_bytes`
+pub const DEFAULT_MAX_OUTPUT_BYTES: u64 = 4 * 1024 * 1024;
+
+/// Start of the message for `SearchError::AtuinNotInstalled`
+pub const ATUIN_NOT_FOUND: &str = "atuin binary not found";
+
+/// Errors from searching history.
+///
+/// Serialized as `{"kind": "atuin_not_installed", ...}` so the frontend can
+/// branch on `kind`; the other fields hold the details.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SearchError {
+    /// No atuin executable at the configured path
+    AtuinNotInstalled { path: String },
+    /// atuin could not be started
+    SpawnFailed { message: String },
+    /// atuin didn't finish within `search_timeout_ms` and was killed
+    TimedOut { timeout_ms: u64 },
+    /// atuin exited unsuccessfully
+    CommandFailed { stderr: String },
+    /// atuin printed more than `max_output_bytes`. `output` holds the
+    /// complete lines that fit, for callers that can make do with part of
+    /// the results; it isn't sent to the frontend.
+    OutputTruncated {
+        max_bytes: u64,
+        #[serde(skip)]
+        output: String,
+    },
+    /// The search filters were rejected
+    InvalidFilter { message: String },
+    /// The atuin settings in config.toml are invalid
+    InvalidConfig { message: String },
+    /// Search state shared between commands was unusable
+    Internal { message: String },
+    /// A newer search started before this one finished, so its results
+    /// were dropped
+    Superseded { request_id: u64 },
+}
+
+impl fmt::Display for SearchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SearchError::AtuinNotInstalled { path } => write!(f, "{} at {}", ATUIN_NOT_FOUND, path),
+            SearchError::SpawnFailed { message } => {
+                write!(f, "Failed to execute atuin command: {}", message)
+            }
+            SearchError::TimedOut { timeout_ms } => {
+                write!(f, "atuin command timed out after {}ms", timeout_ms)
+            }
+            SearchError::CommandFailed { stderr } => write!(f, "atuin command failed: {}", stderr),
+            SearchError::OutputTruncated { max_bytes, .. } => write!(
+                f,
+                "atuin output exceeded {} bytes; narrow the search or raise max_output_bytes",
+                max_bytes
+            ),
+            SearchError::InvalidFilter { message }
+            | SearchError::InvalidConfig { message }
+            | SearchError::Internal { message } => write!(f, "{}", message),
+            SearchError::Superseded { request_id } => {
+                write!(f, "Search {} was superseded by a newer search", request_id)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SearchError {}
+
+impl From<SearchError> for String {
+    fn from(e: SearchError) -> Self {
+        e.to_string()
+    }
+}
+
+/// Something that can answer `atuin` invocations, given the arguments after
+/// the program name. Lets the search pipeline run against a scripted history
+/// in tests.
+pub trait HistoryBackend {
+    fn run(&self, args: &[String]) -> Result<String, SearchError>;
+
+    /// Lines of the last invocation's output dropped for not being valid
+    /// UTF-8
+    fn last_skipped_lines(&self) -> usize {
+        0
+    }
+}
+
+/// Whether a string is a conventional environment variable name
+/// (`[A-Za-z_][A-Za-z0-9_]*`)
+pub fn is_valid_env_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Runs the real `atuin` executable
+pub struct CommandBackend {
+    atuin_path: String,
+    use_login_shell: bool,
+    env: BTreeMap<String, String>,
+    timeout: Option<Duration>,
+    max_output_bytes: Option<usize>,
+    last_skipped_lines: Cell<usize>,
+}
+
+impl CommandBackend {
+    /// Create a backend that runs the atuin at `atuin_path` (a path or a
+    /// name looked up on PATH) and sets `env` on every invocation. Each
+    /// invocation is killed after `timeout_ms`; 0 waits indefinitely.
+    pub fn new(
+        atuin_path: &str,
+        use_login_shell: bool,
+        env: BTreeMap<String, String>,
+        timeout_ms: u64,
+    ) -> Result<Self, String> {
+        if atuin_path.trim().is_empty() {
+            return Err("atuin_path cannot be empty".to_string());
+        }
+        if let Some(key) = env.keys().find(|key| !is_valid_env_key(key)) {
+            return Err(format!("Invalid atuin_env variable name '{}'", key));
+        }
+        Ok(Self {
+            atuin_path: atuin_path.to_string(),
+            use_login_shell,
+            env,
+            timeout: (timeout_ms > 0).then(|| Duration::from_millis(timeout_ms)),
+            max_output_bytes: Some(DEFAULT_MAX_OUTPUT_BYTES as usize),
+            last_skipped_lines: Cell::new(0),
+        })
+    }
+
+    /// Fail with `SearchError::OutputTruncated` when an invocation prints
+    /// more than `max_bytes`; 0 allows any amount
+    pub fn with_max_output_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_output_bytes =
+            (max_bytes > 0).then(|| usize::try_from(max_bytes).unwrap_or(usize::MAX));
+        self
+    }
+
+    /// The atuin invocation for `args`
+    fn command(&self, args: &[String]) -> Command {
+        let mut cmd = Command::new(&self.atuin_path);
+        cmd.args(args).envs(&self.env);
+        cmd
+    }
+}
+
+impl HistoryBackend for CommandBackend {
+    fn run(&self, args: &[String]) -> Result<String, SearchError> {
+        self.last_skipped_lines.set(0);
+        let mut cmd = self.command(args);
+
+        let output = output_with_fallback(
+            &mut cmd,
+            self.use_login_shell,
+            self.timeout,
+            self.max_output_bytes,
+        )
+        .map_err(|e| {
+            if e.kind() == io::ErrorKind::NotFound {
+                SearchError::AtuinNotInstalled {
+                    path: self.atuin_path.clone(),
+                }
+            } else if e.kind() == io::ErrorKind::TimedOut {
+                SearchError::TimedOut {
+                    timeout_ms: self.timeout.map_or(0, |t| t.as_millis() as u64),
+                }
+            } else {
+                SearchError::SpawnFailed {
+                    message: e.to_string(),
+                }
+            }
+        })?;
+        if !output.status.success() {
+            return Err(SearchError::CommandFailed {
+                stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+            });
+        }
+        let mut stdout = output.stdout;
+        let truncated = self
+            .max_output_bytes
+            .is_some_and(|max| truncate_output(&mut stdout, max));
+        let (stdout, skipped) = decode_output(&stdout);
+        if skipped > 0 {
+            eprintln!(
+                "Skipped {} line(s) of atuin output that were not valid UTF-8",
+                skipped
+            );
+        }
+        self.last_skipped_lines.set(skipped);
+        match self.max_output_bytes {
+            Some(max) if truncated => Err(SearchError::OutputTruncated {
+                max_bytes: max as u64,
+                output: stdout,
+            }),
+            _ => Ok(stdout),
+        }
+    }
+
+    fn last_skipped_lines(&self) -> usize {
+        self.last_skipped_lines.get()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shell_quote_escapes_single_quotes() {
+        assert_eq!(shell_quote("git status"), "'git status'");
+        assert_eq!(shell_quote("echo 'hi'"), r"'echo '\''hi'\'''");
+    }
+
+    #[test]
+    fn test_login_shell_command_wraps_atuin_invocation() {
+        let mut cmd = Command::new("atuin");
+        cmd.arg("search")
+            .arg("--format")
+            .arg("{command}|{exit}")
+            .arg("it's");
+
+        let shell_cmd = login_shell_command("/bin/zsh", &cmd);
+        assert_eq!(shell_cmd.get_program(), "/bin/zsh");
+
+        let args: Vec<_> = shell_cmd
+            .get_args()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(
+            args,
+            vec![
+                "-ilc".to_string(),
+                r"atuin 'search' '--format' '{command}|{exit}' 'it'\''s'".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_login_shell_command_quotes_only_unusual_programs() {
+        let script = |program: &str| {
+            let shell_cmd = login_shell_command("/bin/bash", &Command::new(program));
+            shell_cmd
+                .get_args()
+                .nth(1)
+                .unwrap()
+                .to_string_lossy()
+                .into_owned()
+        };
+        assert_eq!(script("/usr/local/bin/atuin"), "/usr/local/bin/atuin");
+        assert_eq!(script("my-atuin_2.0"), "my-atuin_2.0");
+        assert_eq!(script("/opt/my apps/atuin"), "'/opt/my apps/atuin'");
+        assert_eq!(script("atuin;rm"), "'atuin;rm'");
+    }
+
+    #[test]
+    fn test_command_backend_sets_configured_env() {
+        let env = BTreeMap::from([
+            ("ATUIN_LOG".to_string(), "debug".to_string()),
+            ("_CUSTOM_1".to_string(), "x".to_string()),
+        ]);
+        let backend = CommandBackend::new("atuin", false, env, 0).unwrap();
+        let cmd = backend.command(&["search".to_string()]);
+
+        let envs: Vec<_> = cmd
+            .get_envs()
+            .map(|(k, v)| {
+                let value = v.map(|v| v.to_string_lossy().into_owned());
+                (k.to_string_lossy().into_owned(), value)
+            })
+            .collect();
+        assert_eq!(
+            envs,
+            vec![
+                ("ATUIN_LOG".to_string(), Some("debug".to_string())),
+                ("_CUSTOM_1".to_string(), Some("x".to_string())),
+            ]
+        );
+
+        // The login shell fallback keeps them too
+        let shell_cmd = login_shell_command("/bin/sh", &cmd);
+        assert_eq!(shell_cmd.get_envs().count(), 2);
+    }
+
+    #[test]
+    fn test_command_backend_rejects_invalid_env_keys() {
+        for key in ["", "1ABC", "MY-VAR", "A B", "A=B"] {
+            let env = BTreeMap::from([(key.to_string(), "x".to_string())]);
+            assert!(
+                CommandBackend::new("atuin", false, env, 0).is_err(),
+                "{:?} should be rejected",
+                key
+            );
+        }
+    }
+
+    #[test]
+    fn test_command_backend_uses_configured_atuin_path() {
+        let backend =
+            CommandBackend::new("/opt/atuin/bin/atuin", false, BTreeMap::new(), 0).unwrap();
+        assert_eq!(backend.command(&[]).get_program(), "/opt/atuin/bin/atuin");
+        assert!(CommandBackend::new("  ", false, BTreeMap::new(), 0).is_err());
+    }
+
+    #[test]
+    fn test_search_error_serializes_with_kind_tag() {
+        let error = SearchError::AtuinNotInstalled {
+            path: "atuin".to_string(),
+        };
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({"kind": "atuin_not_installed", "path": "atuin"})
+        );
+
+        let error = SearchError::CommandFailed {
+            stderr: "no database".to_string(),
+        };
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({"kind": "command_failed", "stderr": "no database"})
+        );
+    }
+
+    #[test]
+    fn test_missing_atuin_binary_is_reported() {
+        let path = "/nonexistent/atuin-bar-test/atuin";
+        let backend = CommandBackend::new(path, false, BTreeMap::new(), 0).unwrap();
+
+        let err = backend.run(&["search".to_string()]).unwrap_err();
+        assert_eq!(
+            err,
+            SearchError::AtuinNotInstalled {
+                path: path.to_string()
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            format!("atuin binary not found at {}", path)
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_hanging_atuin_is_killed_after_timeout() {
+        // `sleep` stands in for an atuin stuck on a locked database
+        let backend = CommandBackend::new("sleep", false, BTreeMap::new(), 100).unwrap();
+
+        let start = Instant::now();
+        let err = backend.run(&["5".to_string()]).unwrap_err();
+        assert_eq!(err, SearchError::TimedOut { timeout_ms: 100 });
+        assert!(
+            start.elapsed() < Duration::from_secs(2),
+            "took {:?}",
+            start.elapsed()
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_output_with_timeout_collects_output_of_quick_commands() {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "echo out; echo err >&2"]);
+
+        let output = output_with_timeout(&mut cmd, Some(Duration::from_secs(5)), None).unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"out\n");
+        assert_eq!(output.stderr, b"err\n");
+    }
+
+    #[test]
+    fn test_decode_output_skips_only_invalid_lines() {
+        let mut output = b"git status|0|1ms|/repo|2024-01-01 10:00:00\n".to_vec();
+        output.extend_from_slice(b"echo caf\xe9|0|1ms|/repo|2024-01-01 10:01:00\n");
+        output.extend_from_slice("ls ☕|0|1ms|/tmp|2024-01-01 10:02:00".as_bytes());
+
+        let (text, skipped) = decode_output(&output);
+        assert_eq!(skipped, 1);
+        let commands: Vec<String> = crate::results::parse_atuin_output(&text)
+            .into_iter()
+            .map(|entry| entry.command)
+            .collect();
+        assert_eq!(commands, vec!["git status", "ls ☕"]);
+
+        assert_eq!(decode_output(b"ls\n"), ("ls\n".to_string(), 0));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_backend_reports_skipped_lines() {
+        // `printf` stands in for atuin printing a command in another encoding
+        let backend = CommandBackend::new("printf", false, BTreeMap::new(), 0).unwrap();
+
+        let output = backend.run(&["ls\\n\\351\\npwd\\n".to_string()]).unwrap();
+        assert_eq!(output, "ls\npwd\n");
+        assert_eq!(backend.last_skipped_lines(), 1);
+
+        backend.run(&["ls\\n".to_string()]).unwrap();
+        assert_eq!(backend.last_skipped_lines(), 0);
+    }
+
+    #[test]
+    fn test_truncate_output_stops_at_line_boundary() {
+        let line = "git commit -m 'naïve café ☕'|0|1s|/repo|2024-01-01 10:00:00\n";
+        let mut output = line.repeat(1000).into_bytes();
+        let max = 10 * line.len() + line.len() / 2;
+
+        assert!(truncate_output(&mut output, max));
+        assert_eq!(output.len(), 10 * line.len());
+        assert_eq!(String::from_utf8(output).unwrap(), line.repeat(10));
+
+        let mut small = line.as_bytes().to_vec();
+        assert!(!truncate_output(&mut small, line.len()));
+        assert_eq!(small, line.as_bytes());
+    }
+
+    #[test]
+    fn test_truncate_output_drops_a_partial_record() {
+        // Not even the first record fits, with or without its newline
+        for text in [
+            "git commit -m '☕'|0|1s|/repo|2024-01-01 10:00:00",
+            "ls|0|1s|/tmp|2024-01-01 10:00:00\n",
+        ] {
+            for max in 0..text.len() - 1 {
+                let mut output = text.as_bytes().to_vec();
+                assert!(truncate_output(&mut output, max));
+                assert!(output.is_empty(), "kept {:?} of {:?}", output, text);
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_oversized_output_is_reported_as_truncated() {
+        // `seq` stands in for atuin printing a huge history
+        let backend = CommandBackend::new("seq", false, BTreeMap::new(), 0)
+            .unwrap()
+            .with_max_output_bytes(100);
+
+        let err = backend.run(&["100000".to_string()]).unwrap_err();
+        let SearchError::OutputTruncated { max_bytes, output } = err else {
+            panic!("expected truncation, got {:?}", err);
+        };
+        assert_eq!(max_bytes, 100);
+        assert!(output.len() <= 100 && output.ends_with('\n'));
+        assert!(output.starts_with("1\n2\n3\n"));
+
+        let backend = backend.with_max_output_bytes(0);
+        assert!(backend.run(&["1000".to_string()]).is_ok());
+    }
+}
@@ -0,0 +1,253 @@
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::SearchFilters;
+
+/// Path to atuin's own config file.
+///
+/// Mirrors atuin's lookup: `$ATUIN_CONFIG_DIR`, then `$XDG_CONFIG_HOME/atuin`,
+/// then `~/.config/atuin`.
+pub fn get_atuin_config_path() -> Option<PathBuf> {
+    resolve_atuin_config_path(
+        env::var_os("ATUIN_CONFIG_DIR"),
+        env::var_os("XDG_CONFIG_HOME"),
+        dirs::home_dir(),
+    )
+}
+
+/// Resolve the config path from the given environment; empty variables are
+/// treated as unset
+fn resolve_atuin_config_path(
+    atuin_config_dir: Option<OsString>,
+    xdg_config_home: Option<OsString>,
+    home: Option<PathBuf>,
+) -> Option<PathBuf> {
+    let non_empty = |value: Option<OsString>| value.filter(|v| !v.is_empty()).map(PathBuf::from);
+    let dir = non_empty(atuin_config_dir)
+        .or_else(|| non_empty(xdg_config_home).map(|p| p.join("atuin")))
+        .or_else(|| home.map(|p| p.join(".config").join("atuin")))?;
+    Some(dir.join("config.toml"))
+}
+
+/// Settings from atuin's config that affect how searches behave
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct AtuinDefaults {
+    /// "global", "host", "session", "directory" or "workspace"
+    pub filter_mode: Option<String>,
+    /// "prefix", "fulltext", "fuzzy" or "skim"
+    pub search_mode: Option<String>,
+}
+
+impl AtuinDefaults {
+    /// Parse the relevant settings from atuin's config TOML; unknown keys
+    /// and invalid documents are ignored
+    pub fn from_toml(contents: &str) -> Self {
+        let Ok(value) = contents.parse::<toml::Table>() else {
+            return Self::default();
+        };
+        let get = |key: &str| value.get(key).and_then(|v| v.as_str()).map(str::to_string);
+        Self {
+            filter_mode: get("filter_mode"),
+            search_mode: get("search_mode"),
+        }
+    }
+
+    /// Read atuin's defaults from a config file, falling back to atuin's
+    /// built-in defaults when it is missing
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .map(|contents| Self::from_toml(&contents))
+            .unwrap_or_default()
+    }
+}
+
+/// A likely-surprising interaction between the bar's filters and atuin's config
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct CompatWarning {
+    /// Stable identifier for the warning, e.g. "directory_with_session_mode"
+    pub code: String,
+    pub message: String,
+}
+
+impl CompatWarning {
+    fn new(code: &str, message: String) -> Self {
+        Self {
+            code: code.to_string(),
+            message,
+        }
+    }
+}
+
+/// Compare requested filters against atuin's defaults and describe any
+/// combinations that are likely to produce surprising results
+pub fn check_filter_compatibility(
+    filters: &SearchFilters,
+    defaults: &AtuinDefaults,
+) -> Vec<CompatWarning> {
+    let mut warnings = Vec::new();
+    let has_directory = filters
+        .directory
+        .as_deref()
+        .is_some_and(|d| !d.trim().is_empty());
+    let has_time_range = [&filters.time_range, &filters.before]
+        .iter()
+        .any(|t| t.as_deref().is_some_and(|t| !t.trim().is_empty()));
+
+    let filter_mode = defaults.filter_mode.as_deref();
+
+    if filter_mode == Some("session") {
+        if has_directory {
+            warnings.push(CompatWarning::new(
+                "directory_with_session_mode",
+                "Directory filter set but atuin filter_mode is session: only commands from the \
+                 current shell session in that directory will match"
+                    .to_string(),
+            ));
+        }
+        if has_time_range {
+            warnings.push(CompatWarning::new(
+                "time_range_with_session_mode",
+                "Time range set but atuin filter_mode is session: results are limited to the \
+                 current shell session regardless of the range"
+                    .to_string(),
+            ));
+        }
+    }
+
+    if let Some(mode @ ("directory" | "workspace")) = filter_mode {
+        if has_directory {
+            warnings.push(CompatWarning::new(
+                "directory_with_directory_mode",
+                format!(
+                    "Directory filter set but atuin filter_mode is {}: atuin may already \
+                     restrict results to its own current directory",
+                    mode
+                ),
+            ));
+        }
+    }
+
+    if filter_mode == Some("host") {
+        warnings.push(CompatWarning::new(
+            "host_mode",
+            "atuin filter_mode is host: history synced from other machines won't appear"
+                .to_string(),
+        ));
+    }
+
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn defaults(filter_mode: &str) -> AtuinDefaults {
+        AtuinDefaults::from_toml(&format!("filter_mode = \"{}\"\n", filter_mode))
+    }
+
+    #[test]
+    fn test_config_path_honors_atuin_config_dir() {
+        let path = resolve_atuin_config_path(
+            Some("/etc/atuin".into()),
+            Some("/home/me/.xdg".into()),
+            Some(PathBuf::from("/home/me")),
+        );
+        assert_eq!(path, Some(PathBuf::from("/etc/atuin/config.toml")));
+    }
+
+    #[test]
+    fn test_config_path_falls_back_to_platform_default() {
+        let path = resolve_atuin_config_path(
+            Some("".into()),
+            Some("/home/me/.xdg".into()),
+            Some(PathBuf::from("/home/me")),
+        );
+        assert_eq!(path, Some(PathBuf::from("/home/me/.xdg/atuin/config.toml")));
+
+        let path = resolve_atuin_config_path(None, None, Some(PathBuf::from("/home/me")));
+        assert_eq!(
+            path,
+            Some(PathBuf::from("/home/me/.config/atuin/config.toml"))
+        );
+
+        assert_eq!(resolve_atuin_config_path(None, None, None), None);
+    }
+
+    #[test]
+    fn test_from_toml_reads_modes() {
+        let parsed = AtuinDefaults::from_toml(
+            "filter_mode = \"session\"\nsearch_mode = \"fuzzy\"\nstyle = \"compact\"\n",
+        );
+        assert_eq!(parsed.filter_mode.as_deref(), Some("session"));
+        assert_eq!(parsed.search_mode.as_deref(), Some("fuzzy"));
+        assert_eq!(
+            AtuinDefaults::from_toml("not = [valid"),
+            AtuinDefaults::default()
+        );
+    }
+
+    #[test]
+    fn test_directory_filter_with_session_mode_warns() {
+        let filters = SearchFilters {
+            directory: Some("/home/user/project".to_string()),
+            ..Default::default()
+        };
+
+        let warnings = check_filter_compatibility(&filters, &defaults("session"));
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code, "directory_with_session_mode");
+        assert!(warnings[0].message.contains("filter_mode is session"));
+    }
+
+    #[test]
+    fn test_session_mode_reports_each_conflicting_filter() {
+        let filters = SearchFilters {
+            directory: Some("/tmp".to_string()),
+            time_range: Some("30d".to_string()),
+            ..Default::default()
+        };
+
+        let codes: Vec<String> = check_filter_compatibility(&filters, &defaults("session"))
+            .into_iter()
+            .map(|w| w.code)
+            .collect();
+        assert_eq!(
+            codes,
+            vec![
+                "directory_with_session_mode",
+                "time_range_with_session_mode"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_directory_filter_with_directory_mode_warns() {
+        let filters = SearchFilters {
+            directory: Some("/tmp".to_string()),
+            ..Default::default()
+        };
+
+        let warnings = check_filter_compatibility(&filters, &defaults("workspace"));
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code, "directory_with_directory_mode");
+    }
+
+    #[test]
+    fn test_compatible_filters_produce_no_warnings() {
+        let filters = SearchFilters {
+            directory: Some("/tmp".to_string()),
+            exit_filter: Some("failure".to_string()),
+            time_range: Some("7d".to_string()),
+            ..Default::default()
+        };
+
+        assert!(check_filter_compatibility(&filters, &defaults("global")).is_empty());
+        assert!(check_filter_compatibility(&filters, &AtuinDefaults::default()).is_empty());
+        assert!(
+            check_filter_compatibility(&SearchFilters::default(), &defaults("session")).is_empty()
+        );
+    }
+}
@@ -0,0 +1,109 @@
+use tauri_plugin_autostart::AutoLaunchManager;
+
+use crate::Config;
+
+/// OS login-item registration, abstracted so the toggle logic can be tested
+pub trait AutostartManager {
+    fn is_enabled(&self) -> Result<bool, String>;
+    fn enable(&mut self) -> Result<(), String>;
+    fn disable(&mut self) -> Result<(), String>;
+}
+
+impl AutostartManager for &AutoLaunchManager {
+    fn is_enabled(&self) -> Result<bool, String> {
+        AutoLaunchManager::is_enabled(self)
+            .map_err(|e| format!("Failed to read autostart state: {}", e))
+    }
+
+    fn enable(&mut self) -> Result<(), String> {
+        AutoLaunchManager::enable(self).map_err(|e| format!("Failed to enable autostart: {}", e))
+    }
+
+    fn disable(&mut self) -> Result<(), String> {
+        AutoLaunchManager::disable(self).map_err(|e| format!("Failed to disable autostart: {}", e))
+    }
+}
+
+/// Bring the OS registration in line with `enabled`.
+/// Returns whether anything changed; already being in the desired state is a no-op.
+pub fn apply_autostart(manager: &mut impl AutostartManager, enabled: bool) -> Result<bool, String> {
+    if manager.is_enabled()? == enabled {
+        return Ok(false);
+    }
+    if enabled {
+        manager.enable()?;
+    } else {
+        manager.disable()?;
+    }
+    Ok(true)
+}
+
+/// Apply `enabled` to the OS and record it in `config.autostart`
+pub fn update_autostart(
+    manager: &mut impl AutostartManager,
+    config: &mut Config,
+    enabled: bool,
+) -> Result<bool, String> {
+    let changed = apply_autostart(manager, enabled)?;
+    config.autostart = enabled;
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct MockManager {
+        enabled: bool,
+        calls: Vec<&'static str>,
+    }
+
+    impl AutostartManager for MockManager {
+        fn is_enabled(&self) -> Result<bool, String> {
+            Ok(self.enabled)
+        }
+
+        fn enable(&mut self) -> Result<(), String> {
+            self.calls.push("enable");
+            self.enabled = true;
+            Ok(())
+        }
+
+        fn disable(&mut self) -> Result<(), String> {
+            self.calls.push("disable");
+            self.enabled = false;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_update_autostart_reflects_state_in_config() {
+        let mut manager = MockManager::default();
+        let mut config = Config::default();
+        assert!(!config.autostart);
+
+        assert!(update_autostart(&mut manager, &mut config, true).unwrap());
+        assert!(config.autostart);
+        assert!(manager.enabled);
+
+        assert!(update_autostart(&mut manager, &mut config, false).unwrap());
+        assert!(!config.autostart);
+        assert_eq!(manager.calls, vec!["enable", "disable"]);
+    }
+
+    #[test]
+    fn test_apply_autostart_noop_when_already_in_state() {
+        let mut manager = MockManager {
+            enabled: true,
+            ..Default::default()
+        };
+
+        assert!(!apply_autostart(&mut manager, true).unwrap());
+        assert!(manager.calls.is_empty());
+
+        let mut manager = MockManager::default();
+        assert!(!apply_autostart(&mut manager, false).unwrap());
+        assert!(manager.calls.is_empty());
+    }
+}
@@ -0,0 +1,185 @@
+/// A parsed boolean search expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BooleanQuery {
+    /// Case-insensitive substring match
+    Term(String),
+    And(Box<BooleanQuery>, Box<BooleanQuery>),
+    Or(Box<BooleanQuery>, Box<BooleanQuery>),
+    Not(Box<BooleanQuery>),
+}
+
+impl BooleanQuery {
+    /// Whether a command matches the expression
+    pub fn matches(&self, command: &str) -> bool {
+        self.matches_lowercase(&command.to_lowercase())
+    }
+
+    fn matches_lowercase(&self, command: &str) -> bool {
+        match self {
+            BooleanQuery::Term(term) => command.contains(term.as_str()),
+            BooleanQuery::And(a, b) => a.matches_lowercase(command) && b.matches_lowercase(command),
+            BooleanQuery::Or(a, b) => a.matches_lowercase(command) || b.matches_lowercase(command),
+            BooleanQuery::Not(a) => !a.matches_lowercase(command),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Term(String),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+fn tokenize(query: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    for word in query.split_whitespace() {
+        let mut rest = word;
+        while let Some(stripped) = rest.strip_prefix('(') {
+            tokens.push(Token::Open);
+            rest = stripped;
+        }
+        let mut closes = 0;
+        while let Some(stripped) = rest.strip_suffix(')') {
+            closes += 1;
+            rest = stripped;
+        }
+        match rest {
+            "" => {}
+            "AND" => tokens.push(Token::And),
+            "OR" => tokens.push(Token::Or),
+            "NOT" => tokens.push(Token::Not),
+            term => tokens.push(Token::Term(term.to_lowercase())),
+        }
+        tokens.extend(std::iter::repeat_n(Token::Close, closes));
+    }
+    tokens
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    // or := and (OR and)*
+    fn parse_or(&mut self) -> Result<BooleanQuery, String> {
+        let mut expr = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            expr = BooleanQuery::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    // and := not ((AND)? not)*  -- adjacent terms are implicitly ANDed
+    fn parse_and(&mut self) -> Result<BooleanQuery, String> {
+        let mut expr = self.parse_not()?;
+        loop {
+            match self.peek() {
+                Some(Token::And) => {
+                    self.next();
+                }
+                Some(Token::Term(_) | Token::Not | Token::Open) => {}
+                _ => return Ok(expr),
+            }
+            expr = BooleanQuery::And(Box::new(expr), Box::new(self.parse_not()?));
+        }
+    }
+
+    // not := NOT not | primary
+    fn parse_not(&mut self) -> Result<BooleanQuery, String> {
+        if self.peek() == Some(&Token::Not) {
+            self.next();
+            return Ok(BooleanQuery::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_primary()
+    }
+
+    // primary := term | '(' or ')'
+    fn parse_primary(&mut self) -> Result<BooleanQuery, String> {
+        match self.next() {
+            Some(Token::Term(term)) => Ok(BooleanQuery::Term(term)),
+            Some(Token::Open) => {
+                let expr = self.parse_or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(expr),
+                    _ => Err("Missing closing parenthesis".to_string()),
+                }
+            }
+            Some(Token::Close) => Err("Unexpected closing parenthesis".to_string()),
+            Some(token) => Err(format!("Expected a search term, found {:?}", token)),
+            None => Err("Expected a search term at end of query".to_string()),
+        }
+    }
+}
+
+/// Parse a query using `AND`, `OR` and `NOT` (uppercase) with parentheses
+/// over space-separated terms. `NOT` binds tightest, then `AND`, then `OR`.
+pub fn parse_boolean_query(query: &str) -> Result<BooleanQuery, String> {
+    let mut parser = Parser {
+        tokens: tokenize(query),
+        position: 0,
+    };
+    if parser.tokens.is_empty() {
+        return Err("Empty boolean query".to_string());
+    }
+    let expr = parser.parse_or()?;
+    match parser.peek() {
+        None => Ok(expr),
+        Some(token) => Err(format!("Unexpected {:?} in query", token)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_and_query() {
+        let query = parse_boolean_query("git AND push").unwrap();
+        assert!(query.matches("git push origin main"));
+        assert!(!query.matches("git pull"));
+
+        // Adjacent terms are implicitly ANDed
+        assert_eq!(query, parse_boolean_query("git push").unwrap());
+    }
+
+    #[test]
+    fn test_or_query() {
+        let query = parse_boolean_query("docker OR podman").unwrap();
+        assert!(query.matches("docker ps"));
+        assert!(query.matches("Podman run alpine"));
+        assert!(!query.matches("kubectl get pods"));
+    }
+
+    #[test]
+    fn test_not_term_and_parentheses() {
+        let query = parse_boolean_query("(docker OR podman) NOT compose").unwrap();
+        assert!(query.matches("docker build ."));
+        assert!(!query.matches("docker compose up"));
+        assert!(!query.matches("ls"));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse_boolean_query("git AND").is_err());
+        assert!(parse_boolean_query("(git OR hg").is_err());
+        assert!(parse_boolean_query("git)").is_err());
+        assert!(parse_boolean_query("OR git").is_err());
+        assert!(parse_boolean_query("   ").is_err());
+    }
+}
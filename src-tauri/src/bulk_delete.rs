@@ -0,0 +1,299 @@
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeSet, HashMap};
+use std::hash::BuildHasher;
+use std::time::{Duration, Instant};
+
+use crate::atuin::{HistoryBackend, SearchError};
+use crate::results::parse_atuin_output;
+use crate::{filter_args, SearchFilters};
+
+/// How long a confirmation token stays valid
+pub const DELETE_TOKEN_TTL: Duration = Duration::from_secs(60);
+
+/// Single-use confirmation tokens for bulk deletes.
+///
+/// Deleting is a two-step handshake: the UI first asks for a token for a set
+/// of filters (showing the user what will be removed), then passes the token
+/// back to actually delete. A token only works for the filters it was issued
+/// for, once, and before it expires.
+#[derive(Debug, Default)]
+pub struct DeleteTokens {
+    pending: HashMap<String, (SearchFilters, Instant)>,
+    hasher: RandomState,
+    issued: u64,
+}
+
+impl DeleteTokens {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Issue a token confirming a delete of `filters`
+    pub fn issue(&mut self, filters: &SearchFilters, now: Instant) -> String {
+        self.pending
+            .retain(|_, (_, issued_at)| now.duration_since(*issued_at) < DELETE_TOKEN_TTL);
+
+        self.issued += 1;
+        let token = format!("{:016x}", self.hasher.hash_one((self.issued, filters)));
+        self.pending.insert(token.clone(), (filters.clone(), now));
+        token
+    }
+
+    /// Consume a token, checking it was issued for `filters` and is fresh
+    pub fn redeem(
+        &mut self,
+        token: &str,
+        filters: &SearchFilters,
+        now: Instant,
+    ) -> Result<(), String> {
+        let (issued_for, issued_at) = self
+            .pending
+            .remove(token)
+            .ok_or_else(|| "Invalid or already used confirmation token".to_string())?;
+        if now.duration_since(issued_at) >= DELETE_TOKEN_TTL {
+            return Err("Confirmation token expired; please confirm again".to_string());
+        }
+        if &issued_for != filters {
+            return Err("Confirmation token was issued for different filters".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Whether filters would match the entire history: none of the fields that
+/// narrow a search is set
+pub fn is_unfiltered(filters: &SearchFilters) -> bool {
+    [
+        &filters.directory,
+        &filters.exit_filter,
+        &filters.time_range,
+        &filters.before,
+        &filters.host,
+        &filters.session,
+    ]
+    .iter()
+    .all(|field| field.as_deref().is_none_or(|value| value.trim().is_empty()))
+}
+
+/// Check a delete can be carried out by atuin for these filters
+pub fn check_deletable(filters: &SearchFilters, allow_unfiltered: bool) -> Result<(), String> {
+    if filters.boolean_query
+        || filters.duration_percentile.is_some()
+        || filters.min_duration_ms.is_some()
+        || filters.max_duration_ms.is_some()
+    {
+        return Err(
+            "Boolean queries and duration filters can't be used to delete history".to_string(),
+        );
+    }
+    // `atuin search --delete` can't be scoped to a host or session, so it
+    // would also delete matching entries from elsewhere
+    let is_set = |field: &Option<String>| {
+        field
+            .as_deref()
+            .is_some_and(|value| !value.trim().is_empty())
+    };
+    if is_set(&filters.host) || is_set(&filters.session) {
+        return Err("Host and session filters can't be used to delete history".to_string());
+    }
+    if is_unfiltered(filters) && !allow_unfiltered {
+        return Err(
+            "Refusing to delete the entire history; add a filter or explicitly allow an unfiltered delete"
+                .to_string(),
+        );
+    }
+    Ok(())
+}
+
+/// Delete every history entry matching `filters` and return how many were
+/// removed. Callers must have checked `check_deletable` first.
+///
+/// atuin's `--delete` needs a query, so each distinct matched command is
+/// deleted with the same filters applied; any entry this matches is within
+/// the filters, and so was going to be deleted anyway.
+pub fn delete_matching(
+    filters: &SearchFilters,
+    backend: &dyn HistoryBackend,
+) -> Result<u64, SearchError> {
+    let mut list_args = vec![
+        "search".to_string(),
+        "--format".to_string(),
+        "{command}|{exit}|{duration}|{directory}|{time}".to_string(),
+    ];
+    list_args.extend(filter_args(filters));
+    list_args.push(String::new());
+
+    let entries = parse_atuin_output(&backend.run(&list_args)?);
+    if entries.is_empty() {
+        return Ok(0);
+    }
+
+    if is_unfiltered(filters) {
+        backend.run(&["search".to_string(), "--delete-it-all".to_string()])?;
+        return Ok(entries.len() as u64);
+    }
+
+    let commands: BTreeSet<&str> = entries.iter().map(|e| e.command.as_str()).collect();
+    for command in commands {
+        let mut delete_args = vec![
+            "search".to_string(),
+            "--delete".to_string(),
+            "--search-mode".to_string(),
+            "prefix".to_string(),
+        ];
+        delete_args.extend(filter_args(filters));
+        // A command like `--help` must stay the query, not become a flag
+        delete_args.push("--".to_string());
+        delete_args.push(command.to_string());
+        backend.run(&delete_args)?;
+    }
+    Ok(entries.len() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    fn failures() -> SearchFilters {
+        SearchFilters {
+            exit_filter: Some("failure".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_token_is_single_use_and_bound_to_filters() {
+        let mut tokens = DeleteTokens::new();
+        let now = Instant::now();
+
+        let token = tokens.issue(&failures(), now);
+        assert!(tokens.redeem(&token, &failures(), now).is_ok());
+        assert!(tokens.redeem(&token, &failures(), now).is_err());
+
+        let token = tokens.issue(&failures(), now);
+        let err = tokens
+            .redeem(&token, &SearchFilters::default(), now)
+            .unwrap_err();
+        assert!(err.contains("different filters"), "got: {}", err);
+
+        assert!(tokens.redeem("not-a-token", &failures(), now).is_err());
+    }
+
+    #[test]
+    fn test_token_expires() {
+        let mut tokens = DeleteTokens::new();
+        let now = Instant::now();
+        let token = tokens.issue(&failures(), now);
+
+        let err = tokens
+            .redeem(&token, &failures(), now + DELETE_TOKEN_TTL)
+            .unwrap_err();
+        assert!(err.contains("expired"), "got: {}", err);
+    }
+
+    #[test]
+    fn test_unfiltered_delete_is_refused_unless_allowed() {
+        let everything = SearchFilters::default();
+        assert!(check_deletable(&everything, false).is_err());
+        assert!(check_deletable(&everything, true).is_ok());
+        assert!(check_deletable(&failures(), false).is_ok());
+
+        let empty_dir = SearchFilters {
+            directory: Some(String::new()),
+            ..Default::default()
+        };
+        assert!(check_deletable(&empty_dir, false).is_err());
+    }
+
+    #[test]
+    fn test_host_or_session_counts_as_a_filter() {
+        let host_only = SearchFilters {
+            host: Some("laptop".to_string()),
+            ..Default::default()
+        };
+        let session_only = SearchFilters {
+            session: Some("abc123".to_string()),
+            ..Default::default()
+        };
+        assert!(!is_unfiltered(&host_only));
+        assert!(!is_unfiltered(&session_only));
+        // atuin can't scope a delete to them, so they're refused outright
+        assert!(check_deletable(&host_only, true).is_err());
+        assert!(check_deletable(&session_only, true).is_err());
+    }
+
+    #[test]
+    fn test_host_or_session_delete_never_deletes_everything() {
+        for filters in [
+            SearchFilters {
+                host: Some("laptop".to_string()),
+                ..Default::default()
+            },
+            SearchFilters {
+                session: Some("abc123".to_string()),
+                ..Default::default()
+            },
+        ] {
+            let backend = MockBackend {
+                listing: "ls|0|1s|/repo|2024-01-01 10:00:00\n".to_string(),
+                calls: RefCell::new(Vec::new()),
+            };
+            delete_matching(&filters, &backend).unwrap();
+
+            let calls = backend.calls.borrow();
+            assert!(calls
+                .iter()
+                .all(|call| !call.contains(&"--delete-it-all".to_string())));
+        }
+    }
+
+    struct MockBackend {
+        listing: String,
+        calls: RefCell<Vec<Vec<String>>>,
+    }
+
+    impl HistoryBackend for MockBackend {
+        fn run(&self, args: &[String]) -> Result<String, SearchError> {
+            self.calls.borrow_mut().push(args.to_vec());
+            Ok(self.listing.clone())
+        }
+    }
+
+    #[test]
+    fn test_delete_matching_deletes_each_command_within_filters() {
+        let backend = MockBackend {
+            listing: "cargo test|101|1s|/repo|2024-01-01 10:00:00\n\
+                      git push|1|1s|/repo|2024-01-01 10:01:00\n\
+                      cargo test|101|1s|/repo|2024-01-01 10:02:00\n"
+                .to_string(),
+            calls: RefCell::new(Vec::new()),
+        };
+
+        let removed = delete_matching(&failures(), &backend).unwrap();
+        assert_eq!(removed, 3);
+
+        let calls = backend.calls.borrow();
+        assert_eq!(calls.len(), 3);
+        for call in &calls[1..] {
+            assert!(call.contains(&"--delete".to_string()));
+            assert!(call.contains(&"--exclude-exit".to_string()));
+        }
+        assert_eq!(calls[1].last().map(String::as_str), Some("cargo test"));
+        assert_eq!(calls[2].last().map(String::as_str), Some("git push"));
+    }
+
+    #[test]
+    fn test_delete_matching_passes_dashed_commands_as_the_query() {
+        let backend = MockBackend {
+            listing: "--help|1|1s|/repo|2024-01-01 10:00:00
+"
+            .to_string(),
+            calls: RefCell::new(Vec::new()),
+        };
+        delete_matching(&failures(), &backend).unwrap();
+
+        let calls = backend.calls.borrow();
+        assert!(calls[1].ends_with(&["--".to_string(), "--help".to_string()]));
+    }
+}
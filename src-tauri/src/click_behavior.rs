@@ -0,0 +1,76 @@
+/// Actions a click on a result can trigger
+pub const CLICK_ACTIONS: &[&str] = &["none", "select", "preview", "copy", "run"];
+
+/// What single and double clicks on a result do
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ClickBehavior {
+    pub single: String,
+    pub double: String,
+}
+
+impl Default for ClickBehavior {
+    fn default() -> Self {
+        Self {
+            single: "select".to_string(),
+            double: "copy".to_string(),
+        }
+    }
+}
+
+/// Check both mappings are known actions, and that "run" is only used when
+/// running commands is allowed
+pub fn validate_click_behavior(behavior: &ClickBehavior, allow_run: bool) -> Result<(), String> {
+    for (click, action) in [("single", &behavior.single), ("double", &behavior.double)] {
+        if !CLICK_ACTIONS.contains(&action.as_str()) {
+            return Err(format!(
+                "Invalid {} click action '{}': expected one of {}",
+                click,
+                action,
+                CLICK_ACTIONS.join(", ")
+            ));
+        }
+        if action == "run" && !allow_run {
+            return Err(format!(
+                "The {} click action 'run' requires allow_run = true in config.toml",
+                click
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn behavior(single: &str, double: &str) -> ClickBehavior {
+        ClickBehavior {
+            single: single.to_string(),
+            double: double.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_valid_click_mappings() {
+        assert!(validate_click_behavior(&ClickBehavior::default(), false).is_ok());
+        assert!(validate_click_behavior(&behavior("preview", "none"), false).is_ok());
+        assert!(validate_click_behavior(&behavior("select", "run"), true).is_ok());
+    }
+
+    #[test]
+    fn test_run_mapping_requires_allow_run() {
+        let err = validate_click_behavior(&behavior("select", "run"), false).unwrap_err();
+        assert!(err.contains("allow_run"), "got: {}", err);
+    }
+
+    #[test]
+    fn test_unknown_action_is_rejected() {
+        let err = validate_click_behavior(&behavior("launch", "copy"), true).unwrap_err();
+        assert!(
+            err.contains("Invalid single click action 'launch'"),
+            "got: {}",
+            err
+        );
+    }
+}
@@ -0,0 +1,174 @@
+/// Supported values for `Config::copy_trailing_newline`
+pub const TRAILING_NEWLINE_POLICIES: &[&str] = &["strip", "keep", "ask", "add"];
+
+/// What to do with a copy request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CopyPlan {
+    /// Write this text to the clipboard
+    Write(String),
+    /// The text ends in a newline and the user should decide whether to keep it
+    Ask,
+}
+
+/// Result of a copy request, telling the UI whether it needs to prompt
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub struct CopyOutcome {
+    pub copied: bool,
+    /// Nothing was copied; ask whether to keep the trailing newline and copy
+    /// again with the answer
+    pub needs_confirmation: bool,
+}
+
+/// Apply the trailing-newline policy to `text`. `keep_newline` is the user's
+/// answer when the policy is "ask", and is ignored otherwise.
+///
+/// A trailing newline can make some paste targets (e.g. terminals) execute
+/// the command immediately, hence "strip" being the default. "add" ensures
+/// exactly one trailing newline for users who want just that.
+pub fn prepare_copy(
+    text: &str,
+    policy: &str,
+    keep_newline: Option<bool>,
+) -> Result<CopyPlan, String> {
+    let stripped = text
+        .strip_suffix('\n')
+        .map(|t| t.strip_suffix('\r').unwrap_or(t));
+    if policy == "add" {
+        return Ok(CopyPlan::Write(match stripped {
+            Some(_) => text.to_string(),
+            None => format!("{}\n", text),
+        }));
+    }
+    let Some(stripped) = stripped else {
+        return Ok(CopyPlan::Write(text.to_string()));
+    };
+
+    match policy {
+        "strip" => Ok(CopyPlan::Write(stripped.to_string())),
+        "keep" => Ok(CopyPlan::Write(text.to_string())),
+        "ask" => Ok(match keep_newline {
+            Some(true) => CopyPlan::Write(text.to_string()),
+            Some(false) => CopyPlan::Write(stripped.to_string()),
+            None => CopyPlan::Ask,
+        }),
+        other => Err(format!(
+            "Invalid copy_trailing_newline '{}': expected one of {}",
+            other,
+            TRAILING_NEWLINE_POLICIES.join(", ")
+        )),
+    }
+}
+
+/// Tidy `text` for pasting: trailing spaces and tabs are trimmed from each
+/// line, `\r\n` line endings become `\n` and any other `\r` is dropped.
+/// Spacing inside a line is left alone, and a final newline is kept for
+/// `prepare_copy` to deal with.
+pub fn normalize_command(text: &str) -> String {
+    text.split('\n')
+        .map(|line| {
+            line.replace('\r', "")
+                .trim_end_matches([' ', '\t'])
+                .to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMMAND: &str = "rm -rf build\n";
+
+    #[test]
+    fn test_strip_removes_trailing_newline() {
+        assert_eq!(
+            prepare_copy(COMMAND, "strip", None),
+            Ok(CopyPlan::Write("rm -rf build".to_string()))
+        );
+        assert_eq!(
+            prepare_copy("ls\r\n", "strip", None),
+            Ok(CopyPlan::Write("ls".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_keep_leaves_text_alone() {
+        assert_eq!(
+            prepare_copy(COMMAND, "keep", None),
+            Ok(CopyPlan::Write(COMMAND.to_string()))
+        );
+    }
+
+    #[test]
+    fn test_add_terminates_command_once() {
+        assert_eq!(
+            prepare_copy("ls", "add", None),
+            Ok(CopyPlan::Write("ls\n".to_string()))
+        );
+        // Already terminated commands aren't terminated twice
+        assert_eq!(
+            prepare_copy(COMMAND, "add", None),
+            Ok(CopyPlan::Write(COMMAND.to_string()))
+        );
+        assert_eq!(
+            prepare_copy("ls\r\n", "add", None),
+            Ok(CopyPlan::Write("ls\r\n".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_strip_and_keep_leave_unterminated_command_alone() {
+        for policy in ["strip", "keep"] {
+            assert_eq!(
+                prepare_copy("ls", policy, None),
+                Ok(CopyPlan::Write("ls".to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn test_ask_flags_trailing_newline_until_answered() {
+        assert_eq!(prepare_copy(COMMAND, "ask", None), Ok(CopyPlan::Ask));
+        assert_eq!(
+            prepare_copy(COMMAND, "ask", Some(true)),
+            Ok(CopyPlan::Write(COMMAND.to_string()))
+        );
+        assert_eq!(
+            prepare_copy(COMMAND, "ask", Some(false)),
+            Ok(CopyPlan::Write("rm -rf build".to_string()))
+        );
+        // Nothing to ask about without a trailing newline
+        assert_eq!(
+            prepare_copy("ls", "ask", None),
+            Ok(CopyPlan::Write("ls".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_normalize_trims_trailing_spaces() {
+        assert_eq!(normalize_command("git status  \t"), "git status");
+        assert_eq!(
+            normalize_command("echo a \\  \n  echo b  \n"),
+            "echo a \\\n  echo b\n"
+        );
+    }
+
+    #[test]
+    fn test_normalize_strips_carriage_returns() {
+        assert_eq!(normalize_command("ls\r\n"), "ls\n");
+        assert_eq!(normalize_command("echo a\r\necho b"), "echo a\necho b");
+        assert_eq!(normalize_command("echo\r done"), "echo done");
+    }
+
+    #[test]
+    fn test_normalize_keeps_internal_spacing() {
+        let command = "printf '%s  %s' a  b";
+        assert_eq!(normalize_command(command), command);
+    }
+
+    #[test]
+    fn test_unknown_policy_is_rejected() {
+        assert!(prepare_copy(COMMAND, "sometimes", None).is_err());
+    }
+}
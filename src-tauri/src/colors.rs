@@ -0,0 +1,153 @@
+/// 64-bit FNV-1a hash; unlike `DefaultHasher` its output is stable across
+/// Rust versions, so colors stay the same between sessions and upgrades
+fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    bytes.iter().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(PRIME)
+    })
+}
+
+/// Convert HSL (hue in degrees, saturation and lightness in 0..1) to RGB
+fn hsl_to_rgb(hue: f64, saturation: f64, lightness: f64) -> (u8, u8, u8) {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let sector = hue / 60.0;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    let channel = |v: f64| ((v + m) * 255.0).round().clamp(0.0, 255.0) as u8;
+    (channel(r), channel(g), channel(b))
+}
+
+/// WCAG relative luminance of an RGB color
+fn relative_luminance((r, g, b): (u8, u8, u8)) -> f64 {
+    let channel = |v: u8| {
+        let v = f64::from(v) / 255.0;
+        if v <= 0.03928 {
+            v / 12.92
+        } else {
+            ((v + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * channel(r) + 0.7152 * channel(g) + 0.0722 * channel(b)
+}
+
+/// WCAG contrast ratio between two luminances
+fn contrast_ratio(a: f64, b: f64) -> f64 {
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+/// Minimum contrast against the window surface (WCAG AA for normal text)
+const MIN_CONTRAST: f64 = 4.5;
+
+/// Window surface colors from styles.css (`--elevated-surface`)
+const DARK_SURFACE: (u8, u8, u8) = (0x2a, 0x2a, 0x2a);
+const LIGHT_SURFACE: (u8, u8, u8) = (0xff, 0xff, 0xff);
+
+/// Deterministic hex color (e.g. "#5fb3d9") for a directory.
+///
+/// The hue comes from a hash of the path. Lightness starts from a
+/// theme-specific value and is pushed away from the window surface until
+/// the color has readable contrast on the "dark" or "light" theme.
+pub fn directory_color(directory: &str, theme: &str) -> String {
+    let normalized = directory.trim().trim_end_matches('/');
+    let hash = fnv1a(normalized.as_bytes());
+    let hue = (hash % 360) as f64;
+
+    let light_theme = theme == "light";
+    let surface = relative_luminance(if light_theme {
+        LIGHT_SURFACE
+    } else {
+        DARK_SURFACE
+    });
+    let (mut lightness, step) = if light_theme {
+        (0.45, -0.02)
+    } else {
+        (0.6, 0.02)
+    };
+
+    let mut rgb = hsl_to_rgb(hue, 0.65, lightness);
+    while contrast_ratio(relative_luminance(rgb), surface) < MIN_CONTRAST
+        && (0.0..=1.0).contains(&(lightness + step))
+    {
+        lightness += step;
+        rgb = hsl_to_rgb(hue, 0.65, lightness);
+    }
+
+    let (r, g, b) = rgb;
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn parse_hex(hex: &str) -> (u8, u8, u8) {
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
+        (channel(1), channel(3), channel(5))
+    }
+
+    #[test]
+    fn test_directory_color_is_deterministic() {
+        let first = directory_color("/home/user/project", "dark");
+        assert_eq!(first, directory_color("/home/user/project", "dark"));
+        assert_eq!(first, directory_color("/home/user/project/", "dark"));
+        assert_eq!(first.len(), 7);
+        assert!(first.starts_with('#'));
+    }
+
+    #[test]
+    fn test_different_directories_usually_differ() {
+        let directories = [
+            "/",
+            "/tmp",
+            "/home/user",
+            "/home/user/project",
+            "/home/user/src/atuin",
+            "/var/log",
+            "/etc",
+            "/usr/local/bin",
+            "/opt",
+            "/srv/www",
+        ];
+        let colors: HashSet<String> = directories
+            .iter()
+            .map(|d| directory_color(d, "dark"))
+            .collect();
+        assert!(
+            colors.len() >= 8,
+            "expected mostly distinct colors, got {:?}",
+            colors
+        );
+    }
+
+    #[test]
+    fn test_directory_color_contrasts_with_theme() {
+        let dark = relative_luminance(DARK_SURFACE);
+        let light = relative_luminance(LIGHT_SURFACE);
+
+        for i in 0..200 {
+            let directory = format!("/home/user/project-{}", i);
+            let on_dark = relative_luminance(parse_hex(&directory_color(&directory, "dark")));
+            let on_light = relative_luminance(parse_hex(&directory_color(&directory, "light")));
+            assert!(
+                contrast_ratio(on_dark, dark) >= MIN_CONTRAST,
+                "{} on dark",
+                directory
+            );
+            assert!(
+                contrast_ratio(on_light, light) >= MIN_CONTRAST,
+                "{} on light",
+                directory
+            );
+        }
+    }
+}
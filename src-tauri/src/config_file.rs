@@ -0,0 +1,590 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use toml_edit::{DocumentMut, Item, TableLike};
+
+use crate::atomic_write::write_atomically;
+use crate::{render_config, Config};
+
+/// Format version of config files written by this build. Bump it and add a
+/// step to `migrate_config` when a field is renamed or changes meaning.
+pub const CONFIG_VERSION: u32 = 2;
+
+/// Version assumed for config files without a `version` key, which predate
+/// versioning
+pub fn legacy_config_version() -> u32 {
+    1
+}
+
+/// Upgrade config file contents from an older version to `CONFIG_VERSION`,
+/// changing only the keys a migration step touches and the version, so keys
+/// the file lacks keep following the defaults. Comments and unknown keys are
+/// kept. Returns `None` when the file is already current.
+pub fn migrate_config(contents: &str) -> Result<Option<String>, String> {
+    let mut doc = contents
+        .parse::<DocumentMut>()
+        .map_err(|e| format!("Failed to parse config file: {}", e))?;
+    let version = doc
+        .get("version")
+        .and_then(Item::as_integer)
+        .unwrap_or(legacy_config_version().into());
+    if version >= i64::from(CONFIG_VERSION) {
+        return Ok(None);
+    }
+
+    // 1 -> 2: `allow_execute` was renamed to `allow_run`
+    if version < 2 {
+        if let Some(item) = doc.remove("allow_execute") {
+            if !doc.contains_key("allow_run") {
+                doc.insert("allow_run", item);
+            }
+        }
+    }
+
+    doc.insert("version", toml_edit::value(i64::from(CONFIG_VERSION)));
+    Ok(Some(doc.to_string()))
+}
+
+/// Health of the config file on disk
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub enum ConfigStatus {
+    Ok,
+    /// The file exists but isn't valid; the app is running on defaults
+    ParseError(String),
+    Missing,
+}
+
+/// Check whether the config file at `path` exists and parses
+pub fn config_status(path: &Path) -> ConfigStatus {
+    match fs::read_to_string(path) {
+        Ok(contents) => match toml::from_str::<Config>(&contents) {
+            Ok(_) => ConfigStatus::Ok,
+            Err(e) => ConfigStatus::ParseError(e.to_string()),
+        },
+        Err(e) if e.kind() == io::ErrorKind::NotFound => ConfigStatus::Missing,
+        Err(e) => ConfigStatus::ParseError(format!("Failed to read config file: {}", e)),
+    }
+}
+
+/// Read and parse the config file at `path` without falling back to
+/// defaults. A parse error says at which line and column it was found.
+pub fn validate_config(path: &Path) -> Result<Config, String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read config file {}: {}", path.display(), e))?;
+    toml::from_str(&contents).map_err(|e| match e.span() {
+        Some(span) => {
+            let (line, column) = line_column(&contents, span.start);
+            format!(
+                "Invalid config at line {}, column {}: {}",
+                line,
+                column,
+                e.message()
+            )
+        }
+        None => format!("Invalid config: {}", e.message()),
+    })
+}
+
+/// 1-based line and column of byte `offset` in `contents`
+fn line_column(contents: &str, offset: usize) -> (usize, usize) {
+    let before = contents.get(..offset).unwrap_or(contents);
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rsplit('\n')
+        .next()
+        .map_or(0, |text| text.chars().count())
+        + 1;
+    (line, column)
+}
+
+/// First unused `<name>.bak`, `<name>.bak.1`, ... next to `path`
+fn backup_path(path: &Path) -> PathBuf {
+    let mut backup = sibling_path(path, ".bak");
+    let mut n = 1;
+    while backup.exists() {
+        backup = sibling_path(path, &format!(".bak.{}", n));
+        n += 1;
+    }
+    backup
+}
+
+/// Replace a broken config with defaults, keeping the broken file as a
+/// backup. A missing file is created; a valid one is left alone.
+/// Returns the config now in effect.
+pub fn repair_config(path: &Path) -> Result<Config, String> {
+    match config_status(path) {
+        ConfigStatus::Ok => {
+            let contents =
+                fs::read_to_string(path).map_err(|e| format!("Failed to read config: {}", e))?;
+            toml::from_str(&contents).map_err(|e| format!("Failed to parse config file: {}", e))
+        }
+        status => {
+            if status != ConfigStatus::Missing {
+                let backup = backup_path(path);
+                fs::rename(path, &backup).map_err(|e| {
+                    format!("Failed to back up config to {}: {}", backup.display(), e)
+                })?;
+            } else if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| format!("Failed to create config directory: {}", e))?;
+            }
+
+            let config = Config::default();
+            fs::write(path, render_config(&config))
+                .map_err(|e| format!("Failed to write config: {}", e))?;
+            Ok(config)
+        }
+    }
+}
+
+/// Remove every top-level key whose value equals the default, keeping
+/// comments attached to the remaining keys and any unknown keys untouched.
+/// A key's own leading comment block is removed along with it.
+pub fn prune_defaults(contents: &str) -> Result<String, String> {
+    let mut doc = contents
+        .parse::<DocumentMut>()
+        .map_err(|e| format!("Failed to parse config file: {}", e))?;
+    let current: toml::Table = contents
+        .parse()
+        .map_err(|e| format!("Failed to parse config file: {}", e))?;
+    let defaults = toml::Table::try_from(Config::default())
+        .map_err(|e| format!("Failed to serialize default config: {}", e))?;
+
+    for (key, default_value) in &defaults {
+        // Without its version the file would be migrated again on load
+        if key != "version" && current.get(key) == Some(default_value) {
+            doc.as_table_mut().remove(key);
+        }
+    }
+
+    Ok(doc.to_string())
+}
+
+/// Top-level `Config` fields that are left out of the file when unset, so
+/// their absence from a serialized config means "unset" rather than unknown
+const OPTIONAL_KEYS: &[&str] = &["window_x", "window_y"];
+
+/// Rewrite `contents` so it describes `config`, touching only the keys whose
+/// value changed. Comments, ordering and keys atuin-bar doesn't know about
+/// (including whole tables) are left as they are.
+pub fn merge_config(contents: &str, config: &Config) -> Result<String, String> {
+    let mut doc = contents
+        .parse::<DocumentMut>()
+        .map_err(|e| format!("Failed to parse config file: {}", e))?;
+    let raw: toml::Table = contents
+        .parse()
+        .map_err(|e| format!("Failed to parse config file: {}", e))?;
+    // Keys missing from the file are in effect at their default
+    let mut current = toml::Table::try_from(Config::default())
+        .map_err(|e| format!("Failed to serialize default config: {}", e))?;
+    current.insert("version".to_string(), legacy_config_version().into());
+    current.extend(raw);
+    let updated =
+        toml::Table::try_from(config).map_err(|e| format!("Failed to serialize config: {}", e))?;
+
+    merge_table(doc.as_table_mut(), &current, &updated, false)?;
+    for key in OPTIONAL_KEYS
+        .iter()
+        .filter(|key| !updated.contains_key(**key))
+    {
+        doc.remove(key);
+    }
+    Ok(doc.to_string())
+}
+
+/// Apply the differences between `current` and `updated` to `doc`. Top-level
+/// keys absent from `updated` are unknown to atuin-bar and kept; inside one
+/// of its own tables (e.g. `[size_presets]`) they were removed and are dropped.
+fn merge_table(
+    doc: &mut dyn TableLike,
+    current: &toml::Table,
+    updated: &toml::Table,
+    nested: bool,
+) -> Result<(), String> {
+    for (key, value) in updated {
+        let existing = current.get(key);
+        if existing == Some(value) {
+            continue;
+        }
+
+        if let toml::Value::Table(updated_table) = value {
+            if !doc.get(key).is_some_and(|item| item.is_table_like()) {
+                let mut table = toml_edit::Table::new();
+                table.set_implicit(nested);
+                doc.insert(key, Item::Table(table));
+            }
+            let empty = toml::Table::new();
+            let current_table = existing.and_then(toml::Value::as_table).unwrap_or(&empty);
+            if let Some(table) = doc.get_mut(key).and_then(Item::as_table_like_mut) {
+                merge_table(table, current_table, updated_table, true)?;
+            }
+            continue;
+        }
+
+        let mut new_value = value
+            .to_string()
+            .parse::<toml_edit::Value>()
+            .map_err(|e| format!("Failed to serialize {}: {}", key, e))?;
+        match doc.get_mut(key) {
+            Some(Item::Value(old)) => {
+                *new_value.decor_mut() = old.decor().clone();
+                *old = new_value;
+            }
+            _ => {
+                doc.insert(key, Item::Value(new_value));
+            }
+        }
+    }
+
+    if nested {
+        for key in current.keys().filter(|key| !updated.contains_key(*key)) {
+            doc.remove(key);
+        }
+    }
+
+    Ok(())
+}
+
+/// Write `config` to `path`, editing the existing file in place, or
+/// writing the commented template when there is no file yet. A file that
+/// isn't valid TOML is left alone rather than replaced, so its contents
+/// aren't lost.
+pub fn write_config(path: &Path, config: &Config) -> Result<(), String> {
+    let contents = match fs::read_to_string(path) {
+        Ok(existing) => merge_config(&existing, config)
+            .map_err(|e| format!("{}; fix or remove {} to save settings", e, path.display()))?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => render_config(config),
+        Err(e) => return Err(format!("Failed to read config file: {}", e)),
+    };
+    write_config_file(path, &contents)
+}
+
+/// Replace the config file at `path` with `contents`, keeping the previous
+/// version as `<name>.bak`
+pub fn write_config_file(path: &Path, contents: &str) -> Result<(), String> {
+    replace_file(path, |file| file.write_all(contents.as_bytes()))
+}
+
+/// Path next to `path` with `suffix` appended to its file name
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Replace the file at `path` with what `write` produces, atomically (see
+/// `write_atomically`). The original is first copied to `<name>.bak`.
+fn replace_file(
+    path: &Path,
+    write: impl FnOnce(&mut fs::File) -> io::Result<()>,
+) -> Result<(), String> {
+    if path.exists() {
+        let backup = sibling_path(path, ".bak");
+        fs::copy(path, &backup)
+            .map_err(|e| format!("Failed to back up config to {}: {}", backup.display(), e))?;
+    }
+    write_atomically(path, write).map_err(|e| format!("Failed to write config: {}", e))
+}
+
+/// A config file in a fresh temp directory for tests, removed with the
+/// directory when dropped, even if the test panics
+#[cfg(test)]
+pub(crate) struct TempConfig {
+    pub dir: PathBuf,
+    pub path: PathBuf,
+}
+
+#[cfg(test)]
+impl TempConfig {
+    /// `name` keeps the directories of tests running at once apart
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("atuin-bar-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        TempConfig { dir, path }
+    }
+
+    /// A temp config file holding `contents`
+    pub fn with_contents(name: &str, contents: &str) -> Self {
+        let config = Self::new(name);
+        fs::write(&config.path, contents).unwrap();
+        config
+    }
+}
+
+#[cfg(test)]
+impl Drop for TempConfig {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prune_defaults_keeps_only_non_default_values() {
+        let defaults = Config::default();
+        let contents = format!(
+            r#"# Atuin Bar Configuration
+
+# Global shortcut to toggle the window
+shortcut = "{}"
+
+# Theme: "dark" or "light" (default: "dark")
+theme = "light"
+
+# Maximum number of results to display (default: 20)
+max_results = 20
+
+# Window width in pixels (default: 700)
+window_width = 700
+
+# Custom note kept by the user
+unknown_key = "kept"
+
+[size_presets]
+"#,
+            defaults.shortcut
+        );
+
+        let pruned = prune_defaults(&contents).unwrap();
+        let table: toml::Table = pruned.parse().unwrap();
+        let keys: Vec<&str> = table.keys().map(String::as_str).collect();
+        assert_eq!(keys, vec!["theme", "unknown_key"]);
+
+        assert!(pruned.contains("# Theme: \"dark\" or \"light\""));
+        assert!(pruned.contains("# Custom note kept by the user"));
+        assert!(!pruned.contains("max_results"));
+        assert!(!pruned.contains("# Window width in pixels"));
+    }
+
+    #[test]
+    fn test_merge_config_updates_tables_in_place() {
+        let contents = r#"shortcut = "Alt+Space"
+
+[size_presets]
+# Fits the laptop screen
+tall = { width = 700, height = 900 }
+huge = { width = 1600, height = 1200 }
+"#;
+        let mut config: Config = toml::from_str(contents).unwrap();
+        config.size_presets.remove("huge");
+        config.click_behavior.single = "copy".to_string();
+
+        let merged = merge_config(contents, &config).unwrap();
+        assert!(merged.contains("# Fits the laptop screen\ntall = { width = 700, height = 900 }"));
+        assert!(!merged.contains("huge"));
+
+        let parsed: Config = toml::from_str(&merged).unwrap();
+        assert_eq!(parsed.shortcut, "Alt+Space");
+        assert_eq!(parsed.size_presets, config.size_presets);
+        assert_eq!(parsed.click_behavior.single, "copy");
+    }
+
+    #[test]
+    fn test_merge_config_leaves_unchanged_file_untouched() {
+        let contents = "# keep me\ntheme = \"light\"\nunknown = 1\n";
+        let config: Config = toml::from_str(contents).unwrap();
+        assert_eq!(merge_config(contents, &config).unwrap(), contents);
+    }
+
+    #[test]
+    fn test_prune_defaults_rejects_invalid_toml() {
+        assert!(prune_defaults("theme = ").is_err());
+    }
+
+    #[test]
+    fn test_config_status_detects_parse_error() {
+        let temp = TempConfig::new("config-status");
+        let path = &temp.path;
+
+        assert_eq!(config_status(path), ConfigStatus::Missing);
+
+        fs::write(path, "theme = \"light\"\n").unwrap();
+        assert_eq!(config_status(path), ConfigStatus::Ok);
+
+        fs::write(path, "theme = \n").unwrap();
+        assert!(matches!(config_status(path), ConfigStatus::ParseError(_)));
+
+        fs::write(path, "max_results = \"many\"\n").unwrap();
+        assert!(matches!(config_status(path), ConfigStatus::ParseError(_)));
+    }
+
+    #[test]
+    fn test_migrate_config_upgrades_version_one_file() {
+        let contents = "# My settings\ntheme = \"light\"\nallow_execute = true\nunknown = 1\n";
+
+        let migrated = migrate_config(contents)
+            .unwrap()
+            .expect("version 1 is migrated");
+        assert!(migrated.starts_with("# My settings\ntheme = \"light\"\n"));
+        assert!(migrated.contains("unknown = 1"));
+        assert!(!migrated.contains("allow_execute"));
+
+        let table: toml::Table = migrated.parse().unwrap();
+        assert_eq!(
+            table.get("version"),
+            Some(&toml::Value::Integer(CONFIG_VERSION.into()))
+        );
+        assert_eq!(table.get("allow_run"), Some(&toml::Value::Boolean(true)));
+        // Keys the file lacks aren't written out, so they keep following
+        // the defaults
+        assert_eq!(table.get("window_height"), None);
+        assert_eq!(table.get("search_mode"), None);
+
+        let config: Config = toml::from_str(&migrated).unwrap();
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.theme, "light");
+        assert!(config.allow_run);
+
+        // Migrating is done once
+        assert_eq!(migrate_config(&migrated).unwrap(), None);
+    }
+
+    #[test]
+    fn test_config_without_version_is_version_one() {
+        let config: Config = toml::from_str("theme = \"light\"\n").unwrap();
+        assert_eq!(config.version, 1);
+        assert_eq!(Config::default().version, CONFIG_VERSION);
+
+        let rendered: Config = toml::from_str(&render_config(&Config::default())).unwrap();
+        assert_eq!(rendered.version, CONFIG_VERSION);
+        assert_eq!(
+            migrate_config(&render_config(&Config::default())).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_prune_defaults_keeps_version() {
+        let contents = format!("version = {}\ntheme = \"dark\"\n", CONFIG_VERSION);
+        let pruned = prune_defaults(&contents).unwrap();
+        assert_eq!(pruned, format!("version = {}\n", CONFIG_VERSION));
+    }
+
+    #[test]
+    fn test_validate_config_parses_well_formed_file() {
+        let temp = TempConfig::new("config-validate");
+        let path = &temp.path;
+        fs::write(path, "theme = \"light\"\nmax_results = 30\n").unwrap();
+
+        let config = validate_config(path).unwrap();
+        assert_eq!(config.theme, "light");
+        assert_eq!(config.max_results, 30);
+    }
+
+    #[test]
+    fn test_validate_config_reports_error_position() {
+        let temp = TempConfig::new("config-validate-broken");
+        let (dir, path) = (&temp.dir, &temp.path);
+        fs::write(path, "theme = \"light\"\nmax_results = \"many\"\n").unwrap();
+
+        let err = validate_config(path).unwrap_err();
+        assert!(
+            err.starts_with("Invalid config at line 2, column 15:"),
+            "got: {}",
+            err
+        );
+
+        fs::write(path, "theme = \"light\"\n\nwindow_width = [broken\n").unwrap();
+        let err = validate_config(path).unwrap_err();
+        assert!(err.contains("at line 3"), "got: {}", err);
+
+        assert!(validate_config(&dir.join("missing.toml")).is_err());
+    }
+
+    #[test]
+    fn test_repair_config_backs_up_broken_file_and_restores_defaults() {
+        let temp = TempConfig::new("config-repair");
+        let (dir, path) = (&temp.dir, &temp.path);
+        fs::write(path, "theme = [broken\n").unwrap();
+
+        let config = repair_config(path).unwrap();
+        assert_eq!(config.theme, Config::default().theme);
+        assert_eq!(config_status(path), ConfigStatus::Ok);
+
+        let backup = dir.join("config.toml.bak");
+        assert_eq!(fs::read_to_string(&backup).unwrap(), "theme = [broken\n");
+
+        // A second repair doesn't clobber the first backup
+        fs::write(path, "theme = \n").unwrap();
+        repair_config(path).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join("config.toml.bak.1")).unwrap(),
+            "theme = \n"
+        );
+        assert_eq!(fs::read_to_string(&backup).unwrap(), "theme = [broken\n");
+    }
+
+    #[test]
+    fn test_write_config_file_keeps_backup_of_previous_contents() {
+        let temp = TempConfig::new("config-write");
+        let (dir, path) = (&temp.dir, &temp.path);
+
+        write_config_file(path, "theme = \"light\"\n").unwrap();
+        assert!(
+            !dir.join("config.toml.bak").exists(),
+            "nothing to back up yet"
+        );
+
+        write_config_file(path, "theme = \"dark\"\n").unwrap();
+        assert_eq!(fs::read_to_string(path).unwrap(), "theme = \"dark\"\n");
+        assert_eq!(
+            fs::read_to_string(dir.join("config.toml.bak")).unwrap(),
+            "theme = \"light\"\n"
+        );
+        assert!(!dir.join("config.toml.tmp").exists());
+    }
+
+    #[test]
+    fn test_write_config_refuses_to_replace_invalid_file() {
+        let temp =
+            TempConfig::with_contents("config-write-invalid", "theme = \"dark\n# my notes\n");
+        let path = &temp.path;
+
+        let err = write_config(path, &Config::default()).unwrap_err();
+        assert!(err.contains("Failed to parse config file"), "got: {}", err);
+        assert_eq!(
+            fs::read_to_string(path).unwrap(),
+            "theme = \"dark\n# my notes\n"
+        );
+        assert!(!temp.dir.join("config.toml.bak").exists());
+    }
+
+    #[test]
+    fn test_failed_write_leaves_original_intact() {
+        let temp = TempConfig::new("config-write-failed");
+        let (dir, path) = (&temp.dir, &temp.path);
+        fs::write(path, "theme = \"light\"\n").unwrap();
+
+        // Dies halfway through writing the new contents
+        let result = replace_file(path, |file| {
+            file.write_all(b"theme = \"da")?;
+            Err(io::Error::other("disk full"))
+        });
+        assert!(result.unwrap_err().contains("disk full"));
+
+        assert_eq!(fs::read_to_string(path).unwrap(), "theme = \"light\"\n");
+        assert_eq!(
+            fs::read_to_string(dir.join("config.toml.bak")).unwrap(),
+            "theme = \"light\"\n"
+        );
+        assert!(!dir.join("config.toml.tmp").exists());
+    }
+
+    #[test]
+    fn test_repair_config_leaves_valid_config_alone() {
+        let temp = TempConfig::new("config-repair-valid");
+        let (dir, path) = (&temp.dir, &temp.path);
+        fs::write(path, "theme = \"light\"\n").unwrap();
+
+        let config = repair_config(path).unwrap();
+        assert_eq!(config.theme, "light");
+        assert_eq!(fs::read_to_string(path).unwrap(), "theme = \"light\"\n");
+        assert!(!dir.join("config.toml.bak").exists());
+    }
+}
@@ -0,0 +1,264 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use notify::{RecursiveMode, Watcher};
+use tauri::{Emitter, Manager};
+use tauri_plugin_global_shortcut::GlobalShortcutExt;
+
+use crate::keybindings::{self, ActiveShortcut, ShortcutRegistrar};
+use crate::{apply_env_overrides, coerce_unknown_theme, Config};
+
+/// Event emitted with the new `Config` after config.toml changed on disk
+pub const CONFIG_RELOADED_EVENT: &str = "config-reloaded";
+
+/// How long the config file must stay unchanged before it is reloaded.
+/// Editors often write a file more than once when saving.
+const RELOAD_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Tracks the last good config and applies edits to it
+pub struct ConfigReloader {
+    current: Config,
+}
+
+impl ConfigReloader {
+    pub fn new(current: Config) -> Self {
+        Self { current }
+    }
+
+    /// Parse `contents`, with the `ATUIN_BAR_*` overrides looked up with
+    /// `var`, and re-register the global shortcuts that changed. On error
+    /// the previous config (and shortcuts) stay in effect.
+    pub fn reload(
+        &mut self,
+        registrar: &mut impl ShortcutRegistrar,
+        contents: &str,
+        var: impl Fn(&str) -> Option<String>,
+    ) -> Result<&Config, String> {
+        let mut config: Config =
+            toml::from_str(contents).map_err(|e| format!("Failed to parse config file: {}", e))?;
+        apply_env_overrides(&mut config, var);
+        coerce_unknown_theme(&mut config);
+
+        // The shortcut may already be live, e.g. when the settings window
+        // changed it and then saved the file
+        if !registrar.is_registered(&config.shortcut) {
+            keybindings::swap_shortcut(
+                registrar,
+                &self.current.shortcut,
+                &config.shortcut,
+                |_| Ok(()),
+            )?;
+        }
+        // A bad extra shortcut is skipped rather than failing the reload
+        for e in keybindings::sync_extra_shortcuts(registrar, &self.current, &config) {
+            eprintln!("{}", e);
+        }
+
+        self.current = config;
+        Ok(&self.current)
+    }
+}
+
+/// Wait until no event has arrived on `events` for `quiet`, absorbing a
+/// burst of changes. Returns false once the sender is gone.
+fn wait_for_quiet<T>(events: &Receiver<T>, quiet: Duration) -> bool {
+    loop {
+        match events.recv_timeout(quiet) {
+            Ok(_) => continue,
+            Err(RecvTimeoutError::Timeout) => return true,
+            Err(RecvTimeoutError::Disconnected) => return false,
+        }
+    }
+}
+
+/// Reload the config at `path` whenever it changes, re-registering the
+/// global shortcut and emitting `CONFIG_RELOADED_EVENT`
+pub fn watch_config<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    path: PathBuf,
+    current: Config,
+) -> Result<(), String> {
+    // Watch the directory: editors often save by replacing the file, which
+    // would end a watch on the file itself
+    let dir = path
+        .parent()
+        .ok_or_else(|| "Config path has no parent directory".to_string())?
+        .to_path_buf();
+    let file_name = path.file_name().map(|name| name.to_os_string());
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if let Ok(event) = event {
+            if event
+                .paths
+                .iter()
+                .any(|p| p.file_name() == file_name.as_deref())
+            {
+                let _ = tx.send(());
+            }
+        }
+    })
+    .map_err(|e| format!("Failed to watch config file: {}", e))?;
+    watcher
+        .watch(&dir, RecursiveMode::NonRecursive)
+        .map_err(|e| format!("Failed to watch config file: {}", e))?;
+
+    std::thread::spawn(move || {
+        // Keep the watcher alive for as long as events are handled
+        let _watcher = watcher;
+        let mut reloader = ConfigReloader::new(current);
+
+        while rx.recv().is_ok() {
+            if !wait_for_quiet(&rx, RELOAD_DEBOUNCE) {
+                break;
+            }
+
+            let contents = match fs::read_to_string(&path) {
+                Ok(contents) => contents,
+                Err(e) => {
+                    eprintln!("Failed to read config file: {}", e);
+                    continue;
+                }
+            };
+            let mut registrar = app.global_shortcut();
+            match reloader.reload(&mut registrar, &contents, |name| std::env::var(name).ok()) {
+                Ok(config) => {
+                    if let Ok(mut active) = app.state::<Mutex<ActiveShortcut>>().lock() {
+                        active.0 = config.shortcut.clone();
+                    }
+                    if let Err(e) = app.emit(CONFIG_RELOADED_EVENT, config) {
+                        eprintln!("Failed to emit {}: {}", CONFIG_RELOADED_EVENT, e);
+                    }
+                }
+                Err(e) => eprintln!("Keeping previous config: {}", e),
+            }
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct MockRegistrar {
+        registered: Vec<String>,
+    }
+
+    impl ShortcutRegistrar for MockRegistrar {
+        fn register(&mut self, accelerator: &str) -> Result<(), String> {
+            self.registered.push(accelerator.to_string());
+            Ok(())
+        }
+
+        fn unregister(&mut self, accelerator: &str) -> Result<(), String> {
+            self.registered.retain(|a| a != accelerator);
+            Ok(())
+        }
+
+        fn is_registered(&self, accelerator: &str) -> bool {
+            self.registered.iter().any(|a| a == accelerator)
+        }
+    }
+
+    fn reloader(shortcut: &str) -> (ConfigReloader, MockRegistrar) {
+        let config = Config {
+            shortcut: shortcut.to_string(),
+            ..Default::default()
+        };
+        let registrar = MockRegistrar {
+            registered: vec![shortcut.to_string()],
+        };
+        (ConfigReloader::new(config), registrar)
+    }
+
+    /// No `ATUIN_BAR_*` variables set
+    fn no_env(_: &str) -> Option<String> {
+        None
+    }
+
+    #[test]
+    fn test_reload_swaps_changed_shortcut() {
+        let (mut reloader, mut registrar) = reloader("Alt+Space");
+
+        let config = reloader
+            .reload(
+                &mut registrar,
+                "shortcut = \"Control+Shift+K\"\ntheme = \"light\"\n",
+                no_env,
+            )
+            .unwrap();
+        assert_eq!(config.theme, "light");
+        assert_eq!(registrar.registered, vec!["Control+Shift+K"]);
+    }
+
+    #[test]
+    fn test_reload_keeps_already_registered_shortcut() {
+        let (mut reloader, mut registrar) = reloader("Alt+Space");
+        registrar.registered.push("Control+Shift+K".to_string());
+
+        reloader
+            .reload(&mut registrar, "shortcut = \"Control+Shift+K\"\n", no_env)
+            .unwrap();
+        assert_eq!(registrar.registered, vec!["Alt+Space", "Control+Shift+K"]);
+    }
+
+    #[test]
+    fn test_reload_syncs_extra_shortcuts() {
+        let (mut reloader, mut registrar) = reloader("Alt+Space");
+
+        reloader
+            .reload(
+                &mut registrar,
+                "shortcut = \"Alt+Space\"\nshortcuts = [\"Super+H\", \"Control+Shift+K\"]\n",
+                no_env,
+            )
+            .unwrap();
+        assert_eq!(
+            registrar.registered,
+            vec!["Alt+Space", "Super+H", "Control+Shift+K"]
+        );
+
+        reloader
+            .reload(
+                &mut registrar,
+                "shortcut = \"Alt+Space\"\nshortcuts = [\"Control+Shift+K\"]\n",
+                no_env,
+            )
+            .unwrap();
+        assert_eq!(registrar.registered, vec!["Alt+Space", "Control+Shift+K"]);
+    }
+
+    #[test]
+    fn test_reload_keeps_previous_config_on_invalid_edit() {
+        let (mut reloader, mut registrar) = reloader("Alt+Space");
+
+        assert!(reloader
+            .reload(&mut registrar, "shortcut = \n", no_env)
+            .is_err());
+        assert!(reloader
+            .reload(&mut registrar, "shortcut = \"Nope+Nope\"\n", no_env)
+            .is_err());
+        assert_eq!(registrar.registered, vec!["Alt+Space"]);
+        assert_eq!(reloader.current.shortcut, "Alt+Space");
+    }
+
+    #[test]
+    fn test_wait_for_quiet_absorbs_bursts() {
+        let (tx, rx) = mpsc::channel();
+        for _ in 0..3 {
+            tx.send(()).unwrap();
+        }
+
+        assert!(wait_for_quiet(&rx, Duration::from_millis(10)));
+        assert!(rx.try_recv().is_err());
+
+        drop(tx);
+        assert!(!wait_for_quiet(&rx, Duration::from_millis(10)));
+    }
+}
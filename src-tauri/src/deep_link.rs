@@ -0,0 +1,144 @@
+use crate::{normalize_filters, SearchFilters};
+
+/// URL scheme other tools can use to deep-link into a search
+pub const DEEP_LINK_SCHEME: &str = "atuin-bar";
+
+/// Event emitted to the main window when a deep link requests a search
+pub const DEEP_LINK_SEARCH_EVENT: &str = "deep-link-search";
+
+/// A search requested through an `atuin-bar://search?...` URL
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct DeepLinkSearch {
+    /// Query to pre-fill in the search input
+    pub query: String,
+    /// Filters to apply before running the search
+    pub filters: SearchFilters,
+}
+
+/// A deep link the app was launched with. It arrives before the frontend
+/// listens for `DEEP_LINK_SEARCH_EVENT`, so it's kept for the frontend to
+/// take once it's ready.
+#[derive(Debug, Default)]
+pub struct PendingDeepLink(Option<DeepLinkSearch>);
+
+impl PendingDeepLink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep `search` until it's taken, replacing any earlier one
+    pub fn set(&mut self, search: DeepLinkSearch) {
+        self.0 = Some(search);
+    }
+
+    /// The pending search, if any; it's only handed out once
+    pub fn take(&mut self) -> Option<DeepLinkSearch> {
+        self.0.take()
+    }
+}
+
+/// Parse a deep link such as `atuin-bar://search?q=git%20push&dir=~/src`.
+///
+/// Supported parameters are `q` (query), `dir` (directory), `exit`
+/// (exit filter), `time` (start of the time range) and `before` (its end).
+/// Values are URL-decoded and the resulting filters are normalized; unknown
+/// parameters are ignored.
+pub fn parse_deep_link(url: &str) -> Result<DeepLinkSearch, String> {
+    let url = tauri::Url::parse(url).map_err(|e| format!("Invalid deep link URL: {}", e))?;
+
+    if url.scheme() != DEEP_LINK_SCHEME {
+        return Err(format!(
+            "Unsupported deep link scheme '{}': expected '{}'",
+            url.scheme(),
+            DEEP_LINK_SCHEME
+        ));
+    }
+
+    // Accept both `atuin-bar://search?...` and `atuin-bar:search?...`
+    let action = url
+        .host_str()
+        .unwrap_or_else(|| url.path().trim_matches('/'));
+    if action != "search" {
+        return Err(format!("Unsupported deep link action '{}'", action));
+    }
+
+    let mut query = String::new();
+    let mut filters = SearchFilters::default();
+    for (key, value) in url.query_pairs() {
+        match key.as_ref() {
+            "q" => query = value.into_owned(),
+            "dir" => filters.directory = Some(value.into_owned()),
+            "exit" => filters.exit_filter = Some(value.into_owned()),
+            "time" => filters.time_range = Some(value.into_owned()),
+            "before" => filters.before = Some(value.into_owned()),
+            _ => {}
+        }
+    }
+
+    Ok(DeepLinkSearch {
+        query: query.trim().to_string(),
+        filters: normalize_filters(filters)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_deep_link_query_and_filters() {
+        let search = parse_deep_link(
+            "atuin-bar://search?q=git%20push&dir=%2Fhome%2Fuser&exit=Success&time=7d",
+        )
+        .expect("deep link should parse");
+
+        assert_eq!(search.query, "git push");
+        assert_eq!(
+            search.filters,
+            SearchFilters {
+                directory: Some("/home/user".to_string()),
+                exit_filter: Some("success".to_string()),
+                time_range: Some("7d".to_string()),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_deep_link_query_only() {
+        let search = parse_deep_link("atuin-bar://search?q=cargo+build").unwrap();
+        assert_eq!(search.query, "cargo build");
+        assert_eq!(search.filters, SearchFilters::default());
+    }
+
+    #[test]
+    fn test_parse_deep_link_custom_time_range() {
+        let search =
+            parse_deep_link("atuin-bar://search?q=deploy&time=2024-01-01&before=2024-02-01")
+                .unwrap();
+        assert_eq!(search.filters.time_range.as_deref(), Some("2024-01-01"));
+        assert_eq!(search.filters.before.as_deref(), Some("2024-02-01"));
+    }
+
+    #[test]
+    fn test_pending_deep_link_is_taken_once() {
+        let mut pending = PendingDeepLink::new();
+        assert_eq!(pending.take(), None);
+
+        pending.set(parse_deep_link("atuin-bar://search?q=ls").unwrap());
+        pending.set(parse_deep_link("atuin-bar://search?q=git").unwrap());
+        assert_eq!(
+            pending.take().map(|search| search.query).as_deref(),
+            Some("git")
+        );
+        assert_eq!(pending.take(), None);
+    }
+
+    #[test]
+    fn test_parse_deep_link_rejects_invalid_urls() {
+        assert!(parse_deep_link("https://search?q=ls").is_err());
+        assert!(parse_deep_link("atuin-bar://settings").is_err());
+        assert!(parse_deep_link("atuin-bar://search?q=ls&exit=sometimes").is_err());
+        assert!(parse_deep_link("not a url").is_err());
+    }
+}
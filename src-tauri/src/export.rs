@@ -0,0 +1,123 @@
+use std::io::Write;
+use std::path::Path;
+
+use crate::atomic_write::write_atomically;
+use crate::results::HistoryEntry;
+
+/// Formats `export_results` can write
+pub const EXPORT_FORMATS: [&str; 3] = ["json", "csv", "txt"];
+
+const CSV_HEADER: &str = "command,exit,duration,directory,time";
+
+/// Render `entries` in `format`: a JSON array of entries, CSV with one row
+/// per entry, or plain text with one command per line
+pub fn render_export(entries: &[HistoryEntry], format: &str) -> Result<String, String> {
+    match format {
+        "json" => serde_json::to_string_pretty(entries)
+            .map_err(|e| format!("Failed to serialize results: {}", e)),
+        "csv" => {
+            let mut csv = String::from(CSV_HEADER);
+            csv.push('\n');
+            for entry in entries {
+                let row = [
+                    csv_field(&entry.command),
+                    entry.exit.to_string(),
+                    csv_field(&entry.duration),
+                    csv_field(&entry.directory),
+                    csv_field(&entry.time),
+                ];
+                csv.push_str(&row.join(","));
+                csv.push('\n');
+            }
+            Ok(csv)
+        }
+        "txt" => Ok(entries
+            .iter()
+            .map(|entry| format!("{}\n", entry.command))
+            .collect()),
+        other => Err(format!(
+            "Unknown export format '{}' (expected one of: {})",
+            other,
+            EXPORT_FORMATS.join(", ")
+        )),
+    }
+}
+
+/// Quote a CSV field if it contains a separator, quote or line break,
+/// doubling any quotes inside it
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Write `entries` to `path` in `format`. The file is replaced atomically so
+/// an interrupted export never leaves a truncated file.
+pub fn export_results(entries: &[HistoryEntry], path: &Path, format: &str) -> Result<(), String> {
+    let contents = render_export(entries, format)?;
+    write_atomically(path, |file| file.write_all(contents.as_bytes()))
+        .map_err(|e| format!("Failed to write export to {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::parse_atuin_line;
+    use std::fs;
+
+    fn entries() -> Vec<HistoryEntry> {
+        vec![
+            parse_atuin_line("git status|0|12ms|/repo|2024-01-01 10:00:00").unwrap(),
+            parse_atuin_line("echo \"a, b\"|1|3ms|/tmp|2024-01-01 10:01:00").unwrap(),
+        ]
+    }
+
+    #[test]
+    fn test_json_export_is_array_of_entries() {
+        let json = render_export(&entries(), "json").unwrap();
+        let parsed: Vec<HistoryEntry> = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].command, "git status");
+        assert_eq!(parsed[1].exit, 1);
+    }
+
+    #[test]
+    fn test_csv_export_quotes_commands_with_commas() {
+        let csv = render_export(&entries(), "csv").unwrap();
+        assert_eq!(
+            csv,
+            "command,exit,duration,directory,time\n\
+             git status,0,12ms,/repo,2024-01-01 10:00:00\n\
+             \"echo \"\"a, b\"\"\",1,3ms,/tmp,2024-01-01 10:01:00\n"
+        );
+    }
+
+    #[test]
+    fn test_txt_export_lists_commands_only() {
+        let txt = render_export(&entries(), "txt").unwrap();
+        assert_eq!(txt, "git status\necho \"a, b\"\n");
+    }
+
+    #[test]
+    fn test_unknown_format_is_rejected_without_writing() {
+        let path =
+            std::env::temp_dir().join(format!("atuin-bar-export-{}.xml", std::process::id()));
+        let err = export_results(&entries(), &path, "xml").unwrap_err();
+        assert!(err.contains("Unknown export format 'xml'"));
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_export_writes_file() {
+        let path =
+            std::env::temp_dir().join(format!("atuin-bar-export-{}.txt", std::process::id()));
+        export_results(&entries(), &path, "txt").unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "git status\necho \"a, b\"\n"
+        );
+        let _ = fs::remove_file(&path);
+    }
+}
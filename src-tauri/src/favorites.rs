@@ -0,0 +1,190 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::app_data::get_data_dir;
+use crate::results::{HistoryEntry, MatchSource};
+
+/// Persisted favorite commands file
+pub fn get_favorites_path() -> Option<PathBuf> {
+    get_data_dir().map(|p| p.join("favorites.json"))
+}
+
+/// Commands pinned above the search results, in the order they were added
+#[derive(Debug, Default, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct Favorites {
+    commands: Vec<String>,
+}
+
+impl Favorites {
+    /// Load favorites from a file, starting empty if it is missing or invalid
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        let json = serde_json::to_string(self)
+            .map_err(|e| format!("Failed to serialize favorites: {}", e))?;
+        fs::write(path, json).map_err(|e| format!("Failed to write favorites: {}", e))
+    }
+
+    /// Add `command` unless it already is a favorite. Returns whether it was
+    /// added.
+    pub fn add(&mut self, command: &str) -> Result<bool, String> {
+        if command.trim().is_empty() {
+            return Err("Favorite command cannot be empty".to_string());
+        }
+        if self.commands.iter().any(|favorite| favorite == command) {
+            return Ok(false);
+        }
+        self.commands.push(command.to_string());
+        Ok(true)
+    }
+
+    /// Remove `command` if it is a favorite. Returns whether it was removed.
+    pub fn remove(&mut self, command: &str) -> bool {
+        let before = self.commands.len();
+        self.commands.retain(|favorite| favorite != command);
+        self.commands.len() != before
+    }
+
+    pub fn commands(&self) -> &[String] {
+        &self.commands
+    }
+
+    /// Favorites containing `query`, ignoring case; an empty query matches
+    /// every favorite
+    pub fn matching(&self, query: &str) -> Vec<&str> {
+        let query = query.trim().to_lowercase();
+        self.commands
+            .iter()
+            .filter(|command| command.to_lowercase().contains(&query))
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+/// Pin `favorites` above `entries`, flagged with `is_favorite`. Entries are
+/// oldest first, like atuin output, and displayed in reverse, so favorites
+/// are added at the end in reverse order to show on top in their own order.
+/// A favorite that is also in `entries` is moved up (keeping its most recent
+/// run) rather than listed twice; one that isn't gets an entry with no
+/// metadata.
+pub fn append_favorites(entries: Vec<HistoryEntry>, favorites: &[&str]) -> Vec<HistoryEntry> {
+    let mut pinned: Vec<HistoryEntry> = favorites
+        .iter()
+        .map(|command| favorite_entry(command))
+        .collect();
+    let mut rest = Vec::with_capacity(entries.len());
+
+    for entry in entries {
+        match favorites
+            .iter()
+            .position(|favorite| *favorite == entry.command)
+        {
+            // atuin lists newer runs later, so the last one seen wins
+            Some(index) => {
+                pinned[index] = HistoryEntry {
+                    is_favorite: true,
+                    ..entry
+                }
+            }
+            None => rest.push(entry),
+        }
+    }
+
+    rest.extend(pinned.into_iter().rev());
+    rest
+}
+
+/// Entry for a favorite that wasn't found in history
+fn favorite_entry(command: &str) -> HistoryEntry {
+    HistoryEntry {
+        command: command.to_string(),
+        exit: -1,
+        exit_info: None,
+        duration: String::new(),
+        directory: String::new(),
+        time: String::new(),
+        match_ranges: Vec::new(),
+        match_source: MatchSource::Local,
+        is_new: false,
+        is_favorite: true,
+        relative_time: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::parse_atuin_line;
+
+    fn entry(command: &str, time: &str) -> HistoryEntry {
+        parse_atuin_line(&format!("{}|0|1ms|/repo|{}", command, time)).unwrap()
+    }
+
+    #[test]
+    fn test_add_and_remove_are_idempotent() {
+        let mut favorites = Favorites::default();
+        assert!(favorites.add("git status").unwrap());
+        assert!(!favorites.add("git status").unwrap());
+        assert!(favorites.add("cargo test").unwrap());
+        assert_eq!(favorites.commands(), ["git status", "cargo test"]);
+
+        assert!(favorites.remove("git status"));
+        assert!(!favorites.remove("git status"));
+        assert_eq!(favorites.commands(), ["cargo test"]);
+
+        assert!(favorites.add("  ").is_err());
+    }
+
+    #[test]
+    fn test_matching_ignores_case() {
+        let mut favorites = Favorites::default();
+        favorites.add("git status").unwrap();
+        favorites.add("cargo test").unwrap();
+
+        assert_eq!(favorites.matching("GIT"), vec!["git status"]);
+        assert_eq!(favorites.matching(""), vec!["git status", "cargo test"]);
+        assert!(favorites.matching("docker").is_empty());
+    }
+
+    #[test]
+    fn test_append_favorites_without_duplicating_history() {
+        let entries = vec![
+            entry("git status", "2024-01-01 10:00:00"),
+            entry("ls", "2024-01-01 10:01:00"),
+            entry("git status", "2024-01-01 10:02:00"),
+        ];
+
+        let merged = append_favorites(entries, &["cargo test", "git status"]);
+        // Displayed in reverse: the favorites on top, in their own order
+        let displayed: Vec<&HistoryEntry> = merged.iter().rev().collect();
+        let commands: Vec<&str> = displayed.iter().map(|e| e.command.as_str()).collect();
+        assert_eq!(commands, vec!["cargo test", "git status", "ls"]);
+
+        assert!(displayed[0].is_favorite);
+        assert_eq!(displayed[0].time, "");
+        assert!(displayed[1].is_favorite);
+        assert_eq!(displayed[1].time, "2024-01-01 10:02:00");
+        assert!(!displayed[2].is_favorite);
+    }
+
+    #[test]
+    fn test_favorites_round_trip_through_file() {
+        let path =
+            std::env::temp_dir().join(format!("atuin-bar-favorites-{}.json", std::process::id()));
+        let mut favorites = Favorites::default();
+        favorites.add("git status").unwrap();
+        favorites.save(&path).unwrap();
+
+        assert_eq!(Favorites::load(&path), favorites);
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::HashSet;
+
+/// Label of the search window
+pub const MAIN_WINDOW: &str = "main";
+
+/// Tracks which of the app's own windows currently have focus, so losing
+/// focus from the main window to e.g. the settings window can be told apart
+/// from focus leaving the application.
+#[derive(Debug, Default)]
+pub struct FocusTracker {
+    focused: HashSet<String>,
+    /// Keeps the main window open when it loses focus, until unpinned
+    pinned: bool,
+}
+
+impl FocusTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a focus change for a window
+    pub fn set_focused(&mut self, label: &str, focused: bool) {
+        if focused {
+            self.focused.insert(label.to_string());
+        } else {
+            self.focused.remove(label);
+        }
+    }
+
+    /// Forget a window that was closed
+    pub fn remove(&mut self, label: &str) {
+        self.focused.remove(label);
+    }
+
+    /// Whether any app window has focus
+    pub fn app_has_focus(&self) -> bool {
+        !self.focused.is_empty()
+    }
+
+    /// Pin or unpin the main window
+    pub fn set_pinned(&mut self, pinned: bool) {
+        self.pinned = pinned;
+    }
+
+    /// Whether the main window should hide after it lost focus.
+    ///
+    /// It never hides while pinned or when `hide_on_blur` is off. Otherwise,
+    /// with the "window" scope it always hides; with "app" it only hides
+    /// once focus has left all of the app's windows.
+    pub fn should_hide_main(&self, scope: &str, hide_on_blur: bool) -> bool {
+        if !hide_on_blur || self.pinned || self.focused.contains(MAIN_WINDOW) {
+            return false;
+        }
+        scope == "window" || !self.app_has_focus()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_focus_moving_to_settings_does_not_hide_main() {
+        let mut tracker = FocusTracker::new();
+        tracker.set_focused(MAIN_WINDOW, true);
+
+        tracker.set_focused(MAIN_WINDOW, false);
+        tracker.set_focused("settings", true);
+
+        assert!(!tracker.should_hide_main("app", true));
+    }
+
+    #[test]
+    fn test_focus_leaving_the_app_hides_main() {
+        let mut tracker = FocusTracker::new();
+        tracker.set_focused(MAIN_WINDOW, true);
+        tracker.set_focused(MAIN_WINDOW, false);
+
+        assert!(tracker.should_hide_main("app", true));
+    }
+
+    #[test]
+    fn test_closing_settings_counts_as_focus_leaving() {
+        let mut tracker = FocusTracker::new();
+        tracker.set_focused("settings", true);
+        tracker.remove("settings");
+
+        assert!(tracker.should_hide_main("app", true));
+    }
+
+    #[test]
+    fn test_window_scope_hides_on_any_focus_loss() {
+        let mut tracker = FocusTracker::new();
+        tracker.set_focused("settings", true);
+
+        assert!(tracker.should_hide_main("window", true));
+    }
+
+    #[test]
+    fn test_main_regaining_focus_keeps_it_visible() {
+        let mut tracker = FocusTracker::new();
+        tracker.set_focused(MAIN_WINDOW, false);
+        tracker.set_focused(MAIN_WINDOW, true);
+
+        assert!(!tracker.should_hide_main("window", true));
+    }
+
+    #[test]
+    fn test_pinned_main_stays_visible() {
+        let mut tracker = FocusTracker::new();
+        tracker.set_pinned(true);
+        tracker.set_focused(MAIN_WINDOW, false);
+
+        assert!(!tracker.should_hide_main("app", true));
+        assert!(!tracker.should_hide_main("window", true));
+
+        tracker.set_pinned(false);
+        assert!(tracker.should_hide_main("app", true));
+    }
+
+    #[test]
+    fn test_hide_on_blur_disabled_keeps_main_visible() {
+        let mut tracker = FocusTracker::new();
+        tracker.set_focused(MAIN_WINDOW, false);
+
+        assert!(!tracker.should_hide_main("app", false));
+        assert!(!tracker.should_hide_main("window", false));
+    }
+}
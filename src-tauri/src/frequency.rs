@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::app_data::get_data_dir;
+use crate::results::HistoryEntry;
+
+/// Weight of the selection-frequency signal relative to recency (0..1)
+const FREQUENCY_WEIGHT: f64 = 0.7;
+
+/// Persisted selection counts file
+pub fn get_selection_counts_path() -> Option<PathBuf> {
+    get_data_dir().map(|p| p.join("selection_counts.json"))
+}
+
+/// How often each command was selected (copied or run) from the bar
+#[derive(Debug, Default, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SelectionCounts {
+    counts: HashMap<String, u32>,
+}
+
+impl SelectionCounts {
+    /// Load counts from a file, starting empty if it is missing or invalid
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        let json = serde_json::to_string(self)
+            .map_err(|e| format!("Failed to serialize selection counts: {}", e))?;
+        fs::write(path, json).map_err(|e| format!("Failed to write selection counts: {}", e))
+    }
+
+    pub fn record(&mut self, command: &str) {
+        *self.counts.entry(command.to_string()).or_default() += 1;
+    }
+
+    pub fn count(&self, command: &str) -> u32 {
+        self.counts.get(command).copied().unwrap_or(0)
+    }
+}
+
+/// Rank distinct commands for autocomplete by blending recency with how
+/// often each command has been selected.
+///
+/// `entries` are in atuin's output order (oldest first). Recency is scored
+/// linearly from 0 (oldest) to 1 (newest); frequency is log-scaled and
+/// normalized against the most-selected candidate.
+pub fn rank_suggestions(
+    entries: &[HistoryEntry],
+    counts: &SelectionCounts,
+    limit: usize,
+) -> Vec<String> {
+    // Keep the most recent position for each distinct command
+    let mut latest: HashMap<&str, usize> = HashMap::new();
+    for (position, entry) in entries.iter().enumerate() {
+        latest.insert(entry.command.as_str(), position);
+    }
+
+    let newest = entries.len().saturating_sub(1).max(1) as f64;
+    let max_frequency = latest
+        .keys()
+        .map(|command| f64::from(counts.count(command)).ln_1p())
+        .fold(0.0, f64::max);
+
+    let mut scored: Vec<(&str, f64, usize)> = latest
+        .into_iter()
+        .map(|(command, position)| {
+            let recency = position as f64 / newest;
+            let frequency = if max_frequency > 0.0 {
+                f64::from(counts.count(command)).ln_1p() / max_frequency
+            } else {
+                0.0
+            };
+            let score = (1.0 - FREQUENCY_WEIGHT) * recency + FREQUENCY_WEIGHT * frequency;
+            (command, score, position)
+        })
+        .collect();
+
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1).then(b.2.cmp(&a.2)));
+    scored
+        .into_iter()
+        .take(limit)
+        .map(|(command, _, _)| command.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::parse_atuin_line;
+
+    fn entries(commands: &[&str]) -> Vec<HistoryEntry> {
+        commands
+            .iter()
+            .map(|c| parse_atuin_line(&format!("{}|0|1ms|/tmp|2024-01-01 10:00:00", c)).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_without_selections_newest_ranks_first() {
+        let history = entries(&["git status", "git stash", "git switch"]);
+        let ranked = rank_suggestions(&history, &SelectionCounts::default(), 10);
+        assert_eq!(ranked, vec!["git switch", "git stash", "git status"]);
+    }
+
+    #[test]
+    fn test_frequent_selection_outranks_more_recent_command() {
+        let history = entries(&["git status", "git stash", "git switch"]);
+        let mut counts = SelectionCounts::default();
+        for _ in 0..10 {
+            counts.record("git status");
+        }
+        counts.record("git switch");
+
+        let ranked = rank_suggestions(&history, &counts, 10);
+        assert_eq!(ranked[0], "git status");
+    }
+
+    #[test]
+    fn test_duplicates_are_collapsed_and_limited() {
+        let history = entries(&["ls", "pwd", "ls", "cd /tmp"]);
+        let ranked = rank_suggestions(&history, &SelectionCounts::default(), 2);
+        assert_eq!(ranked, vec!["cd /tmp", "ls"]);
+    }
+
+    #[test]
+    fn test_selection_counts_round_trip() {
+        let path = std::env::temp_dir().join(format!(
+            "atuin-bar-selection-counts-{}.json",
+            std::process::id()
+        ));
+        let mut counts = SelectionCounts::default();
+        counts.record("make test");
+        counts.record("make test");
+        counts.save(&path).unwrap();
+
+        let loaded = SelectionCounts::load(&path);
+        assert_eq!(loaded.count("make test"), 2);
+        assert_eq!(loaded.count("unknown"), 0);
+
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,616 @@
+use std::str::FromStr;
+
+use tauri_plugin_global_shortcut::{GlobalShortcut, Shortcut};
+
+use crate::Config;
+
+/// Where a keybinding is active
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeybindingScope {
+    /// Registered system-wide through the global shortcut plugin
+    Global,
+    /// Handled by the search window while it has focus
+    Window,
+}
+
+/// A single keybinding shown in the settings cheatsheet
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct KeybindingInfo {
+    /// Accelerator string, e.g. "CommandOrControl+Shift+Space"
+    pub accelerator: String,
+    /// Stable action identifier, e.g. "toggle_window"
+    pub action: String,
+    /// Human-readable description
+    pub description: String,
+    pub scope: KeybindingScope,
+}
+
+impl KeybindingInfo {
+    fn new(accelerator: &str, action: &str, description: &str, scope: KeybindingScope) -> Self {
+        Self {
+            accelerator: accelerator.to_string(),
+            action: action.to_string(),
+            description: description.to_string(),
+            scope,
+        }
+    }
+}
+
+/// Action identifier for the global show/hide shortcut
+pub const TOGGLE_WINDOW_ACTION: &str = "toggle_window";
+
+/// All active keybindings for a configuration.
+///
+/// This is the single registry of bindings: `run()` registers the global
+/// ones and the settings window renders the full list as a cheatsheet.
+pub fn keybindings(config: &Config) -> Vec<KeybindingInfo> {
+    use KeybindingScope::{Global, Window};
+
+    let toggle = |accelerator: &str| {
+        KeybindingInfo::new(
+            accelerator,
+            TOGGLE_WINDOW_ACTION,
+            "Show or hide the search window",
+            Global,
+        )
+    };
+
+    let mut bindings = vec![toggle(&config.shortcut)];
+    for accelerator in &config.shortcuts {
+        if !bindings
+            .iter()
+            .any(|binding| &binding.accelerator == accelerator)
+        {
+            bindings.push(toggle(accelerator));
+        }
+    }
+    bindings.extend([
+        KeybindingInfo::new("ArrowDown", "select_next", "Select the next result", Window),
+        KeybindingInfo::new(
+            "ArrowUp",
+            "select_previous",
+            "Select the previous result",
+            Window,
+        ),
+        KeybindingInfo::new(
+            "h",
+            "toggle_details",
+            "Show details for the selected result",
+            Window,
+        ),
+        KeybindingInfo::new(
+            "Enter",
+            "copy_selected",
+            "Copy the selected command and hide",
+            Window,
+        ),
+        KeybindingInfo::new(
+            "CommandOrControl+1-9",
+            "copy_nth_result",
+            "Copy the Nth visible result and hide",
+            Window,
+        ),
+        KeybindingInfo::new(
+            "CommandOrControl+T",
+            "open_in_terminal",
+            "Open a terminal in the selected command's directory",
+            Window,
+        ),
+        KeybindingInfo::new(
+            "CommandOrControl+P",
+            "toggle_pinned",
+            "Pin the window so it stays open when it loses focus",
+            Window,
+        ),
+        KeybindingInfo::new(
+            "Escape",
+            "hide_window",
+            "Close details, or hide (keeping the search unless clear_on_hide is set)",
+            Window,
+        ),
+    ]);
+    bindings
+}
+
+/// Accelerators that must be registered as global shortcuts
+pub fn global_shortcuts(config: &Config) -> Vec<String> {
+    keybindings(config)
+        .into_iter()
+        .filter(|binding| binding.scope == KeybindingScope::Global)
+        .map(|binding| binding.accelerator)
+        .collect()
+}
+
+/// Check that an accelerator string can be registered as a global shortcut
+pub fn validate_accelerator(accelerator: &str) -> Result<(), String> {
+    if accelerator.trim().is_empty() {
+        return Err("Shortcut cannot be empty".to_string());
+    }
+    Shortcut::from_str(accelerator)
+        .map(|_| ())
+        .map_err(|e| format!("Invalid shortcut '{}': {}", accelerator, e))
+}
+
+/// A modifier key in an accelerator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Modifier {
+    Shift,
+    Control,
+    Alt,
+    /// The Command key on macOS, the Windows key or Super elsewhere
+    Super,
+}
+
+/// Modifiers the global shortcut backend registers reliably on the current
+/// platform
+pub fn supported_modifiers() -> &'static [Modifier] {
+    supported_modifiers_on(std::env::consts::OS, is_wayland_session())
+}
+
+/// Whether the desktop session runs on Wayland rather than X11
+fn is_wayland_session() -> bool {
+    std::env::var("XDG_SESSION_TYPE").is_ok_and(|session| session.eq_ignore_ascii_case("wayland"))
+        || std::env::var_os("WAYLAND_DISPLAY").is_some()
+}
+
+/// Some X11 setups don't map a Super modifier at all or grab every Super
+/// combination for the window manager, so Super is left out on X11. Wayland
+/// desktops handle it, and their users often prefer it.
+fn supported_modifiers_on(os: &str, wayland: bool) -> &'static [Modifier] {
+    match os {
+        "linux" if !wayland => &[Modifier::Shift, Modifier::Control, Modifier::Alt],
+        _ => &[
+            Modifier::Shift,
+            Modifier::Control,
+            Modifier::Alt,
+            Modifier::Super,
+        ],
+    }
+}
+
+/// Modifiers in an accelerator, with `CommandOrControl` resolved for the
+/// current platform
+pub fn parse_modifiers(accelerator: &str) -> Result<Vec<Modifier>, String> {
+    parse_modifiers_on(accelerator, std::env::consts::OS)
+}
+
+/// Accepts the same modifier names as the global shortcut plugin
+fn parse_modifiers_on(accelerator: &str, os: &str) -> Result<Vec<Modifier>, String> {
+    let tokens: Vec<&str> = accelerator.split('+').map(str::trim).collect();
+    let mut modifiers = Vec::new();
+    for token in &tokens[..tokens.len() - 1] {
+        let modifier = match token.to_uppercase().as_str() {
+            "SHIFT" => Modifier::Shift,
+            "CONTROL" | "CTRL" => Modifier::Control,
+            "ALT" | "OPTION" => Modifier::Alt,
+            "SUPER" | "COMMAND" | "CMD" => Modifier::Super,
+            "COMMANDORCONTROL" | "COMMANDORCTRL" | "CMDORCTRL" | "CMDORCONTROL" => {
+                if os == "macos" {
+                    Modifier::Super
+                } else {
+                    Modifier::Control
+                }
+            }
+            _ => {
+                return Err(format!(
+                    "Unknown modifier '{}' in shortcut '{}'",
+                    token, accelerator
+                ))
+            }
+        };
+        if !modifiers.contains(&modifier) {
+            modifiers.push(modifier);
+        }
+    }
+    Ok(modifiers)
+}
+
+/// Warnings for modifiers in `accelerator` that may not work as a global
+/// shortcut on the current platform. Malformed shortcuts are left to
+/// `validate_accelerator`.
+pub fn shortcut_warnings(accelerator: &str) -> Vec<String> {
+    shortcut_warnings_on(accelerator, std::env::consts::OS, is_wayland_session())
+}
+
+fn shortcut_warnings_on(accelerator: &str, os: &str, wayland: bool) -> Vec<String> {
+    let supported = supported_modifiers_on(os, wayland);
+    let platform = if os == "linux" { "X11" } else { os };
+    parse_modifiers_on(accelerator, os)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|modifier| !supported.contains(modifier))
+        .map(|modifier| {
+            format!(
+                "The {:?} modifier in '{}' may not work as a global shortcut on {}; \
+                 the desktop may already use it",
+                modifier, accelerator, platform
+            )
+        })
+        .collect()
+}
+
+/// The global shortcut currently registered to toggle the search window
+#[derive(Debug)]
+pub struct ActiveShortcut(pub String);
+
+/// Something that can register and unregister global shortcuts
+pub trait ShortcutRegistrar {
+    fn register(&mut self, accelerator: &str) -> Result<(), String>;
+    fn unregister(&mut self, accelerator: &str) -> Result<(), String>;
+    fn is_registered(&self, accelerator: &str) -> bool;
+}
+
+impl<R: tauri::Runtime> ShortcutRegistrar for &GlobalShortcut<R> {
+    fn register(&mut self, accelerator: &str) -> Result<(), String> {
+        GlobalShortcut::register(self, accelerator)
+            .map_err(|e| format!("Failed to register shortcut '{}': {}", accelerator, e))
+    }
+
+    fn unregister(&mut self, accelerator: &str) -> Result<(), String> {
+        GlobalShortcut::unregister(self, accelerator)
+            .map_err(|e| format!("Failed to unregister shortcut '{}': {}", accelerator, e))
+    }
+
+    fn is_registered(&self, accelerator: &str) -> bool {
+        GlobalShortcut::is_registered(self, accelerator)
+    }
+}
+
+/// Register `accelerator`, falling back to `default` if it is malformed or
+/// can't be registered. Returns the shortcut that is now active.
+pub fn register_shortcut_or_default(
+    registrar: &mut impl ShortcutRegistrar,
+    accelerator: &str,
+    default: &str,
+) -> String {
+    let Err(e) = validate_accelerator(accelerator).and_then(|_| registrar.register(accelerator))
+    else {
+        return accelerator.to_string();
+    };
+    eprintln!("{}; falling back to '{}'", e, default);
+    if accelerator != default {
+        if let Err(e) = registrar.register(default) {
+            eprintln!("{}", e);
+        }
+    }
+    default.to_string()
+}
+
+/// Validate and register each of `accelerators`, skipping the ones that
+/// fail. Returns an error message for every shortcut that was skipped.
+pub fn register_shortcuts(
+    registrar: &mut impl ShortcutRegistrar,
+    accelerators: &[String],
+) -> Vec<String> {
+    accelerators
+        .iter()
+        .filter_map(|accelerator| {
+            validate_accelerator(accelerator)
+                .and_then(|_| registrar.register(accelerator))
+                .err()
+        })
+        .collect()
+}
+
+/// Global shortcuts of `config` besides the primary `shortcut`
+fn extra_shortcuts(config: &Config) -> Vec<String> {
+    global_shortcuts(config)
+        .into_iter()
+        .filter(|accelerator| *accelerator != config.shortcut)
+        .collect()
+}
+
+/// Bring the registered extra shortcuts from `old` to `new`: unregister the
+/// ones `new` no longer lists and register the ones it added. The primary
+/// shortcut is left to `swap_shortcut`, which should run first. Returns an
+/// error message for every shortcut that couldn't be changed.
+pub fn sync_extra_shortcuts(
+    registrar: &mut impl ShortcutRegistrar,
+    old: &Config,
+    new: &Config,
+) -> Vec<String> {
+    let wanted = global_shortcuts(new);
+    let mut errors: Vec<String> = extra_shortcuts(old)
+        .iter()
+        .filter(|accelerator| !wanted.contains(accelerator))
+        .filter_map(|accelerator| registrar.unregister(accelerator).err())
+        .collect();
+    let added: Vec<String> = extra_shortcuts(new)
+        .into_iter()
+        .filter(|accelerator| !registrar.is_registered(accelerator))
+        .collect();
+    errors.extend(register_shortcuts(registrar, &added));
+    errors
+}
+
+/// Replace the active global shortcut `current` with `new`.
+///
+/// The new shortcut is validated and registered before `persist` is called,
+/// and the old one is restored if registration or persisting fails, so a
+/// working shortcut is always left in place.
+pub fn swap_shortcut<F>(
+    registrar: &mut impl ShortcutRegistrar,
+    current: &str,
+    new: &str,
+    persist: F,
+) -> Result<(), String>
+where
+    F: FnOnce(&str) -> Result<(), String>,
+{
+    validate_accelerator(new)?;
+    if current == new {
+        return persist(new);
+    }
+
+    // `current` may have failed to register at startup
+    let was_registered = registrar.is_registered(current);
+    if was_registered {
+        registrar.unregister(current)?;
+    }
+    if let Err(e) = registrar.register(new) {
+        if was_registered {
+            let _ = registrar.register(current);
+        }
+        return Err(e);
+    }
+    if let Err(e) = persist(new) {
+        let _ = registrar.unregister(new);
+        if was_registered {
+            let _ = registrar.register(current);
+        }
+        return Err(e);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Records registered shortcuts and fails to register `reject`
+    #[derive(Default)]
+    struct MockRegistrar {
+        registered: Vec<String>,
+        reject: Option<String>,
+    }
+
+    impl ShortcutRegistrar for MockRegistrar {
+        fn register(&mut self, accelerator: &str) -> Result<(), String> {
+            if self.reject.as_deref() == Some(accelerator) {
+                return Err(format!("{} is taken", accelerator));
+            }
+            self.registered.push(accelerator.to_string());
+            Ok(())
+        }
+
+        fn unregister(&mut self, accelerator: &str) -> Result<(), String> {
+            self.registered.retain(|a| a != accelerator);
+            Ok(())
+        }
+
+        fn is_registered(&self, accelerator: &str) -> bool {
+            self.registered.iter().any(|a| a == accelerator)
+        }
+    }
+
+    #[test]
+    fn test_swap_shortcut_registers_then_persists() {
+        let mut registrar = MockRegistrar {
+            registered: vec!["Alt+Space".to_string()],
+            ..Default::default()
+        };
+        let mut persisted = None;
+
+        swap_shortcut(&mut registrar, "Alt+Space", "Control+Shift+K", |s| {
+            persisted = Some(s.to_string());
+            Ok(())
+        })
+        .expect("swap should succeed");
+
+        assert_eq!(registrar.registered, vec!["Control+Shift+K".to_string()]);
+        assert_eq!(persisted.as_deref(), Some("Control+Shift+K"));
+    }
+
+    #[test]
+    fn test_swap_shortcut_rolls_back_on_registration_failure() {
+        let mut registrar = MockRegistrar {
+            registered: vec!["Alt+Space".to_string()],
+            reject: Some("Control+Shift+K".to_string()),
+        };
+
+        let result = swap_shortcut(&mut registrar, "Alt+Space", "Control+Shift+K", |_| {
+            panic!("a failed swap must not be persisted")
+        });
+
+        assert!(result.is_err());
+        assert_eq!(registrar.registered, vec!["Alt+Space".to_string()]);
+    }
+
+    #[test]
+    fn test_swap_shortcut_rolls_back_on_persist_failure() {
+        let mut registrar = MockRegistrar {
+            registered: vec!["Alt+Space".to_string()],
+            ..Default::default()
+        };
+
+        let result = swap_shortcut(&mut registrar, "Alt+Space", "Control+Shift+K", |_| {
+            Err("disk full".to_string())
+        });
+
+        assert_eq!(result, Err("disk full".to_string()));
+        assert_eq!(registrar.registered, vec!["Alt+Space".to_string()]);
+    }
+
+    #[test]
+    fn test_swap_shortcut_rejects_invalid_accelerator() {
+        let mut registrar = MockRegistrar {
+            registered: vec!["Alt+Space".to_string()],
+            ..Default::default()
+        };
+
+        let result = swap_shortcut(&mut registrar, "Alt+Space", "Hyper+Nope+", |_| Ok(()));
+        assert!(result.is_err());
+        assert_eq!(registrar.registered, vec!["Alt+Space".to_string()]);
+    }
+
+    #[test]
+    fn test_register_shortcut_or_default_falls_back_on_invalid_shortcut() {
+        let mut registrar = MockRegistrar::default();
+
+        let active =
+            register_shortcut_or_default(&mut registrar, "Hyper+Nope+", "Control+Shift+Space");
+        assert_eq!(active, "Control+Shift+Space");
+        assert_eq!(
+            registrar.registered,
+            vec!["Control+Shift+Space".to_string()]
+        );
+
+        let mut registrar = MockRegistrar::default();
+        let active =
+            register_shortcut_or_default(&mut registrar, "Alt+Space", "Control+Shift+Space");
+        assert_eq!(active, "Alt+Space");
+        assert_eq!(registrar.registered, vec!["Alt+Space".to_string()]);
+    }
+
+    #[test]
+    fn test_register_shortcuts_skips_invalid_and_taken_ones() {
+        let mut registrar = MockRegistrar {
+            reject: Some("Super+H".to_string()),
+            ..Default::default()
+        };
+        let accelerators =
+            ["Alt+Space", "Hyper+Nope+", "Super+H", "Control+Shift+K"].map(String::from);
+
+        let errors = register_shortcuts(&mut registrar, &accelerators);
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].contains("Hyper+Nope+"));
+        assert!(errors[1].contains("Super+H"));
+        assert_eq!(
+            registrar.registered,
+            vec!["Alt+Space".to_string(), "Control+Shift+K".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_swap_shortcut_replaces_unregistered_current() {
+        let mut registrar = MockRegistrar::default();
+
+        swap_shortcut(&mut registrar, "Alt+Space", "Control+Shift+K", |_| Ok(())).unwrap();
+        assert_eq!(registrar.registered, vec!["Control+Shift+K".to_string()]);
+    }
+
+    #[test]
+    fn test_parse_modifiers_accepts_aliases() {
+        assert_eq!(
+            parse_modifiers_on("Ctrl+shift+Space", "linux"),
+            Ok(vec![Modifier::Control, Modifier::Shift])
+        );
+        assert_eq!(
+            parse_modifiers_on("Option+Cmd+K", "macos"),
+            Ok(vec![Modifier::Alt, Modifier::Super])
+        );
+        assert_eq!(parse_modifiers_on("F5", "linux"), Ok(vec![]));
+        // Repeated modifiers are listed once
+        assert_eq!(
+            parse_modifiers_on("Control+Ctrl+K", "windows"),
+            Ok(vec![Modifier::Control])
+        );
+    }
+
+    #[test]
+    fn test_parse_modifiers_resolves_command_or_control() {
+        assert_eq!(
+            parse_modifiers_on("CommandOrControl+Space", "macos"),
+            Ok(vec![Modifier::Super])
+        );
+        assert_eq!(
+            parse_modifiers_on("CmdOrCtrl+Space", "linux"),
+            Ok(vec![Modifier::Control])
+        );
+    }
+
+    #[test]
+    fn test_parse_modifiers_rejects_unknown_modifier() {
+        let err = parse_modifiers_on("Hyper+Space", "linux").unwrap_err();
+        assert!(err.contains("Unknown modifier 'Hyper'"));
+    }
+
+    #[test]
+    fn test_super_is_only_flagged_on_x11() {
+        let warnings = shortcut_warnings_on("Super+H", "linux", false);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("on X11"), "got: {}", warnings[0]);
+        assert!(shortcut_warnings_on("Super+H", "linux", true).is_empty());
+        assert!(shortcut_warnings_on("Super+H", "macos", false).is_empty());
+        assert!(shortcut_warnings_on("Super+H", "windows", false).is_empty());
+        assert!(shortcut_warnings_on("Control+Shift+Space", "linux", false).is_empty());
+        assert!(shortcut_warnings(&Config::default().shortcut).is_empty());
+    }
+
+    #[test]
+    fn test_cheatsheet_includes_configured_toggle_shortcut() {
+        let config = Config {
+            shortcut: "Alt+Space".to_string(),
+            ..Default::default()
+        };
+
+        let bindings = keybindings(&config);
+        let toggle = bindings
+            .iter()
+            .find(|binding| binding.accelerator == "Alt+Space")
+            .expect("toggle shortcut should be listed");
+        assert_eq!(toggle.action, TOGGLE_WINDOW_ACTION);
+        assert_eq!(toggle.scope, KeybindingScope::Global);
+    }
+
+    #[test]
+    fn test_sync_extra_shortcuts_registers_added_and_drops_removed() {
+        let mut registrar = MockRegistrar {
+            registered: vec!["Alt+Space".to_string(), "Super+H".to_string()],
+            reject: Some("Control+Shift+J".to_string()),
+        };
+        let old = Config {
+            shortcut: "Alt+Space".to_string(),
+            shortcuts: vec!["Super+H".to_string()],
+            ..Default::default()
+        };
+        let new = Config {
+            shortcuts: vec!["Control+Shift+K".to_string(), "Control+Shift+J".to_string()],
+            ..old.clone()
+        };
+
+        let errors = sync_extra_shortcuts(&mut registrar, &old, &new);
+        assert_eq!(errors, vec!["Control+Shift+J is taken".to_string()]);
+        assert_eq!(registrar.registered, vec!["Alt+Space", "Control+Shift+K"]);
+
+        // An extra that became the primary is left to `swap_shortcut`
+        let promoted = Config {
+            shortcut: "Control+Shift+K".to_string(),
+            shortcuts: Vec::new(),
+            ..Default::default()
+        };
+        assert!(sync_extra_shortcuts(&mut registrar, &new, &promoted).is_empty());
+        assert_eq!(registrar.registered, vec!["Alt+Space", "Control+Shift+K"]);
+    }
+
+    #[test]
+    fn test_global_shortcuts_only_lists_global_bindings() {
+        let config = Config {
+            shortcut: "Alt+Space".to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(global_shortcuts(&config), vec!["Alt+Space".to_string()]);
+
+        let config = Config {
+            shortcut: "Alt+Space".to_string(),
+            shortcuts: vec!["Super+H".to_string(), "Alt+Space".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            global_shortcuts(&config),
+            vec!["Alt+Space".to_string(), "Super+H".to_string()]
+        );
+    }
+}
@@ -0,0 +1,84 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::app_data::get_data_dir;
+use crate::SearchFilters;
+
+/// State file holding the most recently applied filters
+pub fn get_last_filters_path() -> Option<PathBuf> {
+    get_data_dir().map(|p| p.join("last_filters.json"))
+}
+
+/// Persist the filters of the latest search, skipping the write when they
+/// haven't changed
+pub fn save_last_filters(path: &Path, filters: &SearchFilters) -> Result<(), String> {
+    if read_filters(path).as_ref() == Some(filters) {
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    let json = serde_json::to_string(filters)
+        .map_err(|e| format!("Failed to serialize filters: {}", e))?;
+    fs::write(path, json).map_err(|e| format!("Failed to write last filters: {}", e))
+}
+
+fn read_filters(path: &Path) -> Option<SearchFilters> {
+    let contents = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+/// Filters to start with: the persisted ones when `remember` is enabled,
+/// otherwise (or if nothing valid was saved) the defaults
+pub fn load_last_filters(path: &Path, remember: bool) -> SearchFilters {
+    if !remember {
+        return SearchFilters::default();
+    }
+    read_filters(path).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("atuin-bar-{}-{}.json", name, std::process::id()))
+    }
+
+    fn failures_last_day() -> SearchFilters {
+        SearchFilters {
+            exit_filter: Some("failure".to_string()),
+            time_range: Some("24h".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_persisted_filters_are_restored() {
+        let path = temp_path("last-filters-restore");
+        save_last_filters(&path, &failures_last_day()).unwrap();
+
+        assert_eq!(load_last_filters(&path, true), failures_last_day());
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_disabled_flag_yields_defaults() {
+        let path = temp_path("last-filters-disabled");
+        save_last_filters(&path, &failures_last_day()).unwrap();
+
+        assert_eq!(load_last_filters(&path, false), SearchFilters::default());
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_missing_or_corrupt_file_yields_defaults() {
+        let path = temp_path("last-filters-corrupt");
+        let _ = fs::remove_file(&path);
+        assert_eq!(load_last_filters(&path, true), SearchFilters::default());
+
+        fs::write(&path, "{ not json").unwrap();
+        assert_eq!(load_last_filters(&path, true), SearchFilters::default());
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -1,79 +1,424 @@
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::PathBuf;
-use std::process::Command;
-use tauri::{menu::{MenuBuilder, MenuItemBuilder}, Manager};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{
+    menu::{MenuBuilder, MenuItemBuilder},
+    Emitter, Manager,
+};
+use tauri_plugin_autostart::ManagerExt;
 use tauri_plugin_clipboard_manager::ClipboardExt;
-use tauri_plugin_global_shortcut::ShortcutState;
+use tauri_plugin_deep_link::DeepLinkExt;
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, ShortcutState};
+use tauri_plugin_opener::OpenerExt;
+
+pub mod analysis;
+pub mod app_data;
+pub mod atomic_write;
+pub mod atuin;
+pub mod atuin_config;
+pub mod autostart;
+pub mod boolean_query;
+pub mod bulk_delete;
+pub mod click_behavior;
+pub mod clipboard;
+pub mod colors;
+pub mod config_file;
+pub mod config_watch;
+pub mod deep_link;
+pub mod export;
+pub mod favorites;
+pub mod focus;
+pub mod frequency;
+pub mod keybindings;
+pub mod last_filters;
+pub mod new_entries;
+pub mod paging;
+pub mod paste;
+pub mod permissions;
+pub mod recent_searches;
+pub mod results;
+pub mod runner;
+pub mod search_cache;
+pub mod search_sequence;
+pub mod size_presets;
+pub mod sqlite_backend;
+pub mod terminal;
+pub mod window_layout;
+
+pub use atuin::{CommandBackend, HistoryBackend, OutputLoss, SearchError, ATUIN_NOT_FOUND};
+pub use results::parse_atuin_line;
+
+/// One parsed line of `{command}|{exit}|{duration}|{directory}|{time}` atuin
+/// output, shared by the command layer and tests
+pub type ParsedHistoryEntry = HistoryEntry;
+use analysis::{DirectoryOption, DurationBucket, FlakyCommand, HistorySummary, ResultFacets};
+use app_data::{AppDataTarget, AppDataUsage};
+use atuin_config::{AtuinDefaults, CompatWarning};
+use bulk_delete::DeleteTokens;
+use click_behavior::ClickBehavior;
+use clipboard::{CopyOutcome, CopyPlan};
+use config_file::ConfigStatus;
+use deep_link::{parse_deep_link, PendingDeepLink, DEEP_LINK_SEARCH_EVENT};
+use favorites::Favorites;
+use focus::{FocusTracker, MAIN_WINDOW};
+use frequency::SelectionCounts;
+use keybindings::{ActiveShortcut, KeybindingInfo};
+use new_entries::SearchWatermark;
+use paging::{ResultWindow, SearchPage};
+use permissions::PermissionStatus;
+use recent_searches::RecentSearches;
+use results::{ExitInfo, HistoryEntry, LastResults, VerboseResults};
+use runner::{CommandExecutor, CommandOutput, ShellExecutor};
+use search_cache::SearchCache;
+use search_sequence::SearchSequence;
+use size_presets::SizePreset;
+use sqlite_backend::SqliteBackend;
 
 /// Application configuration
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(default)]
 pub struct Config {
+    /// Format version of the config file; older files are migrated on load
+    /// (default: 1 when missing, so files written before versioning migrate)
+    #[serde(default = "config_file::legacy_config_version")]
+    pub version: u32,
     /// Global shortcut to toggle the window (e.g., "CommandOrControl+Shift+Space")
     pub shortcut: String,
-    /// Theme: "dark" or "light" (default: "dark")
+    /// Additional global shortcuts that also toggle the window; changes take
+    /// effect after a restart (default: [])
+    pub shortcuts: Vec<String>,
+    /// Theme: "dark", "light", or "system" to follow the OS (default: "dark")
     pub theme: String,
     /// Maximum number of results to display (default: 20)
     pub max_results: u32,
+    /// Number of history entries fetched per search. Dedup and duration
+    /// filters then narrow these to `max_results`, which is clamped to this
+    /// (default: 100)
+    pub fetch_limit: u32,
     /// Window width in pixels (default: 700)
     pub window_width: u32,
+    /// Font size of the results list in pixels, 8 to 72 (default: 14)
+    pub font_size: u32,
+    /// Opacity of the search window from 0.0 (invisible) to 1.0 (default: 1.0)
+    pub opacity: f64,
+    /// Maximum window height in pixels; the window grows with the results up
+    /// to this height (default: 500)
+    pub window_height: u32,
+    /// Fit the window height to the number of results, up to `window_height`
+    /// (default: true)
+    pub auto_resize: bool,
+    /// Last position of the search window in physical pixels, restored at
+    /// launch; unset centers the window (default: unset)
+    pub window_x: Option<i32>,
+    pub window_y: Option<i32>,
+    /// How long cached search results stay fresh in milliseconds; 0 disables
+    /// the cache (default: 2000)
+    pub search_cache_ttl_ms: u64,
+    /// How long an atuin invocation may run in milliseconds before it is
+    /// killed; 0 waits indefinitely (default: 3000)
+    pub search_timeout_ms: u64,
+    /// Most bytes of output read from one atuin invocation; past it the
+    /// output is cut to the records that fit and the results are flagged as
+    /// truncated, instead of growing without bound. 0 disables the limit
+    /// (default: 4 MiB)
+    pub max_output_bytes: u64,
+    /// Name of the last applied window size preset (default: none)
+    pub size_preset: String,
+    /// The atuin executable: a path, or a name looked up on PATH
+    /// (default: "atuin")
+    pub atuin_path: String,
+    /// Retry atuin through `$SHELL -ilc` when it isn't an executable on PATH,
+    /// e.g. when it is a shell function (default: false)
+    pub use_login_shell: bool,
+    /// Persist how often commands are selected, used to rank autocomplete
+    /// suggestions across launches (default: true)
+    pub remember_selections: bool,
+    /// Terminal to open results in; `{dir}` and `{command}` are substituted.
+    /// Empty uses the platform default (default: "")
+    pub terminal_cmd: String,
+    /// Hide the search window when it loses focus; see `blur_hide_scope`
+    /// (default: true)
+    pub hide_on_blur: bool,
+    /// When the search window hides on focus loss: "app" only when focus
+    /// leaves all app windows, "window" whenever it loses focus (default: "app")
+    pub blur_hide_scope: String,
+    /// Clear the query and results whenever the search window hides,
+    /// including with Escape (default: false)
+    pub clear_on_hide: bool,
+    /// Restore the last-used search filters on launch (default: false)
+    pub remember_filters: bool,
+    /// Number of recent searches offered when the query is empty; 0 stops
+    /// recording them (default: 10)
+    pub recent_searches_limit: u32,
+    /// Allow running commands from the bar (default: false). Also accepted
+    /// as `allow_execute`.
+    #[serde(alias = "allow_execute")]
+    pub allow_run: bool,
+    /// Require confirmation before running destructive-looking commands
+    /// such as `rm -rf` or `sudo` (default: true)
+    pub confirm_dangerous: bool,
+    /// Launch atuin-bar at login (default: false)
+    pub autostart: bool,
+    /// Fields (and their order) returned for structured search results; empty
+    /// returns every field (default: [])
+    pub result_fields: Vec<String>,
+    /// Trailing newline on copied commands: "strip", "keep", "ask", or "add"
+    /// (default: "strip")
+    pub copy_trailing_newline: String,
+    /// Trim trailing whitespace and stray carriage returns from copied
+    /// commands (default: false)
+    pub normalize_on_copy: bool,
+    /// Type the selected command into the previously focused window instead
+    /// of copying it; needs the Accessibility permission on macOS
+    /// (default: false)
+    pub paste_on_select: bool,
+    /// How atuin matches the query: "prefix", "fuzzy", "fulltext", or "skim"
+    /// (default: "prefix")
+    pub search_mode: String,
+    /// Where searches read history from: "cli" runs `atuin search`, "sqlite"
+    /// queries atuin's database directly and falls back to the CLI when it
+    /// can't be opened (default: "cli")
+    pub backend: String,
+    /// What single and double clicks on a result do
+    pub click_behavior: ClickBehavior,
+    /// Custom window size presets, added to (or overriding) the built-in ones
+    pub size_presets: BTreeMap<String, SizePreset>,
+    /// Extra environment variables set when running atuin, e.g. `ATUIN_LOG`
+    pub atuin_env: BTreeMap<String, String>,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
+            version: config_file::CONFIG_VERSION,
             shortcut: if cfg!(target_os = "macos") {
                 "CommandOrControl+Shift+Space".to_string()
             } else {
                 "Control+Shift+Space".to_string()
             },
+            shortcuts: Vec::new(),
             theme: "dark".to_string(),
             max_results: 20,
+            fetch_limit: 100,
             window_width: 700,
+            font_size: 14,
+            opacity: 1.0,
+            window_height: 500,
+            auto_resize: true,
+            window_x: None,
+            window_y: None,
+            search_cache_ttl_ms: 2000,
+            search_timeout_ms: 3000,
+            max_output_bytes: atuin::DEFAULT_MAX_OUTPUT_BYTES,
+            size_preset: String::new(),
+            atuin_path: "atuin".to_string(),
+            use_login_shell: false,
+            remember_selections: true,
+            terminal_cmd: String::new(),
+            hide_on_blur: true,
+            blur_hide_scope: "app".to_string(),
+            clear_on_hide: false,
+            remember_filters: false,
+            recent_searches_limit: 10,
+            allow_run: false,
+            confirm_dangerous: true,
+            autostart: false,
+            result_fields: Vec::new(),
+            copy_trailing_newline: "strip".to_string(),
+            normalize_on_copy: false,
+            paste_on_select: false,
+            search_mode: "prefix".to_string(),
+            backend: "cli".to_string(),
+            click_behavior: ClickBehavior::default(),
+            size_presets: BTreeMap::new(),
+            atuin_env: BTreeMap::new(),
         }
     }
 }
 
+/// Config file tests use instead of the real one. `XDG_CONFIG_HOME` can't
+/// redirect it, as `dirs::config_dir` ignores that on macOS.
+#[cfg(test)]
+static TEST_CONFIG_PATH: Mutex<Option<PathBuf>> = Mutex::new(None);
+
 /// Get the config file path (~/.config/atuin-bar/config.toml)
 pub fn get_config_path() -> Option<PathBuf> {
+    #[cfg(test)]
+    if let Some(path) = TEST_CONFIG_PATH
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+    {
+        return Some(path);
+    }
     dirs::config_dir().map(|p| p.join("atuin-bar").join("config.toml"))
 }
 
-/// Load configuration from file, falling back to defaults
+/// Load configuration from file, falling back to defaults, with
+/// `ATUIN_BAR_*` environment variables taking precedence
 pub fn load_config() -> Config {
-    let Some(config_path) = get_config_path() else {
-        return Config::default();
-    };
+    let mut config = get_config_path().map_or_else(Config::default, |path| load_config_at(&path));
+    apply_env_overrides(&mut config, |name| std::env::var(name).ok());
+    coerce_unknown_theme(&mut config);
+    config
+}
 
+/// Load the config file at `config_path`, creating it with the defaults if
+/// it doesn't exist yet
+fn load_config_at(config_path: &Path) -> Config {
     if !config_path.exists() {
         // Create default config file for user reference
         if let Some(parent) = config_path.parent() {
             let _ = fs::create_dir_all(parent);
         }
-        let default_config = r#"# Atuin Bar Configuration
+        let default_config = format!(
+            r#"# Atuin Bar Configuration
+
+# Format version of this file, used to migrate it when fields change
+version = {version}
 
 # Global shortcut to toggle the window
 # Examples: "CommandOrControl+Shift+Space", "Alt+Space", "Super+H"
 shortcut = "CommandOrControl+Shift+Space"
 
-# Theme: "dark" or "light" (default: "dark")
+# Additional shortcuts that also toggle the window, e.g. ["Alt+Space"]
+shortcuts = []
+
+# Theme: "dark", "light", or "system" to follow the OS (default: "dark")
 theme = "dark"
 
 # Maximum number of results to display (default: 20)
 max_results = 20
 
+# History entries fetched per search; dedup and duration filters narrow them
+# to max_results, so keep this larger (default: 100)
+fetch_limit = 100
+
 # Window width in pixels (default: 700)
 window_width = 700
-"#;
-        let _ = fs::write(&config_path, default_config);
+
+# Font size of the results list in pixels, 8 to 72 (default: 14)
+font_size = 14
+
+# Opacity of the search window from 0.0 (invisible) to 1.0 (default: 1.0)
+opacity = 1.0
+
+# Maximum window height in pixels; the window grows with results up to this (default: 500)
+window_height = 500
+
+# Fit the window height to the number of results, up to window_height (default: true)
+auto_resize = true
+
+# Position of the search window, saved when it hides; unset centers it
+# window_x = 0
+# window_y = 0
+
+# How long cached search results stay fresh in milliseconds; 0 disables caching (default: 2000)
+search_cache_ttl_ms = 2000
+
+# How long atuin may take to answer in milliseconds before it is stopped, e.g.
+# when its database is locked; 0 waits indefinitely (default: 3000)
+search_timeout_ms = 3000
+
+# Most bytes of output read from one atuin invocation; a search printing more
+# shows the results that fit and is flagged as truncated, instead of using
+# ever more memory. 0 disables the limit (default: 4194304)
+max_output_bytes = 4194304
+
+# The atuin executable, e.g. "/nix/var/nix/profiles/default/bin/atuin" when it
+# isn't on PATH (default: "atuin")
+atuin_path = "atuin"
+
+# Run atuin through your login shell if it is a shell function or alias (default: false)
+use_login_shell = false
+
+# Remember how often commands are selected to rank autocomplete suggestions (default: true)
+remember_selections = true
+
+# Terminal used by "open in terminal"; {{dir}} and {{command}} are substituted.
+# Empty uses Terminal.app on macOS, cmd on Windows, or $TERMINAL on Linux.
+# Example: "kitty --directory {{dir}} --hold sh -c {{command}}"
+terminal_cmd = ""
+
+# Hide the search window when it loses focus (default: true)
+hide_on_blur = true
+
+# Hide the search window when focus leaves the app ("app") or whenever it
+# loses focus, including to the settings window ("window") (default: "app")
+blur_hide_scope = "app"
+
+# Clear the query and results whenever the search window hides, including
+# with Escape (default: false)
+clear_on_hide = false
+
+# Restore the last-used search filters on launch (default: false)
+remember_filters = false
+
+# Recent searches offered when the query is empty; 0 stops recording them (default: 10)
+recent_searches_limit = 10
+
+# Allow running commands from the bar (default: false)
+allow_run = false
+
+# Require confirmation before running destructive-looking commands (default: true)
+confirm_dangerous = true
+
+# Launch atuin-bar at login (default: false)
+autostart = false
+
+# Fields (and their order) returned for structured search results, e.g.
+# ["command", "exit", "directory"]; empty returns every field (default: [])
+result_fields = []
+
+# Trailing newline on copied commands, which makes some terminals run them on
+# paste: "strip", "keep", "ask", or "add" to always end with one (default: "strip")
+copy_trailing_newline = "strip"
+
+# Trim trailing whitespace and stray carriage returns from copied commands,
+# keeping spacing inside them (default: false)
+normalize_on_copy = false
+
+# Type the selected command into the previously focused window instead of
+# copying it; needs the Accessibility permission on macOS (default: false)
+paste_on_select = false
+
+# How atuin matches the query: "prefix", "fuzzy", "fulltext", or "skim" (default: "prefix")
+search_mode = "prefix"
+
+# Where searches read history from: "cli" runs `atuin search`, "sqlite" reads
+# atuin's database ($ATUIN_DB_PATH or ~/.local/share/atuin/history.db)
+# directly, which is faster while typing (default: "cli")
+backend = "cli"
+
+# What clicking a result does: "none", "select", "preview", "copy", or "run"
+# ("run" requires allow_run = true)
+[click_behavior]
+single = "select"
+double = "copy"
+
+# Custom window size presets (built-in: "compact", "comfortable", "wide")
+# [size_presets]
+# tall = {{ width = 700, height = 900 }}
+
+# Extra environment variables set when running atuin
+# [atuin_env]
+# ATUIN_LOG = "debug"
+"#,
+            version = config_file::CONFIG_VERSION
+        );
+        let _ = fs::write(config_path, default_config);
         return Config::default();
     }
 
-    match fs::read_to_string(&config_path) {
-        Ok(contents) => toml::from_str(&contents).unwrap_or_else(|e| {
-            eprintln!("Failed to parse config file: {}", e);
-            Config::default()
-        }),
+    match fs::read_to_string(config_path) {
+        Ok(contents) => {
+            toml::from_str(&migrate_config_file(config_path, contents)).unwrap_or_else(|e| {
+                eprintln!("Failed to parse config file: {}", e);
+                Config::default()
+            })
+        }
         Err(e) => {
             eprintln!("Failed to read config file: {}", e);
             Config::default()
@@ -81,6 +426,148 @@ window_width = 700
     }
 }
 
+/// Apply `ATUIN_BAR_THEME`, `ATUIN_BAR_SHORTCUT`, `ATUIN_BAR_MAX_RESULTS`
+/// and `ATUIN_BAR_WINDOW_WIDTH` over `config`, looking them up with `var`.
+/// Numbers that don't parse are ignored. Returns the overridden fields.
+fn apply_env_overrides(
+    config: &mut Config,
+    var: impl Fn(&str) -> Option<String>,
+) -> Vec<&'static str> {
+    let number = |name: &str| {
+        let value = var(name)?;
+        value
+            .trim()
+            .parse::<u32>()
+            .map_err(|_| eprintln!("Ignoring {}={:?}: not a number", name, value))
+            .ok()
+    };
+
+    let mut overridden = Vec::new();
+    if let Some(theme) = var("ATUIN_BAR_THEME") {
+        config.theme = theme;
+        overridden.push("theme");
+    }
+    if let Some(shortcut) = var("ATUIN_BAR_SHORTCUT") {
+        config.shortcut = shortcut;
+        overridden.push("shortcut");
+    }
+    if let Some(max_results) = number("ATUIN_BAR_MAX_RESULTS") {
+        config.max_results = max_results;
+        overridden.push("max_results");
+    }
+    if let Some(window_width) = number("ATUIN_BAR_WINDOW_WIDTH") {
+        config.window_width = window_width;
+        overridden.push("window_width");
+    }
+    overridden
+}
+
+/// The message for an edit to a field the environment overrides
+fn env_overridden_error(field: &str) -> String {
+    format!(
+        "{} is set by the ATUIN_BAR_{} environment variable; unset it to change it here",
+        field,
+        field.to_uppercase()
+    )
+}
+
+/// Refuse an edit that changes a field overridden by the environment from
+/// its `current` value, since it would have no effect while the variable is
+/// set
+fn check_env_overridden(
+    edited: &Config,
+    current: &Config,
+    var: impl Fn(&str) -> Option<String>,
+) -> Result<(), String> {
+    for field in apply_env_overrides(&mut Config::default(), var) {
+        let changed = match field {
+            "theme" => edited.theme != current.theme,
+            "shortcut" => edited.shortcut != current.shortcut,
+            "max_results" => edited.max_results != current.max_results,
+            "window_width" => edited.window_width != current.window_width,
+            _ => false,
+        };
+        if changed {
+            return Err(env_overridden_error(field));
+        }
+    }
+    Ok(())
+}
+
+/// Put back the file's values for fields overridden by the environment, so
+/// saving a loaded config doesn't write them to the file
+fn restore_env_overridden(
+    config: &mut Config,
+    file: &Config,
+    var: impl Fn(&str) -> Option<String>,
+) {
+    for field in apply_env_overrides(&mut Config::default(), var) {
+        match field {
+            "theme" => config.theme = file.theme.clone(),
+            "shortcut" => config.shortcut = file.shortcut.clone(),
+            "max_results" => config.max_results = file.max_results,
+            "window_width" => config.window_width = file.window_width,
+            _ => {}
+        }
+    }
+}
+
+/// Upgrade the config file at `path` to the current version, rewriting it
+/// once. Returns the contents to load; if migrating fails they are loaded
+/// as they are.
+fn migrate_config_file(path: &Path, contents: String) -> String {
+    match config_file::migrate_config(&contents) {
+        Ok(Some(migrated)) => {
+            if let Err(e) = config_file::write_config_file(path, &migrated) {
+                eprintln!("{}", e);
+            }
+            migrated
+        }
+        Ok(None) => contents,
+        Err(e) => {
+            eprintln!("Failed to migrate config file: {}", e);
+            contents
+        }
+    }
+}
+
+/// Supported values for `Config::theme`
+const THEMES: &[&str] = &["dark", "light", "system"];
+
+/// Whether `theme` is one the UI knows how to render
+pub fn is_valid_theme(theme: &str) -> bool {
+    THEMES.contains(&theme)
+}
+
+/// The theme shown for a configured one: "system" follows the OS appearance
+/// and falls back to "dark" when it can't be determined
+fn resolve_theme(configured: &str, system: Option<tauri::Theme>) -> String {
+    match configured {
+        "system" => match system {
+            Some(tauri::Theme::Light) => "light".to_string(),
+            _ => "dark".to_string(),
+        },
+        theme => theme.to_string(),
+    }
+}
+
+/// The configured theme as shown in `window`, with "system" resolved
+fn effective_theme<R: tauri::Runtime>(window: &tauri::WebviewWindow<R>) -> String {
+    resolve_theme(&load_config().theme, window.theme().ok())
+}
+
+/// Replace an unknown theme (e.g. a typo in the config file) with "dark"
+fn coerce_unknown_theme(config: &mut Config) {
+    if !is_valid_theme(&config.theme) {
+        eprintln!(
+            "Unknown theme '{}' in config file, using \"dark\" (expected one of {})",
+            config.theme,
+            THEMES.join(", ")
+        );
+        config.theme = "dark".to_string();
+    }
+}
+
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 #[tauri::command]
 fn greet(name: &str) -> String {
@@ -88,15 +575,43 @@ fn greet(name: &str) -> String {
 }
 
 #[tauri::command]
-fn get_theme() -> String {
-    let config = load_config();
-    config.theme
+fn get_theme<R: tauri::Runtime>(window: tauri::WebviewWindow<R>) -> String {
+    effective_theme(&window)
+}
+
+/// "dark" or "light", following the OS appearance when the theme is "system"
+#[tauri::command]
+fn get_effective_theme<R: tauri::Runtime>(window: tauri::WebviewWindow<R>) -> String {
+    effective_theme(&window)
+}
+
+#[tauri::command]
+fn directory_color<R: tauri::Runtime>(
+    window: tauri::WebviewWindow<R>,
+    directory: String,
+) -> String {
+    colors::directory_color(&directory, &effective_theme(&window))
 }
 
 #[tauri::command]
 fn get_max_results() -> u32 {
-    let config = load_config();
-    config.max_results
+    result_limit(&load_config())
+}
+
+/// Number of results shown: `max_results`, clamped to `fetch_limit` since
+/// no more than that many are fetched
+fn result_limit(config: &Config) -> u32 {
+    config.max_results.min(config.fetch_limit)
+}
+
+#[tauri::command]
+fn get_search_mode() -> String {
+    load_config().search_mode
+}
+
+#[tauri::command]
+fn get_paste_on_select() -> bool {
+    load_config().paste_on_select
 }
 
 #[tauri::command]
@@ -106,351 +621,4257 @@ fn get_window_width() -> u32 {
 }
 
 #[tauri::command]
-fn get_config() -> Config {
-    load_config()
+fn get_window_height() -> u32 {
+    let config = load_config();
+    config.window_height
 }
 
 #[tauri::command]
-fn update_config(
-    shortcut: Option<String>,
-    theme: Option<String>,
-    max_results: Option<u32>,
-    window_width: Option<u32>,
-) -> Result<Config, String> {
-    let Some(config_path) = get_config_path() else {
-        return Err("Could not determine config path".to_string());
-    };
+fn get_font_size() -> u32 {
+    let config = load_config();
+    config.font_size
+}
 
-    // Load current config
-    let mut config = load_config();
+/// Configured window opacity. Tauri can't set a window's opacity, so the
+/// frontend fades the content of the (transparent) window instead. A value
+/// edited out of range in the file is clamped.
+#[tauri::command]
+fn get_opacity() -> f64 {
+    let config = load_config();
+    if config.opacity.is_nan() {
+        return 1.0;
+    }
+    config.opacity.clamp(0.0, 1.0)
+}
 
-    // Update fields if provided
-    if let Some(s) = shortcut {
-        config.shortcut = s;
+/// Reject an opacity outside 0.0 (invisible) to 1.0 (opaque)
+fn validate_opacity(opacity: f64) -> Result<(), String> {
+    if (0.0..=1.0).contains(&opacity) {
+        Ok(())
+    } else {
+        Err(format!("Invalid opacity {}: expected 0.0 to 1.0", opacity))
     }
-    if let Some(t) = theme {
-        config.theme = t;
+}
+
+/// Font sizes accepted by `update_config`, in pixels
+const FONT_SIZE_RANGE: std::ops::RangeInclusive<u32> = 8..=72;
+
+/// Window sizes accepted by `update_config`, in pixels
+const WINDOW_SIZE_RANGE: std::ops::RangeInclusive<u32> = 200..=4000;
+
+/// Reject window dimensions that would make the window unusable
+fn validate_window_size(name: &str, value: u32) -> Result<(), String> {
+    if WINDOW_SIZE_RANGE.contains(&value) {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid {} {}: expected {} to {} pixels",
+            name,
+            value,
+            WINDOW_SIZE_RANGE.start(),
+            WINDOW_SIZE_RANGE.end()
+        ))
     }
-    if let Some(m) = max_results {
-        config.max_results = m;
+}
+
+#[tauri::command]
+fn get_config() -> Config {
+    load_config()
+}
+
+/// Config fields set by `ATUIN_BAR_*` environment variables, which can't be
+/// changed from the settings window
+#[tauri::command]
+fn env_overridden_fields() -> Vec<&'static str> {
+    apply_env_overrides(&mut Config::default(), |name| std::env::var(name).ok())
+}
+
+#[tauri::command]
+fn app_data_usage() -> AppDataUsage {
+    app_data::app_data_usage()
+}
+
+#[tauri::command]
+fn clear_app_data(targets: Vec<AppDataTarget>) -> Result<AppDataUsage, String> {
+    app_data::clear_app_data(&targets)
+}
+
+#[tauri::command]
+fn keybindings_cheatsheet() -> Vec<KeybindingInfo> {
+    keybindings::keybindings(&load_config())
+}
+
+/// Render a string as a quoted, escaped TOML value
+fn toml_string(value: &str) -> toml::Value {
+    toml::Value::from(value)
+}
+
+/// Render a table key, quoting it unless it is a valid bare key
+fn toml_key(key: &str) -> String {
+    let bare = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if bare {
+        key.to_string()
+    } else {
+        toml_string(key).to_string()
     }
-    if let Some(w) = window_width {
-        config.window_width = w;
+}
+
+/// A `key = value` line, commented out when the value is unset
+fn optional_toml_line(key: &str, value: Option<i32>) -> String {
+    match value {
+        Some(value) => format!("{} = {}", key, value),
+        None => format!("# {} = 0", key),
     }
+}
 
-    // Serialize to TOML
-    let toml_str = format!(
+/// Render a configuration as a commented TOML document
+fn render_config(config: &Config) -> String {
+    let mut toml_str = format!(
         r#"# Atuin Bar Configuration
 
+# Format version of this file, used to migrate it when fields change
+version = {}
+
 # Global shortcut to toggle the window
 # Examples: "CommandOrControl+Shift+Space", "Alt+Space", "Super+H"
-shortcut = "{}"
+shortcut = {}
+
+# Additional shortcuts that also toggle the window, e.g. ["Alt+Space"]
+shortcuts = {}
 
-# Theme: "dark" or "light" (default: "dark")
-theme = "{}"
+# Theme: "dark", "light", or "system" to follow the OS (default: "dark")
+theme = {}
 
 # Maximum number of results to display (default: 20)
 max_results = {}
 
+# History entries fetched per search; dedup and duration filters narrow them
+# to max_results, so keep this larger (default: 100)
+fetch_limit = {}
+
 # Window width in pixels (default: 700)
 window_width = {}
-"#,
-        config.shortcut, config.theme, config.max_results, config.window_width
-    );
 
-    // Write to file
-    if let Some(parent) = config_path.parent() {
-        let _ = fs::create_dir_all(parent);
-    }
-    fs::write(&config_path, toml_str).map_err(|e| format!("Failed to write config: {}", e))?;
+# Font size of the results list in pixels, 8 to 72 (default: 14)
+font_size = {}
 
-    Ok(config)
-}
+# Opacity of the search window from 0.0 (invisible) to 1.0 (default: 1.0)
+opacity = {}
 
-/// Search filters for atuin queries
-#[derive(Debug, Default, serde::Deserialize)]
-pub struct SearchFilters {
-    /// Filter by directory path
-    pub directory: Option<String>,
-    /// Filter by exit code: "success" (0), "failure" (non-0), or None (all)
-    pub exit_filter: Option<String>,
-    /// Time range: "1h", "24h", "7d", "30d", or None (all)
-    pub time_range: Option<String>,
-}
+# Maximum window height in pixels; the window grows with results up to this (default: 500)
+window_height = {}
 
-// Public function that can be called from integration tests
-pub fn atuin_search(query: &str, filters: Option<SearchFilters>) -> Result<String, String> {
-    let mut cmd = Command::new("atuin");
-    cmd.arg("search")
-        .arg("--search-mode")
-        .arg("prefix")
-        .arg("--limit")
-        .arg("50")
-        .arg("--format")
-        .arg("{command}|{exit}|{duration}|{directory}|{time}");
+# Fit the window height to the number of results, up to window_height (default: true)
+auto_resize = {}
 
-    let filters = filters.unwrap_or_default();
+# Position of the search window, saved when it hides; unset centers it
+{}
+{}
 
-    // Apply directory filter
-    if let Some(ref dir) = filters.directory {
-        if !dir.is_empty() {
-            cmd.arg("--cwd").arg(dir);
-        }
-    }
+# How long cached search results stay fresh in milliseconds; 0 disables caching (default: 2000)
+search_cache_ttl_ms = {}
 
-    // Apply exit code filter
-    if let Some(ref exit_filter) = filters.exit_filter {
-        match exit_filter.as_str() {
-            "success" => {
-                cmd.arg("--exit").arg("0");
-            }
-            "failure" => {
-                cmd.arg("--exclude-exit").arg("0");
-            }
-            _ => {}
+# How long atuin may take to answer in milliseconds before it is stopped, e.g.
+# when its database is locked; 0 waits indefinitely (default: 3000)
+search_timeout_ms = {}
+
+# Most bytes of output read from one atuin invocation; a search printing more
+# shows the results that fit and is flagged as truncated, instead of using
+# ever more memory. 0 disables the limit (default: 4194304)
+max_output_bytes = {}
+
+# Last applied window size preset: "compact", "comfortable", "wide", or one from [size_presets]
+size_preset = {}
+
+# The atuin executable, e.g. "/nix/var/nix/profiles/default/bin/atuin" when it
+# isn't on PATH (default: "atuin")
+atuin_path = {}
+
+# Run atuin through your login shell if it is a shell function or alias (default: false)
+use_login_shell = {}
+
+# Remember how often commands are selected to rank autocomplete suggestions (default: true)
+remember_selections = {}
+
+# Terminal used by "open in terminal"; {{dir}} and {{command}} are substituted.
+# Empty uses Terminal.app on macOS, cmd on Windows, or $TERMINAL on Linux.
+terminal_cmd = {}
+
+# Hide the search window when it loses focus (default: true)
+hide_on_blur = {}
+
+# Hide the search window when focus leaves the app ("app") or whenever it
+# loses focus, including to the settings window ("window") (default: "app")
+blur_hide_scope = {}
+
+# Clear the query and results whenever the search window hides, including
+# with Escape (default: false)
+clear_on_hide = {}
+
+# Restore the last-used search filters on launch (default: false)
+remember_filters = {}
+
+# Recent searches offered when the query is empty; 0 stops recording them (default: 10)
+recent_searches_limit = {}
+
+# Allow running commands from the bar (default: false)
+allow_run = {}
+
+# Require confirmation before running destructive-looking commands (default: true)
+confirm_dangerous = {}
+
+# Launch atuin-bar at login (default: false)
+autostart = {}
+
+# Fields (and their order) returned for structured search results, e.g.
+# ["command", "exit", "directory"]; empty returns every field (default: [])
+result_fields = {}
+
+# Trailing newline on copied commands, which makes some terminals run them on
+# paste: "strip", "keep", "ask", or "add" to always end with one (default: "strip")
+copy_trailing_newline = {}
+
+# Trim trailing whitespace and stray carriage returns from copied commands,
+# keeping spacing inside them (default: false)
+normalize_on_copy = {}
+
+# Type the selected command into the previously focused window instead of
+# copying it; needs the Accessibility permission on macOS (default: false)
+paste_on_select = {}
+
+# How atuin matches the query: "prefix", "fuzzy", "fulltext", or "skim" (default: "prefix")
+search_mode = {}
+
+# Where searches read history from: "cli" runs `atuin search`, "sqlite" reads
+# atuin's database ($ATUIN_DB_PATH or ~/.local/share/atuin/history.db)
+# directly, which is faster while typing (default: "cli")
+backend = {}
+
+# What clicking a result does: "none", "select", "preview", "copy", or "run"
+# ("run" requires allow_run = true)
+[click_behavior]
+single = {}
+double = {}
+"#,
+        config.version,
+        toml_string(&config.shortcut),
+        toml::Value::from(config.shortcuts.clone()),
+        toml_string(&config.theme),
+        config.max_results,
+        config.fetch_limit,
+        config.window_width,
+        config.font_size,
+        toml::Value::Float(config.opacity),
+        config.window_height,
+        config.auto_resize,
+        optional_toml_line("window_x", config.window_x),
+        optional_toml_line("window_y", config.window_y),
+        config.search_cache_ttl_ms,
+        config.search_timeout_ms,
+        config.max_output_bytes,
+        toml_string(&config.size_preset),
+        toml_string(&config.atuin_path),
+        config.use_login_shell,
+        config.remember_selections,
+        toml_string(&config.terminal_cmd),
+        config.hide_on_blur,
+        toml_string(&config.blur_hide_scope),
+        config.clear_on_hide,
+        config.remember_filters,
+        config.recent_searches_limit,
+        config.allow_run,
+        config.confirm_dangerous,
+        config.autostart,
+        toml::Value::from(config.result_fields.clone()),
+        toml_string(&config.copy_trailing_newline),
+        config.normalize_on_copy,
+        config.paste_on_select,
+        toml_string(&config.search_mode),
+        toml_string(&config.backend),
+        toml_string(&config.click_behavior.single),
+        toml_string(&config.click_behavior.double)
+    );
+
+    if !config.size_presets.is_empty() {
+        toml_str.push_str("\n# Custom window size presets\n[size_presets]\n");
+        for (name, preset) in &config.size_presets {
+            toml_str.push_str(&format!(
+                "{} = {{ width = {}, height = {} }}\n",
+                toml_key(name),
+                preset.width,
+                preset.height
+            ));
         }
     }
 
-    // Apply time range filter
-    if let Some(ref time_range) = filters.time_range {
-        let after = match time_range.as_str() {
-            "1h" => Some("1 hour ago"),
-            "24h" => Some("1 day ago"),
-            "7d" => Some("7 days ago"),
-            "30d" => Some("30 days ago"),
-            _ => None,
-        };
-        if let Some(after_str) = after {
-            cmd.arg("--after").arg(after_str);
+    if !config.atuin_env.is_empty() {
+        toml_str.push_str("\n# Extra environment variables set when running atuin\n[atuin_env]\n");
+        for (key, value) in &config.atuin_env {
+            toml_str.push_str(&format!("{} = {}\n", key, toml_string(value)));
         }
     }
 
-    cmd.arg(query);
+    toml_str
+}
 
-    let output = cmd
-        .output()
-        .map_err(|e| format!("Failed to execute atuin command: {}", e))?;
+/// Write a configuration to the config file. Changes to fields overridden
+/// by the environment are refused.
+pub fn save_config(config: &Config) -> Result<(), String> {
+    let Some(config_path) = get_config_path() else {
+        return Err("Could not determine config path".to_string());
+    };
+    check_env_overridden(config, &load_config(), |name| std::env::var(name).ok())?;
 
-    if output.status.success() {
-        String::from_utf8(output.stdout).map_err(|e| format!("Failed to parse atuin output: {}", e))
-    } else {
-        let error_message = String::from_utf8_lossy(&output.stderr);
-        Err(format!("atuin command failed: {}", error_message))
+    if let Some(parent) = config_path.parent() {
+        let _ = fs::create_dir_all(parent);
     }
+    let mut config = config.clone();
+    restore_env_overridden(&mut config, &load_config_at(&config_path), |name| {
+        std::env::var(name).ok()
+    });
+    config_file::write_config(&config_path, &config)
 }
 
-// Tauri command wrapper (private)
-#[tauri::command]
-fn atuin_search_command(query: &str, filters: Option<SearchFilters>) -> Result<String, String> {
-    atuin_search(query, filters)
+/// Settings changed from the settings window; fields left `None` keep their
+/// current value
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(default)]
+struct ConfigUpdate {
+    shortcut: Option<String>,
+    theme: Option<String>,
+    max_results: Option<u32>,
+    window_width: Option<u32>,
+    window_height: Option<u32>,
+    search_cache_ttl_ms: Option<u64>,
+    search_mode: Option<String>,
+    atuin_path: Option<String>,
+    font_size: Option<u32>,
+    opacity: Option<f64>,
 }
 
 #[tauri::command]
-async fn copy_to_clipboard<R: tauri::Runtime>(
+fn update_config<R: tauri::Runtime>(
     app: tauri::AppHandle<R>,
-    text: String,
-) -> Result<(), String> {
-    app.clipboard()
-        .write_text(text)
-        .map_err(|e| format!("Failed to copy to clipboard: {}", e))
+    active_shortcut: tauri::State<'_, Mutex<ActiveShortcut>>,
+    mut update: ConfigUpdate,
+) -> Result<Config, String> {
+    let Some(config_path) = get_config_path() else {
+        return Err("Could not determine config path".to_string());
+    };
+
+    // Fields set by the environment can only be "edited" to the value they
+    // already have, which isn't written to the file
+    let overridden = apply_env_overrides(&mut Config::default(), |name| std::env::var(name).ok());
+    let current = load_config();
+    let mut edited = current.clone();
+    if let Some(shortcut) = &update.shortcut {
+        edited.shortcut = shortcut.clone();
+    }
+    if let Some(theme) = &update.theme {
+        edited.theme = theme.clone();
+    }
+    edited.max_results = update.max_results.unwrap_or(edited.max_results);
+    edited.window_width = update.window_width.unwrap_or(edited.window_width);
+    check_env_overridden(&edited, &current, |name| std::env::var(name).ok())?;
+    let unless_overridden = |field| !overridden.contains(&field);
+    update.shortcut = update.shortcut.filter(|_| unless_overridden("shortcut"));
+    update.theme = update.theme.filter(|_| unless_overridden("theme"));
+    update.max_results = update
+        .max_results
+        .filter(|_| unless_overridden("max_results"));
+    update.window_width = update
+        .window_width
+        .filter(|_| unless_overridden("window_width"));
+
+    let Some(shortcut) = update.shortcut.take() else {
+        return update_config_at(&config_path, update);
+    };
+
+    // Register the new shortcut before saving so it takes effect right away;
+    // the old one is restored if either step fails
+    let mut active = active_shortcut
+        .lock()
+        .map_err(|_| "Active shortcut lock poisoned".to_string())?;
+    let mut registrar = app.global_shortcut();
+    let mut updated = None;
+    keybindings::swap_shortcut(&mut registrar, &active.0, &shortcut, |new| {
+        update.shortcut = Some(new.to_string());
+        updated = Some(update_config_at(&config_path, update)?);
+        Ok(())
+    })?;
+    active.0 = shortcut;
+
+    let updated = updated.ok_or_else(|| "Config was not saved".to_string())?;
+    for e in keybindings::sync_extra_shortcuts(&mut registrar, &current, &updated) {
+        eprintln!("{}", e);
+    }
+    Ok(updated)
 }
 
-#[cfg_attr(mobile, tauri::mobile_entry_point)]
-pub fn run() {
-    // Load configuration
-    let config = load_config();
-    let shortcut = config.shortcut;
+/// Apply `update_config` to the config file at `path`, editing it in place
+fn update_config_at(path: &Path, update: ConfigUpdate) -> Result<Config, String> {
+    // Load current config
+    let mut config: Config = fs::read_to_string(path)
+        .ok()
+        .and_then(|contents| toml::from_str(&contents).ok())
+        .unwrap_or_default();
+    coerce_unknown_theme(&mut config);
 
-    tauri::Builder::default()
-        .plugin(tauri_plugin_opener::init())
-        .plugin(
-            tauri_plugin_global_shortcut::Builder::new()
-                .with_shortcuts([shortcut.as_str()])
-                .unwrap()
-                .with_handler(|app, _shortcut, event| {
-                    if event.state == ShortcutState::Pressed {
-                        // Toggle window visibility
-                        if let Some(window) = app.get_webview_window("main") {
-                            match window.is_visible() {
-                                Ok(visible) => {
-                                    if visible {
-                                        let _ = window.hide();
-                                    } else {
-                                        let _ = window.show();
-                                        let _ = window.set_focus();
-                                    }
-                                }
-                                Err(_) => {}
-                            }
-                        }
-                    }
-                })
+    // Update fields if provided
+    if let Some(s) = update.shortcut {
+        keybindings::validate_accelerator(&s)?;
+        config.shortcut = s;
+    }
+    if let Some(t) = update.theme {
+        if !is_valid_theme(&t) {
+            return Err(format!("unknown theme: {}", t));
+        }
+        config.theme = t;
+    }
+    if let Some(m) = update.max_results {
+        config.max_results = m;
+    }
+    if let Some(w) = update.window_width {
+        validate_window_size("window width", w)?;
+        config.window_width = w;
+    }
+    if let Some(h) = update.window_height {
+        validate_window_size("window height", h)?;
+        config.window_height = h;
+    }
+    if let Some(ttl) = update.search_cache_ttl_ms {
+        config.search_cache_ttl_ms = ttl;
+    }
+    if let Some(mode) = update.search_mode {
+        validate_search_mode(&mode)?;
+        config.search_mode = mode;
+    }
+    if let Some(p) = update.atuin_path {
+        if p.trim().is_empty() {
+            return Err("atuin_path cannot be empty".to_string());
+        }
+        config.atuin_path = p;
+    }
+    if let Some(size) = update.font_size {
+        if !FONT_SIZE_RANGE.contains(&size) {
+            return Err(format!(
+                "Invalid font size {}: expected {} to {} pixels",
+                size,
+                FONT_SIZE_RANGE.start(),
+                FONT_SIZE_RANGE.end()
+            ));
+        }
+        config.font_size = size;
+    }
+    if let Some(o) = update.opacity {
+        validate_opacity(o)?;
+        config.opacity = o;
+    }
+
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    config_file::write_config(path, &config)?;
+
+    Ok(config)
+}
+
+#[tauri::command]
+fn prune_config_defaults() -> Result<Config, String> {
+    let Some(config_path) = get_config_path() else {
+        return Err("Could not determine config path".to_string());
+    };
+
+    let contents =
+        fs::read_to_string(&config_path).map_err(|e| format!("Failed to read config: {}", e))?;
+    let pruned = config_file::prune_defaults(&contents)?;
+    config_file::write_config_file(&config_path, &pruned)?;
+
+    Ok(load_config())
+}
+
+#[tauri::command]
+fn config_status() -> ConfigStatus {
+    match get_config_path() {
+        Some(path) => config_file::config_status(&path),
+        None => ConfigStatus::Missing,
+    }
+}
+
+/// Parse `config.toml` without falling back to defaults, so the settings
+/// window can say exactly where a hand edit broke it
+#[tauri::command]
+fn validate_config() -> Result<Config, String> {
+    let Some(config_path) = get_config_path() else {
+        return Err("Could not determine config path".to_string());
+    };
+    config_file::validate_config(&config_path)
+}
+
+/// Location of `config.toml`, so the settings window can point users at it
+#[tauri::command]
+fn config_file_path() -> Option<String> {
+    get_config_path().map(|path| path.display().to_string())
+}
+
+/// Open `config.toml` in the default editor, or with `reveal` show it in the
+/// file manager. A missing file is first created with the defaults.
+#[tauri::command]
+fn open_config_file<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    reveal: Option<bool>,
+) -> Result<(), String> {
+    let Some(config_path) = get_config_path() else {
+        return Err("Could not determine config path".to_string());
+    };
+    if !config_path.exists() {
+        load_config();
+    }
+
+    let opener = app.opener();
+    let opened = if reveal.unwrap_or(false) {
+        opener.reveal_item_in_dir(&config_path)
+    } else {
+        opener.open_path(config_path.display().to_string(), None::<&str>)
+    };
+    opened.map_err(|e| format!("Failed to open {}: {}", config_path.display(), e))
+}
+
+#[tauri::command]
+fn repair_config() -> Result<Config, String> {
+    let Some(config_path) = get_config_path() else {
+        return Err("Could not determine config path".to_string());
+    };
+    config_file::repair_config(&config_path)
+}
+
+#[tauri::command]
+fn get_click_behavior() -> Result<ClickBehavior, String> {
+    let config = load_config();
+    click_behavior::validate_click_behavior(&config.click_behavior, config.allow_run)?;
+    Ok(config.click_behavior)
+}
+
+#[tauri::command]
+fn check_shortcut_permissions() -> PermissionStatus {
+    permissions::check_shortcut_permissions()
+}
+
+/// Warnings about modifiers in `accelerator` that may not work on this
+/// platform
+#[tauri::command]
+fn shortcut_warnings(accelerator: String) -> Vec<String> {
+    keybindings::shortcut_warnings(&accelerator)
+}
+
+#[tauri::command]
+fn set_shortcut<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    active_shortcut: tauri::State<'_, Mutex<ActiveShortcut>>,
+    accelerator: String,
+) -> Result<(), String> {
+    let previous = load_config();
+    let mut config = previous.clone();
+    let mut active = active_shortcut
+        .lock()
+        .map_err(|_| "Active shortcut lock poisoned".to_string())?;
+    let mut registrar = app.global_shortcut();
+
+    keybindings::swap_shortcut(&mut registrar, &active.0, &accelerator, |new| {
+        config.shortcut = new.to_string();
+        save_config(&config)
+    })?;
+    active.0 = accelerator;
+    for e in keybindings::sync_extra_shortcuts(&mut registrar, &previous, &config) {
+        eprintln!("{}", e);
+    }
+    Ok(())
+}
+
+/// Persist a window position so the window reopens there on the next launch
+fn remember_window_position(position: tauri::PhysicalPosition<i32>) -> Result<(), String> {
+    let mut config = load_config();
+    if config.window_x == Some(position.x) && config.window_y == Some(position.y) {
+        return Ok(());
+    }
+    config.window_x = Some(position.x);
+    config.window_y = Some(position.y);
+    save_config(&config)
+}
+
+#[tauri::command]
+fn save_window_position<R: tauri::Runtime>(window: tauri::WebviewWindow<R>) -> Result<(), String> {
+    let position = window
+        .outer_position()
+        .map_err(|e| format!("Failed to get window position: {}", e))?;
+    remember_window_position(position)
+}
+
+/// Move the window to its saved position, or center it when that position
+/// is no longer on any monitor
+fn restore_window_position<R: tauri::Runtime>(window: &tauri::WebviewWindow<R>) {
+    let config = load_config();
+    let (Some(x), Some(y)) = (config.window_x, config.window_y) else {
+        return;
+    };
+
+    let monitors: Vec<window_layout::MonitorArea> = window
+        .available_monitors()
+        .unwrap_or_default()
+        .iter()
+        .map(|monitor| window_layout::MonitorArea {
+            x: monitor.position().x,
+            y: monitor.position().y,
+            width: monitor.size().width,
+            height: monitor.size().height,
+        })
+        .collect();
+    let size = window
+        .outer_size()
+        .map(|size| (size.width, size.height))
+        .unwrap_or_default();
+
+    let result = match window_layout::restore_position((x, y), size, &monitors) {
+        Some((x, y)) => window.set_position(tauri::PhysicalPosition::new(x, y)),
+        None => window.center(),
+    };
+    if let Err(e) = result {
+        eprintln!("Failed to restore window position: {}", e);
+    }
+}
+
+/// Emitted to the main window after it hides when `clear_on_hide` is set,
+/// telling the frontend to clear the query and results
+pub const RESET_SEARCH_EVENT: &str = "reset-search";
+
+/// Remember where the main window is, then hide it
+fn hide_main_window<R: tauri::Runtime>(window: &tauri::WebviewWindow<R>) -> Result<(), String> {
+    if let Ok(position) = window.outer_position() {
+        if let Err(e) = remember_window_position(position) {
+            eprintln!("{}", e);
+        }
+    }
+    window
+        .hide()
+        .map_err(|e| format!("Failed to hide window: {}", e))?;
+    reset_search_on_hide(window.app_handle());
+    Ok(())
+}
+
+/// Ask the frontend to clear its search state if `clear_on_hide` is set.
+/// Every path that hides the main window goes through here.
+fn reset_search_on_hide<R: tauri::Runtime>(app: &tauri::AppHandle<R>) {
+    if !load_config().clear_on_hide {
+        return;
+    }
+    if let Err(e) = app.emit_to(MAIN_WINDOW, RESET_SEARCH_EVENT, ()) {
+        eprintln!("Failed to emit {}: {}", RESET_SEARCH_EVENT, e);
+    }
+}
+
+/// Keep the search window open when it loses focus (e.g. to copy from
+/// another window) until unpinned
+#[tauri::command]
+fn set_pinned(tracker: tauri::State<'_, Mutex<FocusTracker>>, pinned: bool) -> Result<(), String> {
+    tracker
+        .lock()
+        .map_err(|_| "Focus tracker lock poisoned".to_string())?
+        .set_pinned(pinned);
+    Ok(())
+}
+
+/// Hide the search window, keeping its query and results for when it is
+/// shown again unless `clear_on_hide` is set. The search window calls this
+/// when Escape is pressed; a global Escape shortcut would take the key away
+/// from every other app.
+#[tauri::command]
+fn hide_window<R: tauri::Runtime>(app: tauri::AppHandle<R>) -> Result<(), String> {
+    let window = app
+        .get_webview_window(MAIN_WINDOW)
+        .ok_or_else(|| "Main window not found".to_string())?;
+    hide_main_window(&window)
+}
+
+/// Size the search window for `count` results (plus the filter panel when
+/// it is shown), as configured by `auto_resize`, `max_results` and the
+/// window size
+#[tauri::command]
+fn resize_window_to_results<R: tauri::Runtime>(
+    window: tauri::WebviewWindow<R>,
+    count: u32,
+    filters_visible: Option<bool>,
+) -> Result<(), String> {
+    let config = load_config();
+    let height = window_layout::results_window_height(
+        count,
+        config.max_results,
+        config.window_height,
+        config.auto_resize,
+        filters_visible.unwrap_or(false),
+    );
+    window
+        .set_size(tauri::LogicalSize::new(config.window_width, height))
+        .map_err(|e| format!("Failed to resize window: {}", e))
+}
+
+#[tauri::command]
+fn apply_size_preset<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    name: String,
+) -> Result<(), String> {
+    let mut config = load_config();
+    let preset = size_presets::select_size_preset(&mut config, &name)?;
+
+    if let Some(window) = app.get_webview_window("main") {
+        window
+            .set_size(tauri::LogicalSize::new(preset.width, preset.height))
+            .map_err(|e| format!("Failed to resize window: {}", e))?;
+    }
+
+    save_config(&config)
+}
+
+/// Search filters for atuin queries
+#[derive(Debug, Default, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SearchFilters {
+    /// Filter by directory path
+    pub directory: Option<String>,
+    /// Filter by exit code: "success" (0), "failure" (non-0), an exact code
+    /// such as "127", or None (all)
+    pub exit_filter: Option<String>,
+    /// Start of the time range: a preset ("1h", "24h", "7d", "30d"), any
+    /// time atuin understands (e.g. "3 months ago", "2024-01-01"), or None
+    /// (all)
+    pub time_range: Option<String>,
+    /// End of the time range, in any form atuin understands (e.g.
+    /// "2024-02-01"), or None (until now)
+    #[serde(default)]
+    pub before: Option<String>,
+    /// Interpret AND/OR/NOT and parentheses in the query (default: false)
+    #[serde(default)]
+    pub boolean_query: bool,
+    /// Match the query's case exactly, or ignore case; None (the default)
+    /// is smart case, matching exactly only when the query has uppercase
+    #[serde(default)]
+    pub case_sensitive: Option<bool>,
+    /// Only keep entries whose duration is at or above this percentile
+    /// (0-100) of the fetched results, e.g. 95 for the slowest 5%
+    #[serde(default)]
+    pub duration_percentile: Option<f32>,
+    /// Only keep entries that ran for at least this many milliseconds
+    #[serde(default)]
+    pub min_duration_ms: Option<u64>,
+    /// Only keep entries that ran for at most this many milliseconds
+    #[serde(default)]
+    pub max_duration_ms: Option<u64>,
+    /// Show each command once, at its most recent run (default: false)
+    #[serde(default)]
+    pub dedup: bool,
+    /// What counts as a duplicate when `dedup` is set, one of `DEDUP_KEYS`
+    /// (default: "command")
+    #[serde(default)]
+    pub dedup_by: Option<String>,
+    /// Maximum number of results to fetch; None uses the configured
+    /// `max_results`, clamped to `fetch_limit`
+    #[serde(default)]
+    pub limit: Option<u32>,
+    /// Number of newest matches to skip, for browsing past `limit`
+    /// (default: 0)
+    #[serde(default)]
+    pub offset: Option<u32>,
+    /// atuin search mode, one of `SEARCH_MODES` (default: "prefix")
+    #[serde(default)]
+    pub search_mode: Option<String>,
+    /// Only show commands from this atuin session
+    #[serde(default)]
+    pub session: Option<String>,
+    /// Only show commands run on this host, for history synced across
+    /// machines
+    #[serde(default)]
+    pub host: Option<String>,
+    /// Result order, one of `results::SORT_ORDERS`: "recent" shows the
+    /// newest first, "oldest" the oldest first (default: "relevance", atuin's
+    /// own order)
+    #[serde(default)]
+    pub sort: Option<String>,
+}
+
+// `normalize_filters` rejects a NaN percentile, the only value not equal
+// to itself
+impl Eq for SearchFilters {}
+
+impl std::hash::Hash for SearchFilters {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        // Destructured so a new field can't be left out of the hash
+        let SearchFilters {
+            directory,
+            exit_filter,
+            time_range,
+            before,
+            boolean_query,
+            case_sensitive,
+            duration_percentile,
+            min_duration_ms,
+            max_duration_ms,
+            dedup,
+            dedup_by,
+            limit,
+            offset,
+            search_mode,
+            session,
+            host,
+            sort,
+        } = self;
+        directory.hash(state);
+        exit_filter.hash(state);
+        time_range.hash(state);
+        before.hash(state);
+        boolean_query.hash(state);
+        case_sensitive.hash(state);
+        duration_percentile.map(f32::to_bits).hash(state);
+        min_duration_ms.hash(state);
+        max_duration_ms.hash(state);
+        dedup.hash(state);
+        dedup_by.hash(state);
+        limit.hash(state);
+        offset.hash(state);
+        search_mode.hash(state);
+        session.hash(state);
+        host.hash(state);
+        sort.hash(state);
+    }
+}
+
+/// Supported values for `SearchFilters::exit_filter`
+const EXIT_FILTERS: &[&str] = &["success", "failure"];
+
+/// Supported values for `SearchFilters::dedup_by`: the command alone, or the
+/// command together with the directory it ran in
+const DEDUP_KEYS: &[&str] = &["command", "directory"];
+
+/// Shortcuts for `SearchFilters::time_range`, the `--after` value each
+/// stands for, and how many seconds back that is
+const TIME_RANGE_PRESETS: &[(&str, &str, i64)] = &[
+    ("1h", "1 hour ago", 3600),
+    ("24h", "1 day ago", 86_400),
+    ("7d", "7 days ago", 7 * 86_400),
+    ("30d", "30 days ago", 30 * 86_400),
+];
+
+/// Resolve a time range bound to seconds since the Unix epoch, for the
+/// presets and `YYYY-MM-DD[ HH:MM:SS]` dates. Other values are left to atuin
+/// and resolve to None.
+fn time_bound(value: &str, now: i64) -> Option<i64> {
+    if let Some((_, _, secs)) = TIME_RANGE_PRESETS
+        .iter()
+        .find(|(preset, _, _)| *preset == value)
+    {
+        return Some(now - secs);
+    }
+    results::parse_timestamp(value)
+        .or_else(|| results::parse_timestamp(&format!("{} 00:00:00", value)))
+}
+
+/// Supported values for `Config::search_mode`, as accepted by `atuin search`
+const SEARCH_MODES: &[&str] = &["prefix", "fuzzy", "fulltext", "skim"];
+
+/// Reject search modes atuin doesn't know
+fn validate_search_mode(mode: &str) -> Result<(), String> {
+    if SEARCH_MODES.contains(&mode) {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid search mode '{}': expected one of {}",
+            mode,
+            SEARCH_MODES.join(", ")
+        ))
+    }
+}
+
+/// Trim a filter value, mapping empty strings to None
+fn clean_filter_value(value: Option<String>) -> Option<String> {
+    value
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// Canonicalize and validate search filters.
+///
+/// Values are trimmed, `exit_filter` and time range presets are lowercased,
+/// and empty or "all" values become None. Unknown exit filters and search
+/// modes are rejected, as are ranges that end before they start; other time
+/// ranges are left for atuin to interpret.
+pub fn normalize_filters(filters: SearchFilters) -> Result<SearchFilters, String> {
+    let directory = clean_filter_value(filters.directory);
+
+    let exit_filter = clean_filter_value(filters.exit_filter)
+        .map(|v| v.to_lowercase())
+        .filter(|v| v != "all")
+        .map(|v| match v.parse::<i32>() {
+            Ok(code) => Ok(code.to_string()),
+            Err(_) if EXIT_FILTERS.contains(&v.as_str()) => Ok(v),
+            Err(_) => Err(format!(
+                "Invalid exit filter '{}': expected one of {} or an exit code",
+                v,
+                EXIT_FILTERS.join(", ")
+            )),
+        })
+        .transpose()?;
+
+    let time_range = clean_filter_value(filters.time_range)
+        .filter(|v| !v.eq_ignore_ascii_case("all"))
+        .map(|v| {
+            let lower = v.to_lowercase();
+            if TIME_RANGE_PRESETS
+                .iter()
+                .any(|(preset, _, _)| *preset == lower)
+            {
+                lower
+            } else {
+                v
+            }
+        });
+
+    let before = clean_filter_value(filters.before);
+    if let (Some(after), Some(before)) = (&time_range, &before) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or_default();
+        if let (Some(start), Some(end)) = (time_bound(after, now), time_bound(before, now)) {
+            if end < start {
+                return Err(format!(
+                    "Invalid time range: before '{}' is earlier than '{}'",
+                    before, after
+                ));
+            }
+        }
+    }
+
+    let duration_percentile = filters.duration_percentile;
+    if let Some(percentile) = duration_percentile {
+        if !(0.0..=100.0).contains(&percentile) {
+            return Err(format!(
+                "Invalid duration percentile '{}': expected a value from 0 to 100",
+                percentile
+            ));
+        }
+    }
+
+    if let (Some(min), Some(max)) = (filters.min_duration_ms, filters.max_duration_ms) {
+        if min > max {
+            return Err(format!(
+                "Invalid duration range: minimum {}ms is above maximum {}ms",
+                min, max
+            ));
+        }
+    }
+
+    if filters.limit == Some(0) {
+        return Err("Invalid limit '0': expected at least 1".to_string());
+    }
+
+    let search_mode = clean_filter_value(filters.search_mode).map(|v| v.to_lowercase());
+    if let Some(ref search_mode) = search_mode {
+        validate_search_mode(search_mode)?;
+    }
+
+    let dedup_by = clean_filter_value(filters.dedup_by).map(|v| v.to_lowercase());
+    if let Some(ref dedup_by) = dedup_by {
+        if !DEDUP_KEYS.contains(&dedup_by.as_str()) {
+            return Err(format!(
+                "Invalid dedup key '{}': expected one of {}",
+                dedup_by,
+                DEDUP_KEYS.join(", ")
+            ));
+        }
+    }
+
+    let sort = clean_filter_value(filters.sort)
+        .map(|v| v.to_lowercase())
+        .filter(|v| v != "relevance");
+    if let Some(ref sort) = sort {
+        if !results::SORT_ORDERS.contains(&sort.as_str()) {
+            return Err(format!(
+                "Invalid sort '{}': expected one of {}",
+                sort,
+                results::SORT_ORDERS.join(", ")
+            ));
+        }
+    }
+
+    Ok(SearchFilters {
+        directory,
+        exit_filter,
+        time_range,
+        before,
+        boolean_query: filters.boolean_query,
+        case_sensitive: filters.case_sensitive,
+        duration_percentile,
+        min_duration_ms: filters.min_duration_ms,
+        max_duration_ms: filters.max_duration_ms,
+        dedup: filters.dedup,
+        dedup_by,
+        limit: filters.limit,
+        offset: filters.offset.filter(|&offset| offset > 0),
+        search_mode,
+        session: clean_filter_value(filters.session),
+        host: clean_filter_value(filters.host),
+        sort,
+    })
+}
+
+#[tauri::command]
+fn normalize_filters_command(filters: SearchFilters) -> Result<SearchFilters, String> {
+    normalize_filters(filters)
+}
+
+/// Number of results fetched when a search doesn't set a limit
+const SEARCH_LIMIT: u32 = 50;
+
+/// Number of entries first fetched for a search whose results are filtered
+/// after fetching. The sample grows until enough results survive the
+/// filters or history runs out.
+const POST_FILTER_SAMPLE: u32 = 1000;
+
+/// Factor the post-filter sample grows by on each attempt
+const POST_FILTER_SAMPLE_GROWTH: u32 = 4;
+
+/// Format of the `atuin search` output lines results are parsed from
+const SEARCH_FORMAT: &str = "{command}|{exit}|{duration}|{directory}|{time}";
+
+/// Fields put in front of `SEARCH_FORMAT` when filtering by host or session,
+/// which atuin's CLI can't do itself
+const HOST_SESSION_FORMAT: &str = "{host}|{session}|";
+
+/// The host and session a search is restricted to, ignoring empty ones
+fn host_session_filters(filters: &SearchFilters) -> (Option<&str>, Option<&str>) {
+    (
+        filters.host.as_deref().filter(|host| !host.is_empty()),
+        filters
+            .session
+            .as_deref()
+            .filter(|session| !session.is_empty()),
+    )
+}
+
+/// Arguments for `atuin search` for a query and (normalized) filters,
+/// fetching up to `limit` entries `offset` back from the newest, or every
+/// match when `limit` is None. The filters' own limit and offset are ignored.
+fn search_args(
+    query: &str,
+    filters: &SearchFilters,
+    limit: Option<u32>,
+    offset: u32,
+) -> Vec<String> {
+    let (host, session) = host_session_filters(filters);
+    let mut args = vec![
+        "search".to_string(),
+        "--search-mode".to_string(),
+        filters
+            .search_mode
+            .as_deref()
+            .unwrap_or("prefix")
+            .to_string(),
+    ];
+    if let Some(limit) = limit {
+        args.push("--limit".to_string());
+        args.push(limit.to_string());
+    }
+    args.push("--format".to_string());
+    args.push(if host.is_some() || session.is_some() {
+        format!("{}{}", HOST_SESSION_FORMAT, SEARCH_FORMAT)
+    } else {
+        SEARCH_FORMAT.to_string()
+    });
+
+    if offset > 0 && limit.is_some() {
+        args.push("--offset".to_string());
+        args.push(offset.to_string());
+    }
+    args.extend(filter_args(filters));
+    // End of options, so a query like `--help` is searched for rather than
+    // parsed as a flag
+    args.push("--".to_string());
+    args.push(query.to_string());
+    args
+}
+
+/// Expand a leading `~` to `home` and `$VAR` or `${VAR}` references through
+/// `var`. Unset variables are left as written.
+fn expand_directory(
+    dir: &str,
+    home: Option<&Path>,
+    var: impl Fn(&str) -> Option<String>,
+) -> String {
+    let mut expanded = String::with_capacity(dir.len());
+    let mut rest = dir;
+    if let Some(home) = home {
+        if rest == "~" || rest.starts_with("~/") {
+            expanded.push_str(&home.to_string_lossy());
+            rest = &rest[1..];
+        }
+    }
+
+    while let Some(start) = rest.find('$') {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let (name, reference_len) = match after.strip_prefix('{') {
+            Some(braced) => match braced.find('}') {
+                Some(end) => (&braced[..end], end + 2),
+                None => ("", 0),
+            },
+            None => {
+                let end = after
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(after.len());
+                (&after[..end], end)
+            }
+        };
+        match var(name).filter(|_| !name.is_empty()) {
+            Some(value) => expanded.push_str(&value),
+            None => expanded.push_str(&rest[start..start + 1 + reference_len]),
+        }
+        rest = &after[reference_len..];
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+/// `atuin search` arguments restricting results to the given filters. Host
+/// and session have no `atuin search` option, so aren't included; see
+/// `filter_host_session`.
+pub(crate) fn filter_args(filters: &SearchFilters) -> Vec<String> {
+    let mut args = Vec::new();
+
+    // Apply directory filter; atuin stores absolute paths
+    if let Some(ref dir) = filters.directory {
+        if !dir.is_empty() {
+            args.push("--cwd".to_string());
+            args.push(expand_directory(dir, dirs::home_dir().as_deref(), |name| {
+                std::env::var(name).ok()
+            }));
+        }
+    }
+
+    // Apply exit code filter
+    if let Some(ref exit_filter) = filters.exit_filter {
+        match exit_filter.as_str() {
+            "success" => args.extend(["--exit".to_string(), "0".to_string()]),
+            "failure" => args.extend(["--exclude-exit".to_string(), "0".to_string()]),
+            code => {
+                if let Ok(code) = code.parse::<i32>() {
+                    args.extend(["--exit".to_string(), code.to_string()]);
+                }
+            }
+        }
+    }
+
+    // Apply time range filter, expanding presets
+    if let Some(ref time_range) = filters.time_range {
+        let time_range = time_range.trim();
+        if !time_range.is_empty() {
+            let after = TIME_RANGE_PRESETS
+                .iter()
+                .find(|(preset, _, _)| *preset == time_range)
+                .map_or(time_range, |(_, after, _)| after);
+            args.push("--after".to_string());
+            args.push(after.to_string());
+        }
+    }
+    if let Some(ref before) = filters.before {
+        let before = before.trim();
+        if !before.is_empty() {
+            args.push("--before".to_string());
+            args.push(before.to_string());
+        }
+    }
+
+    args
+}
+
+/// The arguments `atuin search` is first run with for a query and
+/// (normalized) filters, as `search_output` runs it. An invalid search mode
+/// falls back to prefix, and a valid boolean query fetches unfiltered
+/// history since it's applied to the results.
+pub fn build_atuin_command(query: &str, filters: &SearchFilters) -> Vec<String> {
+    let mut filters = filters.clone();
+    fall_back_to_prefix_mode(&mut filters);
+    PostFilters::new(query, &filters).first_args(&filters)
+}
+
+/// Clear a search mode atuin doesn't know, so the search runs in prefix mode
+fn fall_back_to_prefix_mode(filters: &mut SearchFilters) {
+    if let Some(mode) = filters.search_mode.take() {
+        match validate_search_mode(&mode) {
+            Ok(()) => filters.search_mode = Some(mode),
+            Err(e) => eprintln!("Searching in prefix mode: {}", e),
+        }
+    }
+}
+
+/// The filters of a search that atuin can't apply, so are applied to its
+/// output
+struct PostFilters<'a> {
+    query: &'a str,
+    search_mode: &'a str,
+    boolean_query: Option<boolean_query::BooleanQuery>,
+    case_sensitive: bool,
+    host: Option<&'a str>,
+    session: Option<&'a str>,
+    duration_bounds: Option<(u64, u64)>,
+    duration_percentile: Option<f32>,
+    /// Whether to dedup, and if so whether per directory
+    dedup_by_directory: Option<bool>,
+    sort: Option<&'a str>,
+}
+
+impl<'a> PostFilters<'a> {
+    /// Post-filters for `query` and `filters`, whose search mode has been
+    /// validated. An invalid boolean query is searched for literally and an
+    /// invalid percentile ignored.
+    fn new(query: &'a str, filters: &'a SearchFilters) -> Self {
+        let boolean_query = if filters.boolean_query {
+            match boolean_query::parse_boolean_query(query) {
+                Ok(expr) => Some(expr),
+                Err(e) => {
+                    eprintln!("Invalid boolean query, searching literally: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        // atuin ignores case, so exact-case matching narrows its results. The
+        // uppercase operators of boolean queries don't count towards smart
+        // case.
+        let case_sensitive = !filters.boolean_query
+            && !query.is_empty()
+            && filters
+                .case_sensitive
+                .unwrap_or_else(|| query.chars().any(char::is_uppercase));
+
+        let duration_percentile = filters.duration_percentile.filter(|p| {
+            let valid = (0.0..=100.0).contains(p);
+            if !valid {
+                eprintln!("Ignoring invalid duration percentile: {}", p);
+            }
+            valid
+        });
+
+        let (host, session) = host_session_filters(filters);
+        PostFilters {
+            query,
+            search_mode: filters.search_mode.as_deref().unwrap_or("prefix"),
+            boolean_query,
+            case_sensitive,
+            host,
+            session,
+            duration_bounds: (filters.min_duration_ms.is_some()
+                || filters.max_duration_ms.is_some())
+            .then(|| {
+                (
+                    filters.min_duration_ms.unwrap_or(0),
+                    filters.max_duration_ms.unwrap_or(u64::MAX),
+                )
+            }),
+            duration_percentile,
+            dedup_by_directory: filters
+                .dedup
+                .then(|| filters.dedup_by.as_deref() == Some("directory")),
+            sort: filters.sort.as_deref(),
+        }
+    }
+
+    /// The query atuin is run with. Boolean queries are applied to the
+    /// results, so fetch unfiltered history for them.
+    fn atuin_query(&self) -> &'a str {
+        if self.boolean_query.is_some() {
+            ""
+        } else {
+            self.query
+        }
+    }
+
+    /// Whether these drop some of atuin's results, so a page of them would
+    /// leave too few
+    fn narrows(&self) -> bool {
+        self.boolean_query.is_some()
+            || self.case_sensitive
+            || self.host.is_some()
+            || self.session.is_some()
+            || self.duration_bounds.is_some()
+            || self.duration_percentile.is_some()
+            || self.dedup_by_directory.is_some()
+    }
+
+    /// The limit and offset atuin is first run with for `filters`. atuin's
+    /// limit and offset count entries before these filters, so when they
+    /// drop some a sample of the newest entries is fetched and windowed
+    /// afterwards. Listing the oldest first needs every match, which a None
+    /// limit fetches.
+    fn first_fetch(&self, filters: &SearchFilters) -> (Option<u32>, u32) {
+        let limit = filters.limit.unwrap_or(SEARCH_LIMIT);
+        let offset = filters.offset.unwrap_or(0);
+        if self.sort == Some("oldest") {
+            (None, 0)
+        } else if self.narrows() {
+            (
+                Some(POST_FILTER_SAMPLE.max(offset.saturating_add(limit))),
+                0,
+            )
+        } else {
+            (Some(limit), offset)
+        }
+    }
+
+    /// The arguments atuin is first run with for `filters`
+    fn first_args(&self, filters: &SearchFilters) -> Vec<String> {
+        let (limit, offset) = self.first_fetch(filters);
+        search_args(self.atuin_query(), filters, limit, offset)
+    }
+
+    /// Apply the filters to atuin output
+    fn apply(&self, mut output: String) -> String {
+        if self.host.is_some() || self.session.is_some() {
+            output = filter_host_session(&output, self.host, self.session);
+        }
+        if let Some(ref expr) = self.boolean_query {
+            output = filter_output_lines(&output, |entry| expr.matches(&entry.command));
+        }
+        if self.case_sensitive {
+            output = filter_output_lines(&output, |entry| {
+                matches_case(&entry.command, self.query, self.search_mode)
+            });
+        }
+        // atuin can't filter on duration, so bounds are applied to the
+        // results. Entries whose duration doesn't parse can't be placed and
+        // are dropped.
+        if let Some((min, max)) = self.duration_bounds {
+            output = filter_output_lines(&output, |entry| {
+                results::parse_duration_ms(&entry.duration)
+                    .is_some_and(|ms| (min..=max).contains(&ms))
+            });
+        }
+        if let Some(percentile) = self.duration_percentile {
+            let durations: Vec<u64> = results::parse_atuin_output(&output)
+                .iter()
+                .filter_map(|entry| results::parse_duration_ms(&entry.duration))
+                .collect();
+            let threshold = analysis::duration_percentile_threshold(&durations, percentile);
+            output = filter_output_lines(&output, |entry| {
+                threshold.is_some_and(|threshold| {
+                    results::parse_duration_ms(&entry.duration).is_some_and(|ms| ms >= threshold)
+                })
+            });
+        }
+        if let Some(by_directory) = self.dedup_by_directory {
+            output = dedup_output_lines(&output, by_directory);
+        }
+        if let Some(sort) = self.sort {
+            output = sort_output_lines(&output, sort);
+        }
+        output
+    }
+}
+
+/// A search's output lines, with what was lost reading them from atuin
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchOutput {
+    pub output: String,
+    pub loss: OutputLoss,
+}
+
+/// Run a search through `backend` and return the raw (post-filtered) atuin
+/// output lines. This is the pipeline shared by the Tauri commands and tests.
+pub fn search_output_with_backend(
+    query: &str,
+    filters: Option<SearchFilters>,
+    backend: &dyn HistoryBackend,
+) -> Result<String, SearchError> {
+    search_output(query, filters.unwrap_or_default(), false, backend).map(|found| found.output)
+}
+
+/// `search_output_with_backend` for every match, ignoring the filters'
+/// limit and offset, for analyses that need the whole of a range
+pub fn search_all_output_with_backend(
+    query: &str,
+    filters: Option<SearchFilters>,
+    backend: &dyn HistoryBackend,
+) -> Result<String, SearchError> {
+    search_output(query, filters.unwrap_or_default(), true, backend).map(|found| found.output)
+}
+
+/// Run atuin through `backend`, making do with the lines that fit when its
+/// output is cut off at `max_output_bytes`, and note what was lost
+fn run_keeping_partial(
+    backend: &dyn HistoryBackend,
+    args: &[String],
+    loss: &mut OutputLoss,
+) -> Result<String, SearchError> {
+    let result = match backend.run(args) {
+        Err(SearchError::OutputTruncated { output, .. }) => {
+            loss.truncated = true;
+            Ok(output)
+        }
+        result => result,
+    };
+    loss.skipped_lines = backend.last_skipped_lines();
+    result
+}
+
+/// The search pipeline, for every match when `all` is set
+fn search_output(
+    query: &str,
+    mut filters: SearchFilters,
+    all: bool,
+    backend: &dyn HistoryBackend,
+) -> Result<SearchOutput, SearchError> {
+    fall_back_to_prefix_mode(&mut filters);
+    let post = PostFilters::new(query, &filters);
+    let atuin_query = post.atuin_query();
+    let mut loss = OutputLoss::default();
+
+    if all {
+        let args = search_args(atuin_query, &filters, None, 0);
+        let output = post.apply(run_keeping_partial(backend, &args, &mut loss)?);
+        return Ok(SearchOutput { output, loss });
+    }
+    let limit = filters.limit.unwrap_or(SEARCH_LIMIT);
+    let offset = filters.offset.unwrap_or(0);
+    // The first run is the one `build_atuin_command` previews
+    let first_args = post.first_args(&filters);
+    let (Some(mut sample), _) = post.first_fetch(&filters) else {
+        let output = post.apply(run_keeping_partial(backend, &first_args, &mut loss)?);
+        let output = window_output_lines(&output, offset, limit);
+        return Ok(SearchOutput { output, loss });
+    };
+    if !post.narrows() {
+        let output = post.apply(run_keeping_partial(backend, &first_args, &mut loss)?);
+        return Ok(SearchOutput { output, loss });
+    }
+
+    // The filters drop an unknown share of atuin's results, so fetch a
+    // growing sample of the newest entries until enough survive to fill the
+    // window or history runs out
+    let wanted = offset.saturating_add(limit) as usize;
+    let mut filtered = None;
+    loop {
+        let output = match backend.run(&search_args(atuin_query, &filters, Some(sample), 0)) {
+            Ok(output) => output,
+            // Too much to fetch at once; make do with the previous sample,
+            // or what fit of the first
+            Err(SearchError::OutputTruncated { output, .. }) => {
+                loss.truncated = true;
+                if filtered.is_none() {
+                    loss.skipped_lines = backend.last_skipped_lines();
+                    filtered = Some(post.apply(output));
+                }
+                break;
+            }
+            Err(e) => return Err(e),
+        };
+        loss.skipped_lines = backend.last_skipped_lines();
+        let exhausted = results::atuin_records(&output).len() < sample as usize;
+        let output = post.apply(output);
+        let enough = results::atuin_records(&output).len() >= wanted;
+        filtered = Some(output);
+        if exhausted || enough || sample == u32::MAX {
+            break;
+        }
+        sample = sample.saturating_mul(POST_FILTER_SAMPLE_GROWTH);
+    }
+    let output = window_output_lines(&filtered.unwrap_or_default(), offset, limit);
+    Ok(SearchOutput { output, loss })
+}
+
+/// Run a search through `backend`, fetching at least `fetch_limit` entries
+/// so dedup and duration filters have spare rows to draw from, and return
+/// the newest `filters.limit` that remain
+pub fn search_output_with_fetch_limit(
+    query: &str,
+    filters: Option<SearchFilters>,
+    fetch_limit: u32,
+    backend: &dyn HistoryBackend,
+) -> Result<String, SearchError> {
+    fetch_limited_output(query, filters, fetch_limit, backend).map(|found| found.output)
+}
+
+/// `search_output_with_fetch_limit`, with what was lost reading the output
+fn fetch_limited_output(
+    query: &str,
+    filters: Option<SearchFilters>,
+    fetch_limit: u32,
+    backend: &dyn HistoryBackend,
+) -> Result<SearchOutput, SearchError> {
+    let (filters, limit) = fetch_filters(filters.unwrap_or_default(), fetch_limit);
+    let found = search_output(query, filters, false, backend)?;
+    Ok(SearchOutput {
+        output: newest_output_lines(&found.output, limit),
+        loss: found.loss,
+    })
+}
+
+/// Filters raising the limit to `fetch_limit`, returned with the number of
+/// results the search asked for
+fn fetch_filters(mut filters: SearchFilters, fetch_limit: u32) -> (SearchFilters, u32) {
+    let limit = filters.limit.unwrap_or(SEARCH_LIMIT);
+    filters.limit = Some(fetch_limit.max(limit));
+    (filters, limit)
+}
+
+/// Run a search through `backend` and parse the results
+pub fn search_with_backend(
+    query: &str,
+    filters: Option<SearchFilters>,
+    backend: &dyn HistoryBackend,
+) -> Result<Vec<HistoryEntry>, SearchError> {
+    let output = search_output_with_backend(query, filters, backend)?;
+    Ok(results::parse_atuin_output(&output))
+}
+
+/// Filters asking for one more result than the window holds, which tells
+/// whether there are older results past it. Returns them with the window
+/// size.
+fn window_filters(mut filters: SearchFilters, default_limit: u32) -> (SearchFilters, u32) {
+    let limit = filters.limit.unwrap_or(default_limit);
+    filters.limit = Some(limit.saturating_add(1));
+    (filters, limit)
+}
+
+/// Run a search through `backend` for the window of up to `limit` results
+/// starting `offset` results back from the newest match
+pub fn search_window_with_backend(
+    query: &str,
+    filters: Option<SearchFilters>,
+    backend: &dyn HistoryBackend,
+) -> Result<ResultWindow<HistoryEntry>, SearchError> {
+    let (filters, limit) = window_filters(filters.unwrap_or_default(), SEARCH_LIMIT);
+    let offset = filters.offset.unwrap_or(0);
+    let entries = search_with_backend(query, Some(filters), backend)?;
+    Ok(paging::result_window(entries, offset, limit))
+}
+
+/// Separator written after each command when checking for exact matches, so
+/// multi-line commands can be told apart from several single-line ones
+const RECORD_SEPARATOR: char = '\u{1e}';
+
+/// Whether `command` appears in history exactly as given (not merely as a
+/// prefix or substring of a longer command)
+pub fn verify_in_history_with_backend(
+    command: &str,
+    backend: &dyn HistoryBackend,
+) -> Result<bool, SearchError> {
+    if command.is_empty() {
+        return Ok(false);
+    }
+    let output = backend.run(&[
+        "search".to_string(),
+        "--search-mode".to_string(),
+        "prefix".to_string(),
+        "--format".to_string(),
+        format!("{{command}}{}", RECORD_SEPARATOR),
+        "--".to_string(),
+        command.to_string(),
+    ])?;
+    Ok(output
+        .split(RECORD_SEPARATOR)
+        .any(|found| found.strip_prefix('\n').unwrap_or(found) == command))
+}
+
+/// Filters with the configured defaults filled in. An explicit `limit` wins
+/// over one already in the filters, and `max_results` (clamped to
+/// `fetch_limit`) is used when neither is set; the search mode defaults to
+/// the configured one.
+fn resolve_filters(
+    filters: Option<SearchFilters>,
+    limit: Option<u32>,
+    config: &Config,
+) -> SearchFilters {
+    let mut filters = filters.unwrap_or_default();
+    filters.limit = Some(
+        limit
+            .or(filters.limit)
+            .unwrap_or_else(|| result_limit(config)),
+    );
+    if filters.search_mode.is_none() {
+        filters.search_mode = Some(config.search_mode.clone());
+    }
+    filters
+}
+
+// Public function that can be called from integration tests. A missing atuin
+// is reported as `SearchError::AtuinNotInstalled`, separately from other
+// failures.
+pub fn atuin_search(
+    query: &str,
+    filters: Option<SearchFilters>,
+    limit: Option<u32>,
+) -> Result<String, SearchError> {
+    atuin_search_output(query, filters, limit).map(|found| found.output)
+}
+
+/// `atuin_search`, with what was lost reading atuin's output
+fn atuin_search_output(
+    query: &str,
+    filters: Option<SearchFilters>,
+    limit: Option<u32>,
+) -> Result<SearchOutput, SearchError> {
+    let config = load_config();
+    let filters = search_filters(filters, limit, &config)?;
+    let backend = search_backend(&config)?;
+    fetch_limited_output(query, Some(filters), config.fetch_limit, &*backend)
+}
+
+/// Normalize a search's filters and fill in the config defaults
+fn search_filters(
+    filters: Option<SearchFilters>,
+    limit: Option<u32>,
+    config: &Config,
+) -> Result<SearchFilters, SearchError> {
+    let filters = filters
+        .map(normalize_filters)
+        .transpose()
+        .map_err(|message| SearchError::InvalidFilter { message })?;
+    Ok(resolve_filters(filters, limit, config))
+}
+
+/// The arguments `atuin_search` would run atuin with, without running it
+pub fn preview_atuin_command(
+    query: &str,
+    filters: Option<SearchFilters>,
+    limit: Option<u32>,
+) -> Result<Vec<String>, SearchError> {
+    let config = load_config();
+    let filters = search_filters(filters, limit, &config)?;
+    let (filters, _) = fetch_filters(filters, config.fetch_limit);
+    Ok(build_atuin_command(query, &filters))
+}
+
+/// `atuin_search` for every matching entry rather than a page of them, for
+/// analyses that need the whole of a range. The output isn't capped, as a
+/// truncated sample would skew them.
+pub fn atuin_search_all(
+    query: &str,
+    filters: Option<SearchFilters>,
+) -> Result<String, SearchError> {
+    let config = load_config();
+    let filters = search_filters(filters, None, &config)?;
+    let backend = search_backend(&Config {
+        max_output_bytes: 0,
+        ..config
+    })?;
+    search_all_output_with_backend(query, Some(filters), &*backend)
+}
+
+/// `atuin_search` on a blocking thread, so waiting for atuin doesn't hold up
+/// the async runtime and several searches can be in flight at once
+pub async fn atuin_search_async(
+    query: String,
+    filters: Option<SearchFilters>,
+    limit: Option<u32>,
+) -> Result<String, SearchError> {
+    tauri::async_runtime::spawn_blocking(move || atuin_search(&query, filters, limit))
+        .await
+        .map_err(|e| SearchError::Internal {
+            message: format!("Search task failed: {}", e),
+        })?
+}
+
+/// The backend searches run through: atuin's database when `backend` is
+/// "sqlite" and it can be opened, otherwise the atuin CLI
+pub(crate) fn search_backend(config: &Config) -> Result<Box<dyn HistoryBackend>, SearchError> {
+    let cli = CommandBackend::new(
+        &config.atuin_path,
+        config.use_login_shell,
+        config.atuin_env.clone(),
+        config.search_timeout_ms,
+    )
+    .map_err(|message| SearchError::InvalidConfig { message })?
+    .with_max_output_bytes(config.max_output_bytes);
+
+    match config.backend.as_str() {
+        "cli" => return Ok(Box::new(cli)),
+        "sqlite" => {}
+        other => {
+            eprintln!("Unknown backend '{}', searching with the atuin CLI", other);
+            return Ok(Box::new(cli));
+        }
+    }
+
+    let db = sqlite_backend::history_db_path(&config.atuin_env)
+        .ok_or_else(|| "Could not determine the atuin database path".to_string())
+        .and_then(|path| sqlite_backend::open_history_db(&path));
+    match db {
+        Ok(conn) => Ok(Box::new(SqliteBackend::new(conn, cli))),
+        Err(e) => {
+            eprintln!("Searching with the atuin CLI: {}", e);
+            Ok(Box::new(cli))
+        }
+    }
+}
+
+/// Whether `command` matches `query` with the same case, the way the search
+/// mode matches: from the start for "prefix", anywhere for "fulltext", and
+/// every term anywhere for the fuzzy modes (negated `!terms` are skipped)
+fn matches_case(command: &str, query: &str, search_mode: &str) -> bool {
+    match search_mode {
+        "prefix" => command.starts_with(query),
+        "fulltext" => command.contains(query),
+        _ => query
+            .split_whitespace()
+            .filter(|term| !term.starts_with('!'))
+            .map(|term| term.trim_start_matches(['^', '\'']).trim_end_matches('$'))
+            .all(|term| command.contains(term)),
+    }
+}
+
+/// Keep the records of atuin output whose entry satisfies `keep`, dropping
+/// text that can't be parsed
+fn filter_output_lines(output: &str, keep: impl Fn(&HistoryEntry) -> bool) -> String {
+    results::atuin_records(output)
+        .into_iter()
+        .filter(|(_, entry)| keep(entry))
+        .map(|(text, _)| format!("{}\n", text))
+        .collect()
+}
+
+/// Keep the records of `HOST_SESSION_FORMAT` output run on `host` and in
+/// `session`, with the host and session fields stripped off
+fn filter_host_session(output: &str, host: Option<&str>, session: Option<&str>) -> String {
+    results::atuin_records(output)
+        .into_iter()
+        .filter_map(|(text, _)| {
+            let mut fields = text.splitn(3, '|');
+            let (entry_host, entry_session, record) =
+                (fields.next()?, fields.next()?, fields.next()?);
+            let matches = host.is_none_or(|host| host == entry_host)
+                && session.is_none_or(|session| session == entry_session);
+            matches.then(|| format!("{}\n", record))
+        })
+        .collect()
+}
+
+/// Keep the window of `limit` records starting `offset` back from the
+/// newest, of atuin output listing them oldest first
+fn window_output_lines(output: &str, offset: u32, limit: u32) -> String {
+    let records = results::atuin_records(output);
+    let end = records.len().saturating_sub(offset as usize);
+    let start = end.saturating_sub(limit as usize);
+    records[start..end]
+        .iter()
+        .map(|(text, _)| format!("{}\n", text))
+        .collect()
+}
+
+/// Keep the `limit` newest records of atuin output, which lists them oldest
+/// first
+fn newest_output_lines(output: &str, limit: u32) -> String {
+    let records = results::atuin_records(output);
+    let Some(excess) = records.len().checked_sub(limit as usize).filter(|&n| n > 0) else {
+        return output.to_string();
+    };
+    records
+        .into_iter()
+        .skip(excess)
+        .map(|(text, _)| format!("{}\n", text))
+        .collect()
+}
+
+/// Reorder atuin output for `SearchFilters::sort`. Output lists records
+/// oldest first and the search window shows them reversed, so records are
+/// sorted in the order they are shown and then reversed back.
+fn sort_output_lines(output: &str, sort: &str) -> String {
+    let newest_first = match sort {
+        "recent" => true,
+        "oldest" => false,
+        _ => return output.to_string(),
+    };
+    let mut records = results::atuin_records(output);
+    results::sort_by_time(&mut records, |(_, entry)| entry, newest_first);
+    records
+        .into_iter()
+        .rev()
+        .map(|(text, _)| format!("{}\n", text))
+        .collect()
+}
+
+/// Keep only the most recent record of each command in atuin output
+fn dedup_output_lines(output: &str, by_directory: bool) -> String {
+    results::dedup_records(results::atuin_records(output), by_directory)
+        .into_iter()
+        .map(|(text, _)| format!("{}\n", text))
+        .collect()
+}
+
+/// Run a search through the cache, remembering the filters if enabled
+fn cached_search(
+    cache: &Mutex<SearchCache>,
+    query: &str,
+    filters: Option<SearchFilters>,
+    limit: Option<u32>,
+) -> Result<SearchOutput, SearchError> {
+    let config = load_config();
+    let ttl = Duration::from_millis(config.search_cache_ttl_ms);
+    let filters = resolve_filters(filters, limit, &config);
+
+    let lock_cache = || {
+        cache.lock().map_err(|_| SearchError::Internal {
+            message: "Search cache lock poisoned".to_string(),
+        })
+    };
+    let now = Instant::now();
+    if let Some(found) = lock_cache()?.get(query, &filters, ttl, now) {
+        return Ok(found.clone());
+    }
+    // The cache isn't locked while atuin runs, so searches don't queue up
+    // behind each other
+    let found = atuin_search_output(query, Some(filters.clone()), None)?;
+    lock_cache()?.insert(query, &filters, found.clone(), ttl, now);
+    Ok(found)
+}
+
+/// Remember a search's results so they can be picked by position.
+///
+/// `entries` are in atuin's order (oldest first), which every result view
+/// lists in reverse, so they are remembered newest first to match the
+/// positions on screen.
+fn remember_results(last: &Mutex<LastResults>, entries: &[HistoryEntry]) -> Result<(), String> {
+    let displayed: Vec<HistoryEntry> = entries.iter().rev().cloned().collect();
+    last.lock()
+        .map_err(|_| "Last results lock poisoned".to_string())?
+        .set(&displayed);
+    Ok(())
+}
+
+/// Number searches afresh; the frontend's ids start over when its page is
+/// reloaded, and would otherwise all count as superseded
+#[tauri::command]
+fn reset_search_sequence(sequence: tauri::State<'_, Mutex<SearchSequence>>) -> Result<(), String> {
+    sequence
+        .lock()
+        .map_err(|_| "Search sequence lock poisoned".to_string())?
+        .reset();
+    Ok(())
+}
+
+/// Fail if a newer search than `request_id` has started
+fn ensure_current_search<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    request_id: u64,
+) -> Result<(), SearchError> {
+    app.state::<Mutex<SearchSequence>>()
+        .lock()
+        .map_err(|_| SearchError::Internal {
+            message: "Search sequence lock poisoned".to_string(),
+        })?
+        .ensure_current(request_id)
+}
+
+/// Search through the cache and remember the results in display order,
+/// unless a newer search has started in the meantime
+fn search_entries<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    request_id: u64,
+    query: &str,
+    filters: Option<SearchFilters>,
+    limit: Option<u32>,
+) -> Result<(Vec<ParsedHistoryEntry>, OutputLoss), SearchError> {
+    // Skip searches overtaken while waiting for a thread
+    ensure_current_search(app, request_id)?;
+    let first_window = filters.as_ref().and_then(|f| f.offset).unwrap_or(0) == 0;
+    let found = cached_search(&app.state::<Mutex<SearchCache>>(), query, filters, limit)?;
+    ensure_current_search(app, request_id)?;
+    let entries = results::parse_atuin_output(&found.output);
+    // Later windows are appended below the first, so the positions of the
+    // first one stay valid
+    if first_window {
+        remember_results(&app.state::<Mutex<LastResults>>(), &entries)
+            .map_err(|message| SearchError::Internal { message })?;
+    }
+    Ok((entries, found.loss))
+}
+
+/// Search history and return the parsed entries, so the frontend never has
+/// to split atuin's `|`-delimited output. Runs on a blocking thread so a
+/// slow search doesn't hold up the ones typed after it.
+///
+/// `request_id` orders overlapping searches (one is generated when it's
+/// omitted): a search overtaken by a newer one fails with
+/// `SearchError::Superseded` instead of returning stale results.
+#[tauri::command]
+async fn atuin_search_json<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    query: String,
+    filters: Option<SearchFilters>,
+    limit: Option<u32>,
+    request_id: Option<u64>,
+) -> Result<Vec<ParsedHistoryEntry>, SearchError> {
+    let (entries, _) = search_entries_async(app, query, filters, limit, request_id).await?;
+    Ok(entries)
+}
+
+/// `search_entries` on a blocking thread, ordered against the other searches
+async fn search_entries_async<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    query: String,
+    filters: Option<SearchFilters>,
+    limit: Option<u32>,
+    request_id: Option<u64>,
+) -> Result<(Vec<ParsedHistoryEntry>, OutputLoss), SearchError> {
+    let request_id = app
+        .state::<Mutex<SearchSequence>>()
+        .lock()
+        .map_err(|_| SearchError::Internal {
+            message: "Search sequence lock poisoned".to_string(),
+        })?
+        .start(request_id);
+    tauri::async_runtime::spawn_blocking(move || {
+        search_entries(&app, request_id, &query, filters, limit)
+    })
+    .await
+    .map_err(|e| SearchError::Internal {
+        message: format!("Search task failed: {}", e),
+    })?
+}
+
+/// Search for a window of `max_results` parsed entries starting `offset`
+/// results back from the newest match, with whether older results follow
+/// so the frontend can offer to load more, and whether any were lost
+/// reading atuin's output
+#[tauri::command]
+async fn atuin_search_window<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    query: String,
+    filters: Option<SearchFilters>,
+    offset: Option<u32>,
+    request_id: Option<u64>,
+) -> Result<ResultWindow<ParsedHistoryEntry>, SearchError> {
+    let mut filters = filters.unwrap_or_default();
+    filters.offset = offset;
+    let (filters, limit) = window_filters(filters, result_limit(&load_config()));
+    let (entries, loss) = search_entries_async(app, query, Some(filters), None, request_id).await?;
+    Ok(ResultWindow {
+        loss,
+        ..paging::result_window(entries, offset.unwrap_or(0), limit)
+    })
+}
+
+/// Directory atuin-bar was launched from, the default for
+/// `search_in_current_dir` since the terminal's own cwd isn't known
+#[derive(Debug)]
+pub struct LaunchDirectory(pub Option<String>);
+
+#[tauri::command]
+fn launch_directory(launch_dir: tauri::State<'_, LaunchDirectory>) -> Option<String> {
+    launch_dir.0.clone()
+}
+
+/// Filters restricting a search to `dir`, or None when it is missing or
+/// blank
+fn current_dir_filters(dir: Option<String>) -> Option<SearchFilters> {
+    clean_filter_value(dir).map(|directory| SearchFilters {
+        directory: Some(directory),
+        ..Default::default()
+    })
+}
+
+/// Search history for commands run in `dir` ("what have I run here?")
+#[tauri::command]
+async fn search_in_current_dir<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    query: String,
+    dir: Option<String>,
+    request_id: Option<u64>,
+) -> Result<Vec<ParsedHistoryEntry>, SearchError> {
+    atuin_search_json(app, query, current_dir_filters(dir), None, request_id).await
+}
+
+/// The arguments a search would run atuin with, without running it
+#[tauri::command]
+fn preview_search_command(
+    query: String,
+    filters: Option<SearchFilters>,
+    limit: Option<u32>,
+) -> Result<Vec<String>, SearchError> {
+    preview_atuin_command(&query, filters, limit)
+}
+
+// Tauri command wrapper (private), kept for compatibility with the
+// `|`-delimited output format
+#[tauri::command]
+async fn atuin_search_command<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    query: String,
+    filters: Option<SearchFilters>,
+    limit: Option<u32>,
+    request_id: Option<u64>,
+) -> Result<String, SearchError> {
+    let entries = atuin_search_json(app, query, filters, limit, request_id).await?;
+    Ok(entries
+        .iter()
+        .map(|entry| format!("{}\n", results::format_atuin_line(entry)))
+        .collect())
+}
+
+/// Run a search through the cache and parse it into entries with match
+/// ranges and `is_new` flags, with matching favorites pinned on top if
+/// `include_favorites`. A new search advances the "new since last search"
+/// watermark; re-reading the same search (e.g. a later page) doesn't.
+fn structured_entries<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    query: &str,
+    filters: Option<SearchFilters>,
+    limit: u32,
+    new_search: bool,
+    include_favorites: bool,
+) -> Result<Vec<HistoryEntry>, String> {
+    let search_mode = filters
+        .as_ref()
+        .and_then(|f| f.search_mode.clone())
+        .unwrap_or_else(|| load_config().search_mode);
+    let found = cached_search(
+        &app.state::<Mutex<SearchCache>>(),
+        query,
+        filters,
+        Some(limit),
+    )?;
+    let mut entries = results::parse_atuin_output(&found.output);
+    if include_favorites {
+        if let Some(path) = favorites::get_favorites_path() {
+            let favorites = Favorites::load(&path);
+            entries = favorites::append_favorites(entries, &favorites.matching(query));
+        }
+    }
+    remember_results(&app.state::<Mutex<LastResults>>(), &entries)?;
+    for entry in &mut entries {
+        // atuin doesn't report match positions yet
+        results::apply_match_ranges(entry, query, &search_mode, None);
+    }
+
+    let watermark = app.state::<Mutex<SearchWatermark>>();
+    let mut watermark = watermark
+        .lock()
+        .map_err(|_| "Search watermark lock poisoned".to_string())?;
+    if new_search {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or_default();
+        watermark.mark_new(&mut entries, now);
+    } else {
+        watermark.flag_since_previous(&mut entries);
+    }
+    Ok(entries)
+}
+
+/// Fill in verbose metadata as configured for the session and keep only the
+/// configured result fields
+fn finish_structured_entries<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    entries: &mut [HistoryEntry],
+    fields: &[String],
+) -> Result<Vec<serde_json::Value>, String> {
+    let verbose = app
+        .state::<Mutex<VerboseResults>>()
+        .lock()
+        .map_err(|_| "Verbose results lock poisoned".to_string())?
+        .0;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default();
+
+    entries
+        .iter_mut()
+        .map(|entry| {
+            results::apply_verbose_metadata(entry, verbose, now);
+            results::project_entry(entry, fields)
+        })
+        .collect()
+}
+
+#[tauri::command]
+fn atuin_search_structured<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    query: &str,
+    filters: Option<SearchFilters>,
+    include_favorites: Option<bool>,
+) -> Result<Vec<serde_json::Value>, String> {
+    let fields = load_config().result_fields;
+    results::validate_result_fields(&fields)?;
+
+    let include_favorites = include_favorites.unwrap_or(false);
+    let mut entries =
+        structured_entries(&app, query, filters, SEARCH_LIMIT, true, include_favorites)?;
+    finish_structured_entries(&app, &mut entries, &fields)
+}
+
+#[tauri::command]
+fn atuin_search_page<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    query: &str,
+    filters: Option<SearchFilters>,
+    page: usize,
+    page_size: usize,
+    include_favorites: Option<bool>,
+) -> Result<SearchPage<serde_json::Value>, String> {
+    paging::validate_page_size(page_size)?;
+    let fields = load_config().result_fields;
+    results::validate_result_fields(&fields)?;
+
+    // Fetch up to the end of the page and one more, which tells whether a
+    // later page has results
+    let limit = page
+        .saturating_add(1)
+        .saturating_mul(page_size)
+        .saturating_add(1);
+    let limit = u32::try_from(limit).unwrap_or(u32::MAX);
+    let include_favorites = include_favorites.unwrap_or(false);
+    let mut entries =
+        structured_entries(&app, query, filters, limit, page == 0, include_favorites)?;
+    // Page from the newest, so pages stay put as more is fetched
+    entries.reverse();
+    let mut page = paging::paginate(&entries, page, page_size);
+    let values = finish_structured_entries(&app, &mut page.entries, &fields)?;
+    Ok(SearchPage {
+        entries: values,
+        page: page.page,
+        page_size: page.page_size,
+        has_more: page.has_more,
+    })
+}
+
+#[tauri::command]
+fn prepare_bulk_delete(
+    tokens: tauri::State<'_, Mutex<DeleteTokens>>,
+    filters: SearchFilters,
+    allow_unfiltered: Option<bool>,
+) -> Result<String, String> {
+    let filters = normalize_filters(filters)?;
+    bulk_delete::check_deletable(&filters, allow_unfiltered.unwrap_or(false))?;
+    Ok(tokens
+        .lock()
+        .map_err(|_| "Delete tokens lock poisoned".to_string())?
+        .issue(&filters, Instant::now()))
+}
+
+/// Runs on a blocking thread, as a large delete runs atuin once per
+/// matching command with no timeout
+#[tauri::command]
+async fn bulk_delete(
+    tokens: tauri::State<'_, Mutex<DeleteTokens>>,
+    cache: tauri::State<'_, Mutex<SearchCache>>,
+    filters: SearchFilters,
+    confirm_token: String,
+    allow_unfiltered: Option<bool>,
+) -> Result<u64, String> {
+    let filters = normalize_filters(filters)?;
+    bulk_delete::check_deletable(&filters, allow_unfiltered.unwrap_or(false))?;
+    tokens
+        .lock()
+        .map_err(|_| "Delete tokens lock poisoned".to_string())?
+        .redeem(&confirm_token, &filters, Instant::now())?;
+
+    let removed = run_blocking(move || {
+        let config = load_config();
+        // No timeout: killing atuin partway through would leave the delete
+        // half-applied
+        let backend = CommandBackend::new(
+            &config.atuin_path,
+            config.use_login_shell,
+            config.atuin_env,
+            0,
+        )?
+        // Nor a cap on what it prints: the delete has happened by then
+        .with_max_output_bytes(0);
+        bulk_delete::delete_matching(&filters, &backend).map_err(|e| e.to_string())
+    })
+    .await?;
+
+    // Cached results may include the deleted entries
+    *cache
+        .lock()
+        .map_err(|_| "Search cache lock poisoned".to_string())? = SearchCache::new();
+    Ok(removed)
+}
+
+#[tauri::command]
+fn set_verbose_results(
+    verbose: tauri::State<'_, Mutex<VerboseResults>>,
+    enabled: bool,
+) -> Result<(), String> {
+    verbose
+        .lock()
+        .map_err(|_| "Verbose results lock poisoned".to_string())?
+        .0 = enabled;
+    Ok(())
+}
+
+/// Remember the filters the user applied, when `remember_filters` is set.
+/// Only what the user chose is kept: defaults filled in from config are
+/// left out so later config changes still apply, as are the limit and
+/// offset of the page being viewed.
+#[tauri::command]
+fn set_last_filters(filters: SearchFilters) -> Result<(), String> {
+    if !load_config().remember_filters {
+        return Ok(());
+    }
+    let filters = SearchFilters {
+        limit: None,
+        offset: None,
+        ..normalize_filters(filters)?
+    };
+    match last_filters::get_last_filters_path() {
+        Some(path) => last_filters::save_last_filters(&path, &filters),
+        None => Ok(()),
+    }
+}
+
+#[tauri::command]
+fn last_filters() -> SearchFilters {
+    let remember = load_config().remember_filters;
+    last_filters::get_last_filters_path()
+        .map(|path| last_filters::load_last_filters(&path, remember))
+        .unwrap_or_default()
+}
+
+/// Pin `command` above search results; adding a favorite twice is a no-op
+#[tauri::command]
+fn add_favorite(command: String) -> Result<Vec<String>, String> {
+    update_favorites(|favorites| favorites.add(&command).map(|_| ()))
+}
+
+/// Unpin `command`; removing a command that isn't a favorite is a no-op
+#[tauri::command]
+fn remove_favorite(command: String) -> Result<Vec<String>, String> {
+    update_favorites(|favorites| {
+        favorites.remove(&command);
+        Ok(())
+    })
+}
+
+#[tauri::command]
+fn list_favorites() -> Vec<String> {
+    favorites::get_favorites_path()
+        .map(|path| Favorites::load(&path).commands().to_vec())
+        .unwrap_or_default()
+}
+
+#[tauri::command]
+fn export_results(
+    entries: Vec<ParsedHistoryEntry>,
+    path: String,
+    format: String,
+) -> Result<(), String> {
+    export::export_results(&entries, Path::new(&path), &format)
+}
+
+/// Apply `change` to the saved favorites and return the updated list
+fn update_favorites(
+    change: impl FnOnce(&mut Favorites) -> Result<(), String>,
+) -> Result<Vec<String>, String> {
+    let Some(path) = favorites::get_favorites_path() else {
+        return Err("Could not determine favorites path".to_string());
+    };
+    let mut favorites = Favorites::load(&path);
+    change(&mut favorites)?;
+    favorites.save(&path)?;
+    Ok(favorites.commands().to_vec())
+}
+
+/// Remember a query searched for in the bar, for `recent_searches`
+#[tauri::command]
+fn record_search(query: String) -> Result<(), String> {
+    let limit = load_config().recent_searches_limit as usize;
+    let Some(path) = recent_searches::get_recent_searches_path() else {
+        return Ok(());
+    };
+    let mut searches = RecentSearches::load(&path);
+    searches.record(&query, limit);
+    searches.save(&path)
+}
+
+/// Queries recently searched for in the bar, newest first
+#[tauri::command]
+fn recent_searches() -> Vec<String> {
+    let limit = load_config().recent_searches_limit as usize;
+    recent_searches::get_recent_searches_path()
+        .map(|path| RecentSearches::load(&path).recent(limit))
+        .unwrap_or_default()
+}
+
+#[tauri::command]
+fn check_filter_compatibility(filters: SearchFilters) -> Vec<CompatWarning> {
+    let defaults = atuin_config::get_atuin_config_path()
+        .map(|path| AtuinDefaults::load(&path))
+        .unwrap_or_default();
+    atuin_config::check_filter_compatibility(&filters, &defaults)
+}
+
+/// Scans the whole history, so runs on a blocking thread
+#[tauri::command]
+async fn flaky_commands_command(
+    limit: u32,
+    filters: Option<SearchFilters>,
+) -> Result<Vec<FlakyCommand>, String> {
+    run_blocking(move || analysis::flaky_commands(limit, filters)).await
+}
+
+/// Searches the command's whole history, so runs on a blocking thread
+#[tauri::command]
+async fn command_duration_trend(
+    command: String,
+    buckets: u32,
+) -> Result<Vec<DurationBucket>, String> {
+    run_blocking(move || analysis::command_duration_trend(&command, buckets)).await
+}
+
+/// Scans the whole history, so runs on a blocking thread
+#[tauri::command]
+async fn verify_in_history(command: String) -> Result<bool, String> {
+    run_blocking(move || {
+        let config = load_config();
+        let backend = CommandBackend::new(
+            &config.atuin_path,
+            config.use_login_shell,
+            config.atuin_env,
+            config.search_timeout_ms,
+        )?
+        .with_max_output_bytes(config.max_output_bytes);
+        verify_in_history_with_backend(&command, &backend).map_err(|e| e.to_string())
+    })
+    .await
+}
+
+/// Runs per keystroke, so atuin is run on a blocking thread
+#[tauri::command]
+async fn complete_directory(prefix: String, limit: u32) -> Result<Vec<String>, String> {
+    run_blocking(move || analysis::complete_directory(&prefix, limit)).await
+}
+
+/// Scans the whole history, so runs on a blocking thread
+#[tauri::command]
+async fn list_hosts() -> Result<Vec<String>, String> {
+    run_blocking(analysis::list_hosts).await
+}
+
+#[tauri::command]
+async fn list_directories(limit: Option<u32>) -> Result<Vec<DirectoryOption>, SearchError> {
+    tauri::async_runtime::spawn_blocking(move || analysis::list_directories(limit))
+        .await
+        .map_err(|e| SearchError::Internal {
+            message: format!("Directory listing failed: {}", e),
+        })?
+}
+
+#[tauri::command]
+fn atuin_stats_command(count: Option<u32>) -> Result<Vec<(String, u64)>, SearchError> {
+    analysis::atuin_stats(count)
+}
+
+/// Scans the whole range, so runs on a blocking thread
+#[tauri::command]
+async fn history_summary(range: String) -> Result<HistorySummary, String> {
+    run_blocking(move || analysis::history_summary(&range)).await
+}
+
+#[tauri::command]
+async fn copy_history_summary<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    range: String,
+) -> Result<(), String> {
+    let summary = run_blocking(move || analysis::history_summary(&range)).await?;
+    write_clipboard(&app, summary.to_markdown())
+}
+
+#[tauri::command]
+fn describe_exit_code(code: i32) -> ExitInfo {
+    results::describe_exit_code(code)
+}
+
+#[tauri::command]
+fn result_facets(entries: Vec<HistoryEntry>) -> ResultFacets {
+    analysis::compute_result_facets(&entries, analysis::MAX_FACET_VALUES)
+}
+
+#[tauri::command]
+fn record_selection(
+    counts: tauri::State<'_, Mutex<SelectionCounts>>,
+    command: String,
+) -> Result<(), String> {
+    let mut counts = counts
+        .lock()
+        .map_err(|_| "Selection counts lock poisoned".to_string())?;
+    counts.record(&command);
+
+    if load_config().remember_selections {
+        if let Some(path) = frequency::get_selection_counts_path() {
+            counts.save(&path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Runs per keystroke, so atuin is run on a blocking thread
+#[tauri::command]
+async fn autocomplete(
+    counts: tauri::State<'_, Mutex<SelectionCounts>>,
+    prefix: String,
+    limit: u32,
+) -> Result<Vec<String>, String> {
+    let output = run_blocking(move || {
+        atuin_search(&prefix, None, Some(SEARCH_LIMIT)).map_err(|e| e.to_string())
+    })
+    .await?;
+    let entries = results::parse_atuin_output(&output);
+    let counts = counts
+        .lock()
+        .map_err(|_| "Selection counts lock poisoned".to_string())?;
+    Ok(frequency::rank_suggestions(
+        &entries,
+        &counts,
+        limit as usize,
+    ))
+}
+
+#[tauri::command]
+fn open_in_terminal(command: String, directory: Option<String>) -> Result<(), String> {
+    terminal::open_in_terminal(&load_config().terminal_cmd, &command, directory.as_deref())
+}
+
+/// Run commands on a blocking thread, so the app stays responsive while
+/// they do
+async fn run_blocking<T: Send + 'static>(
+    run: impl FnOnce() -> Result<T, String> + Send + 'static,
+) -> Result<T, String> {
+    tauri::async_runtime::spawn_blocking(run)
+        .await
+        .map_err(|e| format!("Command task failed: {}", e))?
+}
+
+#[tauri::command]
+async fn run_queue(
+    commands: Vec<String>,
+    directory: Option<String>,
+    stop_on_error: bool,
+    confirmed: Option<bool>,
+) -> Result<Vec<CommandOutput>, String> {
+    let config = load_config();
+    runner::check_run_policy(
+        &commands,
+        config.allow_run,
+        config.confirm_dangerous,
+        confirmed.unwrap_or(false),
+    )?;
+    run_blocking(move || {
+        runner::run_queue(
+            &mut ShellExecutor::default(),
+            &commands,
+            directory.as_deref(),
+            stop_on_error,
+        )
+    })
+    .await
+}
+
+/// Run one history entry in the user's shell and return what it printed.
+/// Only ever called on an explicit user action.
+#[tauri::command]
+async fn execute_command(
+    command: String,
+    directory: Option<String>,
+    confirmed: Option<bool>,
+) -> Result<CommandOutput, String> {
+    let config = load_config();
+    runner::check_run_policy(
+        std::slice::from_ref(&command),
+        config.allow_run,
+        config.confirm_dangerous,
+        confirmed.unwrap_or(false),
+    )?;
+    run_blocking(move || ShellExecutor::default().execute(&command, directory.as_deref())).await
+}
+
+#[tauri::command]
+fn set_autostart(app: tauri::AppHandle, enabled: bool) -> Result<(), String> {
+    let mut config = load_config();
+    autostart::update_autostart(&mut &*app.autolaunch(), &mut config, enabled)?;
+    save_config(&config)
+}
+
+#[tauri::command]
+fn get_autostart(app: tauri::AppHandle) -> Result<bool, String> {
+    autostart::AutostartManager::is_enabled(&&*app.autolaunch())
+}
+
+/// Write text to the clipboard as-is
+fn write_clipboard<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    text: String,
+) -> Result<(), String> {
+    app.clipboard()
+        .write_text(text)
+        .map_err(|e| format!("Failed to copy to clipboard: {}", e))
+}
+
+/// Hide the search window and type `text` into the window that had focus
+/// before it. Fails unless `paste_on_select` is on and, on macOS, the app
+/// has the Accessibility permission.
+#[tauri::command]
+async fn paste_to_active_window<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    text: String,
+) -> Result<(), String> {
+    paste::check_paste_allowed(
+        load_config().paste_on_select,
+        &permissions::check_paste_permissions(),
+    )?;
+
+    if let Some(main) = app.get_webview_window(MAIN_WINDOW) {
+        let _ = hide_main_window(&main);
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        std::thread::sleep(paste::PASTE_FOCUS_DELAY);
+        paste::type_text(paste::paste_text(&text))
+    })
+    .await
+    .map_err(|e| format!("Failed to paste: {}", e))?
+}
+
+/// Copy a command, applying the configured trailing-newline policy
+fn copy_command<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    text: &str,
+    keep_newline: Option<bool>,
+) -> Result<CopyOutcome, String> {
+    let config = load_config();
+    let text = if config.normalize_on_copy {
+        clipboard::normalize_command(text)
+    } else {
+        text.to_string()
+    };
+    match clipboard::prepare_copy(&text, &config.copy_trailing_newline, keep_newline)? {
+        CopyPlan::Write(text) => {
+            write_clipboard(app, text)?;
+            Ok(CopyOutcome {
+                copied: true,
+                needs_confirmation: false,
+            })
+        }
+        CopyPlan::Ask => Ok(CopyOutcome {
+            copied: false,
+            needs_confirmation: true,
+        }),
+    }
+}
+
+#[tauri::command]
+async fn copy_to_clipboard<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    text: String,
+    keep_newline: Option<bool>,
+) -> Result<CopyOutcome, String> {
+    copy_command(&app, &text, keep_newline)
+}
+
+/// Copy `text` like `copy_to_clipboard`, then hide the search window, in a
+/// single round trip. The window stays open when nothing was copied, either
+/// because the clipboard write failed or the trailing newline needs an answer.
+#[tauri::command]
+async fn copy_and_hide<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    text: String,
+    keep_newline: Option<bool>,
+) -> Result<CopyOutcome, String> {
+    let window = app
+        .get_webview_window(MAIN_WINDOW)
+        .ok_or_else(|| "Main window not found".to_string())?;
+    let outcome = copy_command(&app, &text, keep_newline)?;
+    if outcome.copied {
+        hide_main_window(&window)?;
+    }
+    Ok(outcome)
+}
+
+#[tauri::command]
+fn atuin_config_path() -> Option<String> {
+    atuin_config::get_atuin_config_path().map(|p| p.to_string_lossy().into_owned())
+}
+
+#[tauri::command]
+async fn copy_atuin_config_path<R: tauri::Runtime>(app: tauri::AppHandle<R>) -> Result<(), String> {
+    let path = atuin_config_path().ok_or("Could not determine the atuin config path")?;
+    write_clipboard(&app, path)
+}
+
+#[tauri::command]
+async fn copy_nth_result<R: tauri::Runtime>(
+    app: tauri::AppHandle<R>,
+    last: tauri::State<'_, Mutex<LastResults>>,
+    index: usize,
+    keep_newline: Option<bool>,
+) -> Result<CopyOutcome, String> {
+    let command = last
+        .lock()
+        .map_err(|_| "Last results lock poisoned".to_string())?
+        .nth_command(index)?
+        .to_string();
+    copy_command(&app, &command, keep_newline)
+}
+
+/// Show and focus the main window
+fn show_main_window<R: tauri::Runtime>(app: &tauri::AppHandle<R>) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+/// Emitted to the main window when the global shortcut shows it, with `true`
+pub const WINDOW_SHOWN_EVENT: &str = "window-shown";
+/// Emitted to the main window when the global shortcut hides it, with `false`
+pub const WINDOW_HIDDEN_EVENT: &str = "window-hidden";
+
+/// Toggle the main window for a global shortcut event and tell the frontend
+/// which way it went. Only `Pressed` toggles: the plugin also reports the
+/// key's release, which would otherwise toggle straight back. Returns whether
+/// the window is now visible, or None if nothing was toggled.
+fn toggle_main_window<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    state: ShortcutState,
+) -> Option<bool> {
+    if state != ShortcutState::Pressed {
+        return None;
+    }
+    let window = app.get_webview_window(MAIN_WINDOW)?;
+    let visible = !window.is_visible().ok()?;
+    let event = if visible {
+        let _ = window.show();
+        let _ = window.set_focus();
+        WINDOW_SHOWN_EVENT
+    } else {
+        let _ = hide_main_window(&window);
+        WINDOW_HIDDEN_EVENT
+    };
+    if let Err(e) = app.emit_to(MAIN_WINDOW, event, visible) {
+        eprintln!("Failed to emit {}: {}", event, e);
+    }
+    Some(visible)
+}
+
+/// How long to wait after the main window loses focus for another app
+/// window to report gaining it
+const FOCUS_SETTLE_DELAY: Duration = Duration::from_millis(100);
+
+/// Track focus changes across the app's windows and hide the main window
+/// once focus has left it according to the configured `blur_hide_scope`
+fn handle_focus_change<R: tauri::Runtime>(window: &tauri::Window<R>, focused: bool) {
+    let app = window.app_handle().clone();
+    let tracker = app.state::<Mutex<FocusTracker>>();
+    if let Ok(mut tracker) = tracker.lock() {
+        tracker.set_focused(window.label(), focused);
+    }
+
+    if window.label() != MAIN_WINDOW || focused {
+        return;
+    }
+
+    // Focus-out on main arrives before focus-in on the next window, so
+    // decide only after the other window had a chance to report
+    std::thread::spawn(move || {
+        std::thread::sleep(FOCUS_SETTLE_DELAY);
+        let config = load_config();
+        let should_hide = app
+            .state::<Mutex<FocusTracker>>()
+            .lock()
+            .map(|tracker| tracker.should_hide_main(&config.blur_hide_scope, config.hide_on_blur))
+            .unwrap_or(true);
+        if should_hide {
+            if let Some(main) = app.get_webview_window(MAIN_WINDOW) {
+                let _ = hide_main_window(&main);
+            }
+        }
+    });
+}
+
+/// Show the main window and ask it to run the search described by a deep link
+fn handle_deep_link<R: tauri::Runtime>(app: &tauri::AppHandle<R>, url: &str) {
+    match parse_deep_link(url) {
+        Ok(search) => {
+            show_main_window(app);
+            let _ = app.emit_to("main", DEEP_LINK_SEARCH_EVENT, search);
+        }
+        Err(e) => eprintln!("Ignoring deep link {}: {}", url, e),
+    }
+}
+
+/// Show the main window and keep the search described by a deep link the
+/// app was launched with, for the frontend to take once it's listening
+fn queue_deep_link<R: tauri::Runtime>(app: &tauri::AppHandle<R>, url: &str) {
+    match parse_deep_link(url) {
+        Ok(search) => {
+            show_main_window(app);
+            match app.state::<Mutex<PendingDeepLink>>().lock() {
+                Ok(mut pending) => pending.set(search),
+                Err(_) => eprintln!("Pending deep link lock poisoned"),
+            }
+        }
+        Err(e) => eprintln!("Ignoring deep link {}: {}", url, e),
+    }
+}
+
+/// The search of the deep link the app was launched with, if the frontend
+/// hasn't taken it yet
+#[tauri::command]
+fn take_deep_link(
+    pending: tauri::State<'_, Mutex<PendingDeepLink>>,
+) -> Result<Option<deep_link::DeepLinkSearch>, String> {
+    Ok(pending
+        .lock()
+        .map_err(|_| "Pending deep link lock poisoned".to_string())?
+        .take())
+}
+
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+    // Load configuration
+    let config = load_config();
+    let shortcuts = keybindings::global_shortcuts(&config);
+
+    tauri::Builder::default()
+        // Must be registered first so a second launch (e.g. from a deep link)
+        // is forwarded to the running instance
+        .plugin(tauri_plugin_single_instance::init(|app, _argv, _cwd| {
+            show_main_window(app);
+        }))
+        .plugin(tauri_plugin_deep_link::init())
+        .plugin(tauri_plugin_opener::init())
+        .plugin(
+            tauri_plugin_global_shortcut::Builder::new()
+                .with_handler(|app, _shortcut, event| {
+                    toggle_main_window(app, event.state);
+                })
                 .build(),
         )
-        .plugin(tauri_plugin_clipboard_manager::init())
-        .plugin(tauri_plugin_dialog::init())
-        .invoke_handler(tauri::generate_handler![
-            greet,
-            atuin_search_command,
-            copy_to_clipboard,
-            get_theme,
-            get_max_results,
-            get_window_width,
-            get_config,
-            update_config
-        ])
-        .setup(|app| {
-            let window = app.get_webview_window("main").unwrap();
-
-            let window_clone = window.clone();
-            window.on_window_event(move |event| {
-                if let tauri::WindowEvent::Focused(focused) = event {
-                    if !focused {
-                        let _ = window_clone.hide();
-                    }
-                }
-            });
+        .plugin(tauri_plugin_clipboard_manager::init())
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_autostart::init(
+            tauri_plugin_autostart::MacosLauncher::LaunchAgent,
+            None,
+        ))
+        .manage(Mutex::new(SearchCache::new()))
+        .manage(Mutex::new(SearchSequence::new()))
+        .manage(Mutex::new(PendingDeepLink::new()))
+        .manage(Mutex::new(FocusTracker::new()))
+        .manage(Mutex::new(SearchWatermark::new()))
+        .manage(Mutex::new(VerboseResults::default()))
+        .manage(Mutex::new(LastResults::new()))
+        .manage(Mutex::new(DeleteTokens::new()))
+        .manage(Mutex::new(ActiveShortcut(config.shortcut.clone())))
+        .manage(LaunchDirectory(
+            std::env::current_dir()
+                .ok()
+                .map(|dir| dir.to_string_lossy().into_owned()),
+        ))
+        .manage(Mutex::new(if config.remember_selections {
+            frequency::get_selection_counts_path()
+                .map(|path| SelectionCounts::load(&path))
+                .unwrap_or_default()
+        } else {
+            SelectionCounts::default()
+        }))
+        .invoke_handler(tauri::generate_handler![
+            greet,
+            atuin_search_command,
+            atuin_search_json,
+            atuin_search_window,
+            atuin_search_structured,
+            atuin_search_page,
+            set_verbose_results,
+            prepare_bulk_delete,
+            bulk_delete,
+            copy_to_clipboard,
+            copy_and_hide,
+            paste_to_active_window,
+            get_theme,
+            directory_color,
+            get_effective_theme,
+            get_max_results,
+            get_search_mode,
+            get_paste_on_select,
+            get_window_width,
+            get_window_height,
+            get_font_size,
+            get_opacity,
+            get_config,
+            env_overridden_fields,
+            keybindings_cheatsheet,
+            app_data_usage,
+            clear_app_data,
+            update_config,
+            prune_config_defaults,
+            config_status,
+            config_file_path,
+            open_config_file,
+            validate_config,
+            repair_config,
+            check_shortcut_permissions,
+            shortcut_warnings,
+            get_click_behavior,
+            set_shortcut,
+            apply_size_preset,
+            resize_window_to_results,
+            save_window_position,
+            hide_window,
+            set_pinned,
+            normalize_filters_command,
+            check_filter_compatibility,
+            last_filters,
+            set_last_filters,
+            record_search,
+            recent_searches,
+            add_favorite,
+            remove_favorite,
+            list_favorites,
+            export_results,
+            flaky_commands_command,
+            command_duration_trend,
+            history_summary,
+            complete_directory,
+            list_hosts,
+            list_directories,
+            launch_directory,
+            search_in_current_dir,
+            preview_search_command,
+            reset_search_sequence,
+            take_deep_link,
+            atuin_stats_command,
+            verify_in_history,
+            copy_history_summary,
+            result_facets,
+            describe_exit_code,
+            record_selection,
+            autocomplete,
+            open_in_terminal,
+            run_queue,
+            execute_command,
+            set_autostart,
+            get_autostart,
+            atuin_config_path,
+            copy_atuin_config_path,
+            copy_nth_result
+        ])
+        .on_window_event(|window, event| match event {
+            tauri::WindowEvent::Focused(focused) => handle_focus_change(window, *focused),
+            tauri::WindowEvent::CloseRequested { .. } if window.label() == MAIN_WINDOW => {
+                if let Ok(position) = window.outer_position() {
+                    if let Err(e) = remember_window_position(position) {
+                        eprintln!("{}", e);
+                    }
+                }
+            }
+            tauri::WindowEvent::Destroyed => {
+                if let Ok(mut tracker) = window.state::<Mutex<FocusTracker>>().lock() {
+                    tracker.remove(window.label());
+                }
+            }
+            _ => {}
+        })
+        .setup(move |app| {
+            // Register the toggle shortcuts. A malformed or taken shortcut
+            // is logged rather than stopping startup: the main one falls
+            // back to the platform default and extra ones are skipped.
+            let mut registrar = app.global_shortcut();
+            let toggle = keybindings::register_shortcut_or_default(
+                &mut registrar,
+                &config.shortcut,
+                &Config::default().shortcut,
+            );
+            // The primary was handled above, even if it fell back to the
+            // default, so it isn't retried and reported a second time
+            let extra: Vec<String> = shortcuts
+                .into_iter()
+                .filter(|s| *s != toggle && *s != config.shortcut)
+                .collect();
+            for e in keybindings::register_shortcuts(&mut registrar, &extra) {
+                eprintln!("{}", e);
+            }
+            for warning in keybindings::shortcut_warnings(&toggle) {
+                eprintln!("{}", warning);
+            }
+            if let Ok(mut active) = app.state::<Mutex<ActiveShortcut>>().lock() {
+                active.0 = toggle;
+            }
+
+            // Create menu
+            let settings_item = MenuItemBuilder::with_id("settings", "Settings").build(app)?;
+            let menu = MenuBuilder::new(app).item(&settings_item).build()?;
+
+            app.set_menu(menu)?;
+
+            // Keep the OS login item in sync with the configured autostart state
+            if let Err(e) =
+                autostart::apply_autostart(&mut &*app.autolaunch(), load_config().autostart)
+            {
+                eprintln!("{}", e);
+            }
+
+            if let Some(main) = app.get_webview_window(MAIN_WINDOW) {
+                restore_window_position(&main);
+            }
+
+            // Pick up edits to config.toml without a restart
+            if let Some(path) = get_config_path() {
+                if let Err(e) =
+                    config_watch::watch_config(app.handle().clone(), path, load_config())
+                {
+                    eprintln!("{}", e);
+                }
+            }
+
+            // Handle atuin-bar:// deep links, both at launch and while running
+            #[cfg(any(target_os = "linux", all(debug_assertions, windows)))]
+            app.deep_link().register_all()?;
+
+            let handle = app.handle().clone();
+            app.deep_link().on_open_url(move |event| {
+                for url in event.urls() {
+                    handle_deep_link(&handle, url.as_str());
+                }
+            });
+
+            // The frontend isn't listening yet, so it takes these itself
+            if let Ok(Some(urls)) = app.deep_link().get_current() {
+                for url in urls {
+                    queue_deep_link(app.handle(), url.as_str());
+                }
+            }
+
+            // Handle menu events
+            app.on_menu_event(move |app, event| {
+                if event.id().as_ref() == "settings" {
+                    // Check if settings window already exists
+                    if let Some(settings_window) = app.get_webview_window("settings") {
+                        let _ = settings_window.show();
+                        let _ = settings_window.set_focus();
+                    } else {
+                        // Create settings window
+                        use tauri::WebviewUrl;
+                        use tauri::WebviewWindowBuilder;
+
+                        let settings_window = WebviewWindowBuilder::new(
+                            app,
+                            "settings",
+                            WebviewUrl::App("settings.html".into()),
+                        )
+                        .title("Atuin Bar Settings")
+                        .inner_size(500.0, 400.0)
+                        .resizable(false)
+                        .center()
+                        .build();
+
+                        if let Ok(win) = settings_window {
+                            let _ = win.show();
+                        }
+                    }
+                }
+            });
+
+            Ok(())
+        })
+        .run(tauri::generate_context!())
+        .expect("error while running tauri application");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config_file::TempConfig;
+    use serial_test::serial;
+    use tauri::test::{mock_builder, mock_context, noop_assets};
+    use tauri::Listener;
+
+    /// Points `get_config_path` at a temp config file until dropped
+    struct ConfigPathOverride;
+
+    impl Drop for ConfigPathOverride {
+        fn drop(&mut self) {
+            *TEST_CONFIG_PATH.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        }
+    }
+
+    /// Run `test` with the config file in a temp dir, so that hiding the
+    /// window (which remembers its position) doesn't touch the real one
+    fn with_temp_config_dir(name: &str, test: impl FnOnce()) {
+        let temp = TempConfig::new(name);
+        *TEST_CONFIG_PATH.lock().unwrap_or_else(|e| e.into_inner()) = Some(temp.path.clone());
+        let _override = ConfigPathOverride;
+        test();
+    }
+
+    /// `with_temp_config_dir` with the config file holding `contents`
+    fn with_temp_config(name: &str, contents: &str, test: impl FnOnce()) {
+        let temp = TempConfig::with_contents(name, contents);
+        *TEST_CONFIG_PATH.lock().unwrap_or_else(|e| e.into_inner()) = Some(temp.path.clone());
+        let _override = ConfigPathOverride;
+        test();
+    }
+
+    #[test]
+    #[serial]
+    fn test_toggle_main_window_only_on_press() {
+        with_temp_config_dir("toggle", || {
+            let app = mock_builder()
+                .build(mock_context(noop_assets()))
+                .expect("failed to build mock app");
+            assert_eq!(
+                toggle_main_window(app.handle(), ShortcutState::Pressed),
+                None
+            );
+
+            tauri::WebviewWindowBuilder::new(&app, MAIN_WINDOW, tauri::WebviewUrl::default())
+                .build()
+                .expect("failed to create main window");
+            let window = app.get_webview_window(MAIN_WINDOW).unwrap();
+            let visible = window.is_visible().unwrap();
+
+            // Releasing the shortcut must not undo the toggle made on press
+            assert_eq!(
+                toggle_main_window(app.handle(), ShortcutState::Released),
+                None
+            );
+            assert_eq!(
+                toggle_main_window(app.handle(), ShortcutState::Pressed),
+                Some(!visible)
+            );
+        });
+    }
+
+    #[test]
+    #[serial]
+    fn test_blur_hide_emits_reset_search() {
+        with_temp_config_dir("blur-reset", || {
+            let config_dir = get_config_path().unwrap().parent().unwrap().to_path_buf();
+            fs::create_dir_all(&config_dir).unwrap();
+            fs::write(config_dir.join("config.toml"), "clear_on_hide = true\n").unwrap();
+
+            let app = mock_builder()
+                .manage(Mutex::new(FocusTracker::new()))
+                .build(mock_context(noop_assets()))
+                .expect("failed to build mock app");
+            let window =
+                tauri::WebviewWindowBuilder::new(&app, MAIN_WINDOW, tauri::WebviewUrl::default())
+                    .build()
+                    .expect("failed to create main window");
+
+            let (sender, receiver) = std::sync::mpsc::channel();
+            app.listen_any(RESET_SEARCH_EVENT, move |_| {
+                let _ = sender.send(());
+            });
+
+            handle_focus_change(&window.as_ref().window(), false);
+            assert!(receiver.recv_timeout(FOCUS_SETTLE_DELAY * 20).is_ok());
+        });
+    }
+
+    #[test]
+    #[serial]
+    fn test_escape_hide_keeps_search_by_default() {
+        with_temp_config_dir("escape-keep", || {
+            let app = mock_builder()
+                .build(mock_context(noop_assets()))
+                .expect("failed to build mock app");
+            tauri::WebviewWindowBuilder::new(&app, MAIN_WINDOW, tauri::WebviewUrl::default())
+                .build()
+                .expect("failed to create main window");
+
+            let (sender, receiver) = std::sync::mpsc::channel();
+            app.listen_any(RESET_SEARCH_EVENT, move |_| {
+                let _ = sender.send(());
+            });
+
+            hide_window(app.handle().clone()).unwrap();
+            assert!(receiver.recv_timeout(Duration::from_millis(200)).is_err());
+        });
+    }
+
+    #[test]
+    fn test_current_dir_filters_only_sets_directory() {
+        let filters = current_dir_filters(Some(" /repo ".to_string())).unwrap();
+        assert_eq!(filters.directory.as_deref(), Some("/repo"));
+        assert_eq!(
+            SearchFilters {
+                directory: None,
+                ..filters
+            },
+            SearchFilters::default()
+        );
+
+        assert_eq!(current_dir_filters(Some("  ".to_string())), None);
+        assert_eq!(current_dir_filters(None), None);
+    }
+
+    #[test]
+    fn test_expand_directory() {
+        let home = Some(Path::new("/home/me"));
+        let var = |name: &str| match name {
+            "HOME" => Some("/home/me".to_string()),
+            "PROJECT" => Some("app".to_string()),
+            _ => None,
+        };
+
+        assert_eq!(expand_directory("~", home, var), "/home/me");
+        assert_eq!(expand_directory("~/sub", home, var), "/home/me/sub");
+        assert_eq!(expand_directory("$HOME/sub", home, var), "/home/me/sub");
+        assert_eq!(
+            expand_directory("~/src/${PROJECT}", home, var),
+            "/home/me/src/app"
+        );
+        assert_eq!(
+            expand_directory("/absolute/path", home, var),
+            "/absolute/path"
+        );
+        // Only a leading `~` on its own means home, and unset variables stay
+        assert_eq!(expand_directory("~other/sub", home, var), "~other/sub");
+        assert_eq!(
+            expand_directory("/tmp/$UNSET/x", home, var),
+            "/tmp/$UNSET/x"
+        );
+        assert_eq!(
+            expand_directory("/tmp/${UNCLOSED", home, var),
+            "/tmp/${UNCLOSED"
+        );
+        assert_eq!(expand_directory("/cost$", home, var), "/cost$");
+    }
+
+    #[test]
+    fn test_search_args_end_options_before_query() {
+        let args = search_args(
+            "--version",
+            &SearchFilters::default(),
+            Some(SEARCH_LIMIT),
+            0,
+        );
+        assert_eq!(&args[args.len() - 2..], ["--", "--version"]);
+    }
+
+    #[test]
+    fn test_build_atuin_command_defaults() {
+        let args = build_atuin_command("git", &SearchFilters::default());
+        assert_eq!(
+            args,
+            [
+                "search",
+                "--search-mode",
+                "prefix",
+                "--limit",
+                "50",
+                "--format",
+                SEARCH_FORMAT,
+                "--",
+                "git"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_atuin_command_with_filters() {
+        let filters = SearchFilters {
+            directory: Some("/tmp/project".to_string()),
+            exit_filter: Some("failure".to_string()),
+            time_range: Some("24h".to_string()),
+            search_mode: Some("fuzzy".to_string()),
+            limit: Some(20),
+            offset: Some(40),
+            ..Default::default()
+        };
+        let args = build_atuin_command("make", &filters);
+        assert_eq!(
+            args,
+            [
+                "search",
+                "--search-mode",
+                "fuzzy",
+                "--limit",
+                "20",
+                "--format",
+                SEARCH_FORMAT,
+                "--offset",
+                "40",
+                "--cwd",
+                "/tmp/project",
+                "--exclude-exit",
+                "0",
+                "--after",
+                "1 day ago",
+                "--",
+                "make",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_atuin_command_session_and_host() {
+        let filters = SearchFilters {
+            session: Some("abc123".to_string()),
+            host: Some("laptop".to_string()),
+            exit_filter: Some("2".to_string()),
+            ..Default::default()
+        };
+        let args = build_atuin_command("", &filters);
+        assert_eq!(&args[3..5], ["--limit", "1000"]);
+        assert_eq!(
+            args[6],
+            "{host}|{session}|{command}|{exit}|{duration}|{directory}|{time}"
+        );
+        assert_eq!(&args[7..], ["--exit", "2", "--", ""]);
+    }
+
+    #[test]
+    fn test_filter_host_session() {
+        let output = "laptop|s1|ls|0|1s|/a|2024-01-01 10:00:00\n\
+                      server|s2|echo a|b|0|1s|/a|2024-01-01 10:01:00\n\
+                      laptop|s2|printf 'x\n\
+                      y'|0|1s|/a|2024-01-01 10:02:00\n";
+
+        assert_eq!(
+            filter_host_session(output, Some("laptop"), None),
+            "ls|0|1s|/a|2024-01-01 10:00:00\nprintf 'x\ny'|0|1s|/a|2024-01-01 10:02:00\n"
+        );
+        assert_eq!(
+            filter_host_session(output, None, Some("s2")),
+            "echo a|b|0|1s|/a|2024-01-01 10:01:00\nprintf 'x\ny'|0|1s|/a|2024-01-01 10:02:00\n"
+        );
+        assert_eq!(
+            filter_host_session(output, Some("server"), Some("s2")),
+            "echo a|b|0|1s|/a|2024-01-01 10:01:00\n"
+        );
+    }
+
+    #[test]
+    fn test_build_atuin_command_falls_back_to_prefix_mode() {
+        let filters = SearchFilters {
+            search_mode: Some("bogus".to_string()),
+            ..Default::default()
+        };
+        let args = build_atuin_command("ls", &filters);
+        assert_eq!(&args[1..3], ["--search-mode", "prefix"]);
+    }
+
+    #[test]
+    fn test_build_atuin_command_boolean_query() {
+        let filters = SearchFilters {
+            boolean_query: true,
+            ..Default::default()
+        };
+        let args = build_atuin_command("git AND push", &filters);
+        assert_eq!(&args[args.len() - 2..], ["--", ""]);
+
+        // A query that doesn't parse is searched for literally
+        let args = build_atuin_command("git AND", &filters);
+        assert_eq!(&args[args.len() - 2..], ["--", "git AND"]);
+    }
+
+    #[test]
+    fn test_build_atuin_command_duration_bounds_widen_limit() {
+        let filters = SearchFilters {
+            min_duration_ms: Some(1000),
+            limit: Some(10),
+            ..Default::default()
+        };
+        let args = build_atuin_command("cargo", &filters);
+        assert_eq!(&args[3..5], ["--limit", "1000"]);
+    }
+
+    #[test]
+    fn test_fetch_filters_raises_limit() {
+        let filters = SearchFilters {
+            limit: Some(20),
+            ..Default::default()
+        };
+        let (filters, limit) = fetch_filters(filters, 200);
+        assert_eq!(limit, 20);
+        assert_eq!(filters.limit, Some(200));
+
+        let (filters, limit) = fetch_filters(SearchFilters::default(), 10);
+        assert_eq!(limit, SEARCH_LIMIT);
+        assert_eq!(filters.limit, Some(SEARCH_LIMIT));
+    }
+
+    /// Prints more than `max_output_bytes`, keeping its first record, and
+    /// two lines that aren't valid UTF-8
+    struct TruncatingBackend;
+
+    impl HistoryBackend for TruncatingBackend {
+        fn run(&self, _args: &[String]) -> Result<String, SearchError> {
+            Err(SearchError::OutputTruncated {
+                max_bytes: 64,
+                output: "git status|0|1s|/repo|2024-01-01 10:00:00\n".to_string(),
+            })
+        }
+
+        fn last_skipped_lines(&self) -> usize {
+            2
+        }
+    }
+
+    #[test]
+    fn test_truncated_search_keeps_the_lines_that_fit_and_reports_loss() {
+        for filters in [
+            SearchFilters::default(),
+            SearchFilters {
+                boolean_query: true,
+                ..Default::default()
+            },
+            SearchFilters {
+                sort: Some("oldest".to_string()),
+                ..Default::default()
+            },
+        ] {
+            let found = search_output("git", filters, false, &TruncatingBackend).unwrap();
+            assert_eq!(found.output, "git status|0|1s|/repo|2024-01-01 10:00:00\n");
+            assert!(found.loss.truncated);
+            assert_eq!(found.loss.skipped_lines, 2);
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_hide_window() {
+        with_temp_config_dir("hide-window", || {
+            let app = mock_builder()
+                .build(mock_context(noop_assets()))
+                .expect("failed to build mock app");
+
+            let err = hide_window(app.handle().clone()).unwrap_err();
+            assert_eq!(err, "Main window not found");
+
+            tauri::WebviewWindowBuilder::new(&app, MAIN_WINDOW, tauri::WebviewUrl::default())
+                .build()
+                .expect("failed to create main window");
+            assert!(hide_window(app.handle().clone()).is_ok());
+        });
+    }
+
+    #[test]
+    fn test_greet() {
+        let result = greet("World");
+        assert_eq!(result, "Hello, World! You've been greeted from Rust!");
+    }
+
+    #[test]
+    fn test_greet_empty_string() {
+        let result = greet("");
+        assert_eq!(result, "Hello, ! You've been greeted from Rust!");
+    }
+
+    #[test]
+    #[serial]
+    fn test_copy_to_clipboard() {
+        with_temp_config("copy", "copy_trailing_newline = \"strip\"\n", || {
+            // Create a mock Tauri app with clipboard plugin
+            let app = mock_builder()
+                .plugin(tauri_plugin_clipboard_manager::init())
+                .build(mock_context(noop_assets()))
+                .expect("failed to build mock app");
+
+            let test_text = "Hello, clipboard!".to_string();
+
+            // Test the copy_to_clipboard command
+            let result = tauri::async_runtime::block_on(copy_to_clipboard(
+                app.handle().clone(),
+                test_text.clone(),
+                None,
+            ));
+
+            // Verify the command executed successfully
+            assert!(result.is_ok(), "copy_to_clipboard should succeed");
+
+            // Verify the text was written to clipboard
+            let clipboard_content = app.handle().clipboard().read_text();
+            assert!(
+                clipboard_content.is_ok(),
+                "should be able to read clipboard"
+            );
+            assert_eq!(
+                clipboard_content.unwrap(),
+                test_text,
+                "clipboard should contain the copied text"
+            );
+        });
+    }
+
+    #[test]
+    #[serial]
+    fn test_copy_and_hide() {
+        with_temp_config_dir("copy-and-hide", || {
+            let app = mock_builder()
+                .plugin(tauri_plugin_clipboard_manager::init())
+                .build(mock_context(noop_assets()))
+                .expect("failed to build mock app");
+            tauri::WebviewWindowBuilder::new(&app, MAIN_WINDOW, tauri::WebviewUrl::default())
+                .build()
+                .expect("failed to create main window");
+
+            let (sender, receiver) = std::sync::mpsc::channel();
+            app.listen_any(RESET_SEARCH_EVENT, move |_| {
+                let _ = sender.send(());
+            });
+
+            let outcome = tauri::async_runtime::block_on(copy_and_hide(
+                app.handle().clone(),
+                "git status".to_string(),
+                None,
+            ))
+            .expect("copy_and_hide should succeed");
+            assert!(outcome.copied);
+            assert_eq!(app.handle().clipboard().read_text().unwrap(), "git status");
+            // Hiding went through the usual path
+            assert!(receiver.recv_timeout(Duration::from_secs(1)).is_ok());
+        });
+    }
+
+    #[test]
+    #[serial]
+    fn test_copy_empty_string_to_clipboard() {
+        with_temp_config("copy-empty", "copy_trailing_newline = \"strip\"\n", || {
+            let app = mock_builder()
+                .plugin(tauri_plugin_clipboard_manager::init())
+                .build(mock_context(noop_assets()))
+                .expect("failed to build mock app");
+
+            let empty_text = "".to_string();
+            let result = tauri::async_runtime::block_on(copy_to_clipboard(
+                app.handle().clone(),
+                empty_text,
+                None,
+            ));
+
+            assert!(
+                result.is_ok(),
+                "copy_to_clipboard should handle empty strings"
+            );
+        });
+    }
+
+    #[test]
+    #[serial]
+    fn test_copy_unicode_to_clipboard() {
+        with_temp_config(
+            "copy-unicode",
+            "copy_trailing_newline = \"strip\"\n",
+            || {
+                let app = mock_builder()
+                    .plugin(tauri_plugin_clipboard_manager::init())
+                    .build(mock_context(noop_assets()))
+                    .expect("failed to build mock app");
+
+                let unicode_text = "Hello 世界 🌍".to_string();
+                let result = tauri::async_runtime::block_on(copy_to_clipboard(
+                    app.handle().clone(),
+                    unicode_text.clone(),
+                    None,
+                ));
+
+                assert!(
+                    result.is_ok(),
+                    "copy_to_clipboard should handle unicode text"
+                );
+
+                let clipboard_content = app.handle().clipboard().read_text();
+                assert!(clipboard_content.is_ok());
+                assert_eq!(
+                    clipboard_content.unwrap(),
+                    unicode_text,
+                    "clipboard should preserve unicode characters"
+                );
+            },
+        );
+    }
+
+    #[test]
+    fn test_config_default_window_height() {
+        let config = Config::default();
+        assert_eq!(
+            config.window_height, 500,
+            "Default window height should be 500"
+        );
+    }
+
+    #[test]
+    fn test_validate_window_size_bounds() {
+        for value in [200, 700, 4000] {
+            assert!(validate_window_size("window height", value).is_ok());
+        }
+        for value in [0, 199, 4001, u32::MAX] {
+            let err = validate_window_size("window height", value).unwrap_err();
+            assert!(err.contains("Invalid window height"), "got: {}", err);
+        }
+    }
+
+    #[test]
+    fn test_config_default_window_width() {
+        let config = Config::default();
+        assert_eq!(
+            config.window_width, 700,
+            "Default window width should be 700"
+        );
+    }
+
+    #[test]
+    fn test_config_default_font_size() {
+        assert_eq!(Config::default().font_size, 14);
+        let parsed: Config =
+            toml::from_str(&render_config(&Config::default())).expect("valid TOML");
+        assert_eq!(parsed.font_size, 14);
+    }
+
+    #[test]
+    fn test_config_default_opacity() {
+        assert_eq!(Config::default().opacity, 1.0);
+        let parsed: Config =
+            toml::from_str(&render_config(&Config::default())).expect("valid TOML");
+        assert_eq!(parsed.opacity, 1.0);
+        let parsed: Config = toml::from_str("opacity = 1").expect("integers are accepted");
+        assert_eq!(parsed.opacity, 1.0);
+    }
+
+    #[test]
+    fn test_update_config_rejects_opacity_out_of_range() {
+        let temp = TempConfig::with_contents("opacity", "theme = \"light\"\n");
+        let path = &temp.path;
+
+        for opacity in [1.5, -0.1, f64::NAN] {
+            let err = update_config_at(
+                path,
+                ConfigUpdate {
+                    opacity: Some(opacity),
+                    ..Default::default()
+                },
+            )
+            .unwrap_err();
+            assert!(err.contains("Invalid opacity"), "got: {}", err);
+        }
+        assert_eq!(fs::read_to_string(path).unwrap(), "theme = \"light\"\n");
+
+        let config = update_config_at(
+            path,
+            ConfigUpdate {
+                opacity: Some(0.85),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(config.opacity, 0.85);
+    }
 
-            // Create menu
-            let settings_item = MenuItemBuilder::with_id("settings", "Settings").build(app)?;
-            let menu = MenuBuilder::new(app)
-                .item(&settings_item)
-                .build()?;
+    #[test]
+    fn test_update_config_rejects_font_size_out_of_range() {
+        let temp = TempConfig::with_contents("font-size", "theme = \"light\"\n");
+        let path = &temp.path;
 
-            app.set_menu(menu)?;
+        for size in [0, 7, 73, 500] {
+            let err = update_config_at(
+                path,
+                ConfigUpdate {
+                    font_size: Some(size),
+                    ..Default::default()
+                },
+            )
+            .unwrap_err();
+            assert!(err.contains("Invalid font size"), "got: {}", err);
+        }
+        assert_eq!(fs::read_to_string(path).unwrap(), "theme = \"light\"\n");
 
-            // Handle menu events
-            app.on_menu_event(move |app, event| {
-                if event.id().as_ref() == "settings" {
-                    // Check if settings window already exists
-                    if let Some(settings_window) = app.get_webview_window("settings") {
-                        let _ = settings_window.show();
-                        let _ = settings_window.set_focus();
-                    } else {
-                        // Create settings window
-                        use tauri::WebviewWindowBuilder;
-                        use tauri::WebviewUrl;
+        let config = update_config_at(
+            path,
+            ConfigUpdate {
+                font_size: Some(18),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(config.font_size, 18);
+    }
 
-                        let settings_window = WebviewWindowBuilder::new(
-                            app,
-                            "settings",
-                            WebviewUrl::App("settings.html".into())
-                        )
-                        .title("Atuin Bar Settings")
-                        .inner_size(500.0, 400.0)
-                        .resizable(false)
-                        .center()
-                        .build();
+    #[test]
+    fn test_normalize_filters_canonicalizes_values() {
+        let filters = SearchFilters {
+            directory: Some("  /home/user/project  ".to_string()),
+            exit_filter: Some(" FAILURE ".to_string()),
+            time_range: Some("7D".to_string()),
+            before: None,
+            boolean_query: true,
+            case_sensitive: Some(true),
+            duration_percentile: Some(95.0),
+            min_duration_ms: Some(1_000),
+            max_duration_ms: Some(60_000),
+            dedup: true,
+            dedup_by: Some(" Directory ".to_string()),
+            limit: Some(10),
+            offset: Some(20),
+            search_mode: Some(" Fuzzy ".to_string()),
+            session: Some(" 0195f3a2 ".to_string()),
+            host: Some(" laptop ".to_string()),
+            sort: Some(" Recent ".to_string()),
+        };
 
-                        if let Ok(win) = settings_window {
-                            let _ = win.show();
-                        }
-                    }
-                }
-            });
+        let normalized = normalize_filters(filters).expect("filters should be valid");
+        assert_eq!(
+            normalized,
+            SearchFilters {
+                directory: Some("/home/user/project".to_string()),
+                exit_filter: Some("failure".to_string()),
+                time_range: Some("7d".to_string()),
+                before: None,
+                boolean_query: true,
+                case_sensitive: Some(true),
+                duration_percentile: Some(95.0),
+                min_duration_ms: Some(1_000),
+                max_duration_ms: Some(60_000),
+                dedup: true,
+                dedup_by: Some("directory".to_string()),
+                limit: Some(10),
+                offset: Some(20),
+                search_mode: Some("fuzzy".to_string()),
+                session: Some("0195f3a2".to_string()),
+                host: Some("laptop".to_string()),
+                sort: Some("recent".to_string()),
+            }
+        );
+    }
 
-            Ok(())
-        })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
-}
+    #[test]
+    fn test_normalize_filters_maps_empty_and_all_to_none() {
+        let filters = SearchFilters {
+            directory: Some("   ".to_string()),
+            exit_filter: Some("All".to_string()),
+            time_range: Some("".to_string()),
+            sort: Some("Relevance".to_string()),
+            ..Default::default()
+        };
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use serial_test::serial;
-    use tauri::test::{mock_builder, mock_context, noop_assets};
+        let normalized = normalize_filters(filters).expect("filters should be valid");
+        assert_eq!(normalized, SearchFilters::default());
+    }
 
     #[test]
-    fn test_greet() {
-        let result = greet("World");
-        assert_eq!(result, "Hello, World! You've been greeted from Rust!");
+    fn test_normalize_filters_rejects_unknown_values() {
+        let filters = SearchFilters {
+            exit_filter: Some("sometimes".to_string()),
+            ..Default::default()
+        };
+        let err = normalize_filters(filters).unwrap_err();
+        assert!(err.contains("Invalid exit filter"), "got: {}", err);
+
+        let filters = SearchFilters {
+            dedup: true,
+            dedup_by: Some("host".to_string()),
+            ..Default::default()
+        };
+        let err = normalize_filters(filters).unwrap_err();
+        assert!(err.contains("Invalid dedup key"), "got: {}", err);
+
+        let filters = SearchFilters {
+            sort: Some("alphabetical".to_string()),
+            ..Default::default()
+        };
+        let err = normalize_filters(filters).unwrap_err();
+        assert!(err.contains("Invalid sort"), "got: {}", err);
     }
 
     #[test]
-    fn test_greet_empty_string() {
-        let result = greet("");
-        assert_eq!(result, "Hello, ! You've been greeted from Rust!");
+    fn test_sort_output_lines_in_display_order() {
+        let output = "b|0|1ms|/|2024-01-02 00:00:00\n\
+                      c|0|1ms|/|2024-01-03 00:00:00\n\
+                      x|0|1ms|/|never\n\
+                      a|0|1ms|/|2024-01-01 00:00:00\n";
+        // Shown reversed, so the last line is the first result
+        let shown = |output: String| -> Vec<String> {
+            results::parse_atuin_output(&output)
+                .into_iter()
+                .rev()
+                .map(|entry| entry.command)
+                .collect()
+        };
+
+        assert_eq!(
+            shown(sort_output_lines(output, "recent")),
+            ["c", "b", "a", "x"]
+        );
+        assert_eq!(
+            shown(sort_output_lines(output, "oldest")),
+            ["a", "b", "c", "x"]
+        );
+        assert_eq!(sort_output_lines(output, "relevance"), output);
     }
 
-    #[tokio::test]
-    #[serial]
-    async fn test_copy_to_clipboard() {
-        // Create a mock Tauri app with clipboard plugin
-        let app = mock_builder()
-            .plugin(tauri_plugin_clipboard_manager::init())
-            .build(mock_context(noop_assets()))
-            .expect("failed to build mock app");
+    #[test]
+    fn test_normalize_filters_accepts_exit_codes() {
+        for (value, expected) in [(" 127 ", "127"), ("+130", "130"), ("0", "0"), ("-1", "-1")] {
+            let filters = SearchFilters {
+                exit_filter: Some(value.to_string()),
+                ..Default::default()
+            };
+            let normalized = normalize_filters(filters).unwrap();
+            assert_eq!(
+                normalized.exit_filter.as_deref(),
+                Some(expected),
+                "{:?}",
+                value
+            );
+        }
 
-        let test_text = "Hello, clipboard!".to_string();
+        for value in ["12x", "1.5", "99999999999"] {
+            let filters = SearchFilters {
+                exit_filter: Some(value.to_string()),
+                ..Default::default()
+            };
+            let err = normalize_filters(filters).unwrap_err();
+            assert!(err.contains("Invalid exit filter"), "got: {}", err);
+        }
+    }
 
-        // Test the copy_to_clipboard command
-        let result = copy_to_clipboard(app.handle().clone(), test_text.clone()).await;
+    #[test]
+    fn test_normalize_filters_keeps_custom_time_ranges() {
+        let filters = SearchFilters {
+            time_range: Some(" 3 Months Ago ".to_string()),
+            before: Some(" 2024-02-01 ".to_string()),
+            ..Default::default()
+        };
+        let normalized = normalize_filters(filters).unwrap();
+        assert_eq!(normalized.time_range.as_deref(), Some("3 Months Ago"));
+        assert_eq!(normalized.before.as_deref(), Some("2024-02-01"));
 
-        // Verify the command executed successfully
-        assert!(result.is_ok(), "copy_to_clipboard should succeed");
+        let filters = SearchFilters {
+            before: Some("  ".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(normalize_filters(filters).unwrap().before, None);
+    }
 
-        // Verify the text was written to clipboard
-        let clipboard_content = app.handle().clipboard().read_text();
-        assert!(
-            clipboard_content.is_ok(),
-            "should be able to read clipboard"
+    #[test]
+    fn test_normalize_filters_rejects_out_of_range_percentile() {
+        for percentile in [-1.0, 100.5, f32::NAN] {
+            let filters = SearchFilters {
+                duration_percentile: Some(percentile),
+                ..Default::default()
+            };
+            let err = normalize_filters(filters).unwrap_err();
+            assert!(err.contains("Invalid duration percentile"), "got: {}", err);
+        }
+
+        let filters = SearchFilters {
+            duration_percentile: Some(90.0),
+            ..Default::default()
+        };
+        assert_eq!(
+            normalize_filters(filters).unwrap().duration_percentile,
+            Some(90.0)
+        );
+    }
+
+    #[test]
+    fn test_remember_results_keeps_display_order() {
+        let last = Mutex::new(LastResults::new());
+        let entries = results::parse_atuin_output(
+            "old|0|1ms|/tmp|2024-01-01 09:00:00\nnew|0|1ms|/tmp|2024-01-01 10:00:00",
+        );
+        remember_results(&last, &entries).unwrap();
+
+        let last = last.lock().unwrap();
+        assert_eq!(last.nth_command(1).unwrap(), "new");
+        assert_eq!(last.nth_command(2).unwrap(), "old");
+    }
+
+    #[test]
+    fn test_matches_case_follows_search_mode() {
+        assert!(matches_case("Git push", "Git", "prefix"));
+        assert!(!matches_case("my Git push", "Git", "prefix"));
+        assert!(matches_case("my Git push", "Git", "fulltext"));
+        assert!(!matches_case("my git push", "Git", "fulltext"));
+        assert!(matches_case(
+            "cargo Build --Release",
+            "^cargo Release !test",
+            "fuzzy"
+        ));
+        assert!(!matches_case("cargo build --release", "Release", "fuzzy"));
+    }
+
+    #[test]
+    fn test_normalize_filters_rejects_inverted_duration_range() {
+        let filters = SearchFilters {
+            min_duration_ms: Some(10_000),
+            max_duration_ms: Some(1_000),
+            ..Default::default()
+        };
+        let err = normalize_filters(filters).unwrap_err();
+        assert!(err.contains("Invalid duration range"), "got: {}", err);
+
+        let filters = SearchFilters {
+            min_duration_ms: Some(1_000),
+            max_duration_ms: Some(1_000),
+            ..Default::default()
+        };
+        assert!(normalize_filters(filters).is_ok());
+    }
+
+    #[test]
+    fn test_normalize_filters_rejects_inverted_time_range() {
+        for (time_range, before) in [("2024-02-01", "2024-01-01"), ("7d", "2000-01-01 12:00:00")] {
+            let filters = SearchFilters {
+                time_range: Some(time_range.to_string()),
+                before: Some(before.to_string()),
+                ..Default::default()
+            };
+            let err = normalize_filters(filters).unwrap_err();
+            assert!(err.contains("Invalid time range"), "got: {}", err);
+        }
+
+        // Ranges in order, and bounds only atuin can interpret, are kept
+        for (time_range, before) in [
+            ("2024-01-01", "2024-02-01"),
+            ("2000-01-01", "24h"),
+            ("3 months ago", "2000-01-01"),
+        ] {
+            let filters = SearchFilters {
+                time_range: Some(time_range.to_string()),
+                before: Some(before.to_string()),
+                ..Default::default()
+            };
+            assert!(
+                normalize_filters(filters).is_ok(),
+                "{} to {}",
+                time_range,
+                before
+            );
+        }
+    }
+
+    #[test]
+    fn test_resolve_filters_defaults_to_max_results() {
+        let config = Config::default();
+        assert_eq!(resolve_filters(None, None, &config).limit, Some(20));
+
+        let filters = SearchFilters {
+            limit: Some(5),
+            ..Default::default()
+        };
+        assert_eq!(
+            resolve_filters(Some(filters.clone()), None, &config).limit,
+            Some(5)
+        );
+        assert_eq!(
+            resolve_filters(Some(filters), Some(8), &config).limit,
+            Some(8)
+        );
+
+        let zero = SearchFilters {
+            limit: Some(0),
+            ..Default::default()
+        };
+        assert!(normalize_filters(zero).is_err());
+
+        let config = Config {
+            max_results: 500,
+            fetch_limit: 100,
+            ..Default::default()
+        };
+        assert_eq!(resolve_filters(None, None, &config).limit, Some(100));
+    }
+
+    #[test]
+    fn test_resolve_filters_uses_configured_search_mode() {
+        let config = Config {
+            search_mode: "fuzzy".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            resolve_filters(None, None, &config).search_mode.as_deref(),
+            Some("fuzzy")
         );
+
+        let filters = SearchFilters {
+            search_mode: Some("fulltext".to_string()),
+            ..Default::default()
+        };
         assert_eq!(
-            clipboard_content.unwrap(),
-            test_text,
-            "clipboard should contain the copied text"
+            resolve_filters(Some(filters), None, &config)
+                .search_mode
+                .as_deref(),
+            Some("fulltext")
         );
     }
 
-    #[tokio::test]
+    #[test]
+    fn test_is_valid_theme() {
+        assert!(is_valid_theme("dark"));
+        assert!(is_valid_theme("light"));
+        assert!(is_valid_theme("system"));
+
+        for theme in ["darl", "Dark", "", " light", "solarized"] {
+            assert!(!is_valid_theme(theme), "{:?} should be rejected", theme);
+        }
+    }
+
+    #[test]
+    fn test_resolve_theme_follows_system_appearance() {
+        assert_eq!(resolve_theme("system", Some(tauri::Theme::Light)), "light");
+        assert_eq!(resolve_theme("system", Some(tauri::Theme::Dark)), "dark");
+        assert_eq!(resolve_theme("system", None), "dark");
+
+        // Explicit themes ignore the OS appearance
+        assert_eq!(resolve_theme("dark", Some(tauri::Theme::Light)), "dark");
+        assert_eq!(resolve_theme("light", None), "light");
+    }
+
+    #[test]
+    fn test_coerce_unknown_theme() {
+        let mut config = Config {
+            theme: "darl".to_string(),
+            ..Default::default()
+        };
+        coerce_unknown_theme(&mut config);
+        assert_eq!(config.theme, "dark");
+
+        let mut config = Config {
+            theme: "light".to_string(),
+            ..Default::default()
+        };
+        coerce_unknown_theme(&mut config);
+        assert_eq!(config.theme, "light");
+    }
+
+    #[test]
+    fn test_default_config_file_is_current() {
+        let temp = TempConfig::new("default-file");
+        let path = &temp.path;
+
+        assert_eq!(load_config_at(path).version, config_file::CONFIG_VERSION);
+        let contents = fs::read_to_string(path).unwrap();
+        assert!(contents.contains(&format!("\nversion = {}\n", config_file::CONFIG_VERSION)));
+        assert_eq!(config_file::migrate_config(&contents).unwrap(), None);
+        assert_eq!(load_config_at(path).version, config_file::CONFIG_VERSION);
+    }
+
+    #[test]
     #[serial]
-    async fn test_copy_empty_string_to_clipboard() {
-        let app = mock_builder()
-            .plugin(tauri_plugin_clipboard_manager::init())
-            .build(mock_context(noop_assets()))
-            .expect("failed to build mock app");
+    fn test_env_overrides_take_precedence_over_file() {
+        let temp = TempConfig::with_contents(
+            "env-overrides",
+            "version = 2\ntheme = \"light\"\nmax_results = 30\nwindow_width = 800\n",
+        );
+        let path = &temp.path;
 
-        let empty_text = "".to_string();
-        let result = copy_to_clipboard(app.handle().clone(), empty_text).await;
+        std::env::set_var("ATUIN_BAR_THEME", "system");
+        std::env::set_var("ATUIN_BAR_MAX_RESULTS", "45");
+        std::env::set_var("ATUIN_BAR_WINDOW_WIDTH", "wide");
+        let mut config = load_config_at(path);
+        let overridden = apply_env_overrides(&mut config, |name| std::env::var(name).ok());
+        std::env::remove_var("ATUIN_BAR_THEME");
+        std::env::remove_var("ATUIN_BAR_MAX_RESULTS");
+        std::env::remove_var("ATUIN_BAR_WINDOW_WIDTH");
 
-        assert!(
-            result.is_ok(),
-            "copy_to_clipboard should handle empty strings"
+        assert_eq!(config.theme, "system");
+        assert_eq!(config.max_results, 45);
+        // Not a number, so the file value stays
+        assert_eq!(config.window_width, 800);
+        assert_eq!(overridden, vec!["theme", "max_results"]);
+    }
+
+    #[test]
+    fn test_env_overridden_values_are_not_saved() {
+        let var = |name: &str| (name == "ATUIN_BAR_SHORTCUT").then(|| "Alt+Space".to_string());
+        let file = Config {
+            shortcut: "Super+H".to_string(),
+            ..Default::default()
+        };
+        let mut config = file.clone();
+        apply_env_overrides(&mut config, var);
+        assert_eq!(config.shortcut, "Alt+Space");
+
+        config.max_results = 40;
+        restore_env_overridden(&mut config, &file, var);
+        assert_eq!(config.shortcut, "Super+H");
+        assert_eq!(config.max_results, 40);
+    }
+
+    #[test]
+    fn test_edits_to_env_overridden_fields_are_refused() {
+        let var = |name: &str| (name == "ATUIN_BAR_SHORTCUT").then(|| "Alt+Space".to_string());
+        let mut current = Config::default();
+        apply_env_overrides(&mut current, var);
+
+        let mut edited = current.clone();
+        edited.max_results = 40;
+        assert!(check_env_overridden(&edited, &current, var).is_ok());
+
+        edited.shortcut = "Control+Shift+K".to_string();
+        let err = check_env_overridden(&edited, &current, var).unwrap_err();
+        assert_eq!(
+            err,
+            "shortcut is set by the ATUIN_BAR_SHORTCUT environment variable; unset it to change it here"
         );
     }
 
-    #[tokio::test]
-    #[serial]
-    async fn test_copy_unicode_to_clipboard() {
-        let app = mock_builder()
-            .plugin(tauri_plugin_clipboard_manager::init())
-            .build(mock_context(noop_assets()))
-            .expect("failed to build mock app");
+    #[test]
+    fn test_update_config_rejects_unknown_theme() {
+        let temp = TempConfig::with_contents("theme", "theme = \"light\"\n");
+        let path = &temp.path;
+
+        let err = update_config_at(
+            path,
+            ConfigUpdate {
+                theme: Some("darl".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err, "unknown theme: darl");
+        assert_eq!(fs::read_to_string(path).unwrap(), "theme = \"light\"\n");
+    }
 
-        let unicode_text = "Hello 世界 🌍".to_string();
-        let result = copy_to_clipboard(app.handle().clone(), unicode_text.clone()).await;
+    #[test]
+    fn test_update_config_rejects_invalid_shortcut_before_writing() {
+        let temp = TempConfig::with_contents("shortcut", "shortcut = \"Alt+Space\"\n");
+        let path = &temp.path;
 
+        let err = update_config_at(
+            path,
+            ConfigUpdate {
+                shortcut: Some("NotAKey+Foo".to_string()),
+                theme: Some("light".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap_err();
         assert!(
-            result.is_ok(),
-            "copy_to_clipboard should handle unicode text"
+            err.contains("Invalid shortcut 'NotAKey+Foo'"),
+            "got: {}",
+            err
+        );
+        assert_eq!(
+            fs::read_to_string(path).unwrap(),
+            "shortcut = \"Alt+Space\"\n"
+        );
+    }
+
+    #[test]
+    fn test_update_config_rejects_unknown_search_mode() {
+        let temp = TempConfig::with_contents("search-mode", "theme = \"light\"\n");
+        let path = &temp.path;
+
+        let err = update_config_at(
+            path,
+            ConfigUpdate {
+                search_mode: Some("regex".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap_err();
+        assert!(err.contains("Invalid search mode 'regex'"), "got: {}", err);
+        assert_eq!(fs::read_to_string(path).unwrap(), "theme = \"light\"\n");
+
+        let config = update_config_at(
+            path,
+            ConfigUpdate {
+                search_mode: Some("fuzzy".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(config.search_mode, "fuzzy");
+        let saved: Config = toml::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(saved.search_mode, "fuzzy");
+    }
+
+    #[test]
+    fn test_filter_output_lines_applies_boolean_query() {
+        let output = "docker ps|0|1s|/tmp|2024-01-01 10:00:00\n\
+                      podman run alpine|0|1s|/tmp|2024-01-01 10:00:01\n\
+                      docker compose up|1|1s|/tmp|2024-01-01 10:00:02\n";
+        let expr = boolean_query::parse_boolean_query("(docker OR podman) NOT compose").unwrap();
+
+        let filtered = filter_output_lines(output, |entry| expr.matches(&entry.command));
+        assert_eq!(
+            filtered,
+            "docker ps|0|1s|/tmp|2024-01-01 10:00:00\n\
+             podman run alpine|0|1s|/tmp|2024-01-01 10:00:01\n"
+        );
+    }
+
+    #[test]
+    fn test_render_config_round_trips_size_presets() {
+        let mut config = Config {
+            size_preset: "tall".to_string(),
+            ..Default::default()
+        };
+        config.size_presets.insert(
+            "tall".to_string(),
+            SizePreset {
+                width: 700,
+                height: 900,
+            },
         );
 
-        let clipboard_content = app.handle().clipboard().read_text();
-        assert!(clipboard_content.is_ok());
+        let parsed: Config = toml::from_str(&render_config(&config)).expect("valid TOML");
+        assert_eq!(parsed.size_preset, "tall");
+        assert_eq!(parsed.size_presets, config.size_presets);
+    }
+
+    #[test]
+    fn test_render_config_round_trips_atuin_env() {
+        let mut config = Config::default();
+        config
+            .atuin_env
+            .insert("ATUIN_LOG".to_string(), "debug".to_string());
+        config
+            .atuin_env
+            .insert("MY_VAR".to_string(), "a \"quoted\" value".to_string());
+
+        let parsed: Config = toml::from_str(&render_config(&config)).expect("valid TOML");
+        assert_eq!(parsed.atuin_env, config.atuin_env);
+    }
+
+    #[test]
+    fn test_config_parses_shortcut_list() {
+        let parsed: Config = toml::from_str(
+            "shortcut = \"Alt+Space\"\nshortcuts = [\"Super+H\", \"Control+Shift+K\"]\n",
+        )
+        .expect("valid TOML");
+        assert_eq!(parsed.shortcut, "Alt+Space");
         assert_eq!(
-            clipboard_content.unwrap(),
-            unicode_text,
-            "clipboard should preserve unicode characters"
+            parsed.shortcuts,
+            vec!["Super+H".to_string(), "Control+Shift+K".to_string()]
         );
+
+        let parsed: Config = toml::from_str(&render_config(&parsed)).expect("valid TOML");
+        assert_eq!(
+            parsed.shortcuts,
+            vec!["Super+H".to_string(), "Control+Shift+K".to_string()]
+        );
+        assert!(Config::default().shortcuts.is_empty());
     }
 
     #[test]
-    fn test_config_default_window_width() {
+    fn test_allow_execute_is_an_alias_for_allow_run() {
+        let parsed: Config = toml::from_str("allow_execute = true").expect("valid TOML");
+        assert!(parsed.allow_run);
+        assert!(
+            !Config::default().allow_run,
+            "Running commands is off by default"
+        );
+    }
+
+    #[test]
+    fn test_config_round_trips_through_file() {
+        let temp = TempConfig::new("config-round-trip");
+        let path = &temp.path;
+
+        let mut config = Config {
+            theme: "light".to_string(),
+            max_results: 75,
+            opacity: 0.8,
+            window_x: Some(120),
+            search_mode: "fuzzy".to_string(),
+            shortcuts: vec!["Super+H".to_string()],
+            ..Default::default()
+        };
+        config
+            .atuin_env
+            .insert("ATUIN_LOG".to_string(), "debug".to_string());
+        config_file::write_config(path, &config).unwrap();
+        assert_eq!(load_config_at(path), config);
+
+        // Saving over the existing file edits it in place
+        config.theme = "dark".to_string();
+        config.window_x = None;
+        config.atuin_env.clear();
+        config_file::write_config(path, &config).unwrap();
+        assert_eq!(load_config_at(path), config);
+    }
+
+    #[test]
+    fn test_render_config_round_trips_window_position() {
         let config = Config::default();
+        let parsed: Config = toml::from_str(&render_config(&config)).expect("valid TOML");
+        assert_eq!((parsed.window_x, parsed.window_y), (None, None));
+
+        let config = Config {
+            window_x: Some(-1280),
+            window_y: Some(40),
+            ..Default::default()
+        };
+        let parsed: Config = toml::from_str(&render_config(&config)).expect("valid TOML");
+        assert_eq!((parsed.window_x, parsed.window_y), (Some(-1280), Some(40)));
+    }
+
+    #[test]
+    fn test_render_config_escapes_string_values() {
+        let mut config = Config {
+            shortcut: "Alt+\"Space\"".to_string(),
+            theme: "my\"weird\\theme".to_string(),
+            terminal_cmd: "kitty --title \"a\nb\"".to_string(),
+            ..Default::default()
+        };
+        config.size_presets.insert(
+            "very tall".to_string(),
+            SizePreset {
+                width: 700,
+                height: 1200,
+            },
+        );
+
+        let parsed: Config = toml::from_str(&render_config(&config)).expect("valid TOML");
+        assert_eq!(parsed.theme, "my\"weird\\theme");
+        assert_eq!(parsed.shortcut, config.shortcut);
+        assert_eq!(parsed.terminal_cmd, config.terminal_cmd);
+        assert_eq!(parsed.size_presets, config.size_presets);
+    }
+
+    #[test]
+    fn test_update_config_preserves_comments_and_unknown_tables() {
+        let temp = TempConfig::with_contents(
+            "update-config",
+            r#"# My hand-tuned settings
+max_results = 30 # plenty
+
+theme = "dark"
+
+[advanced]
+# Not an atuin-bar setting
+experimental = true
+"#,
+        );
+        let path = &temp.path;
+
+        let config = update_config_at(
+            path,
+            ConfigUpdate {
+                theme: Some("light".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(config.theme, "light");
+        assert_eq!(config.max_results, 30);
+
         assert_eq!(
-            config.window_width, 700,
-            "Default window width should be 700"
+            fs::read_to_string(path).unwrap(),
+            r#"# My hand-tuned settings
+max_results = 30 # plenty
+
+theme = "light"
+
+[advanced]
+# Not an atuin-bar setting
+experimental = true
+"#
         );
     }
 
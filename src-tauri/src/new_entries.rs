@@ -0,0 +1,129 @@
+use crate::results::{parse_timestamp, HistoryEntry};
+
+/// Remembers when the previous search ran, so entries recorded since then
+/// can be flagged as new.
+///
+/// The watermark is the wall-clock time of the search rather than the newest
+/// entry it returned, so an entry that only showed up in history after the
+/// search (e.g. synced from another machine) is flagged too. Times are
+/// seconds since the Unix epoch, compared with atuin's timestamps read as
+/// UTC by `parse_timestamp`.
+#[derive(Debug, Default)]
+pub struct SearchWatermark {
+    last_search: Option<i64>,
+    /// Watermark the latest search was compared against, for re-reading its
+    /// results (e.g. later pages) without losing the flags
+    previous: Option<i64>,
+}
+
+impl SearchWatermark {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set `is_new` on entries recorded since the previous search, then
+    /// move the watermark to `now`, when this search ran. Nothing is flagged
+    /// on the first search, since there is nothing to compare against.
+    pub fn mark_new(&mut self, entries: &mut [HistoryEntry], now: i64) {
+        self.previous = self.last_search;
+        self.flag_since_previous(entries);
+        self.last_search = Some(now);
+    }
+
+    /// Set `is_new` against the watermark the latest search was compared
+    /// against, without advancing it
+    pub fn flag_since_previous(&self, entries: &mut [HistoryEntry]) {
+        for entry in entries.iter_mut() {
+            let time = parse_timestamp(&entry.time);
+            entry.is_new = matches!((time, self.previous), (Some(t), Some(last)) if t > last);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::parse_atuin_line;
+
+    fn entry_at(command: &str, time: &str) -> HistoryEntry {
+        parse_atuin_line(&format!("{}|0|1ms|/tmp|{}", command, time)).unwrap()
+    }
+
+    fn at(time: &str) -> i64 {
+        parse_timestamp(time).unwrap()
+    }
+
+    #[test]
+    fn test_mark_new_flags_entries_after_previous_search() {
+        let mut watermark = SearchWatermark::new();
+        let mut first = vec![
+            entry_at("ls", "2024-01-01 10:00:00"),
+            entry_at("pwd", "2024-01-01 09:00:00"),
+        ];
+        watermark.mark_new(&mut first, at("2024-01-01 10:01:00"));
+        assert!(first.iter().all(|e| !e.is_new));
+
+        let mut second = vec![
+            entry_at("cargo test", "2024-01-01 10:05:00"),
+            entry_at("ls", "2024-01-01 10:00:00"),
+            entry_at("pwd", "2024-01-01 09:00:00"),
+        ];
+        watermark.mark_new(&mut second, at("2024-01-01 10:06:00"));
+        assert!(second[0].is_new);
+        assert!(!second[1].is_new);
+        assert!(!second[2].is_new);
+
+        // The watermark advanced, so the same entries are no longer new
+        watermark.mark_new(&mut second, at("2024-01-01 10:07:00"));
+        assert!(second.iter().all(|e| !e.is_new));
+    }
+
+    #[test]
+    fn test_entries_older_than_the_newest_seen_are_new_if_after_the_search() {
+        let mut watermark = SearchWatermark::new();
+        // The newest entry seen is from 10:00, but the search ran at 10:30
+        watermark.mark_new(
+            &mut [entry_at("ls", "2024-01-01 10:00:00")],
+            at("2024-01-01 10:30:00"),
+        );
+
+        // Recorded at 10:20 (before the search) and 10:40 (after it)
+        let mut entries = vec![
+            entry_at("git pull", "2024-01-01 10:40:00"),
+            entry_at("make", "2024-01-01 10:20:00"),
+        ];
+        watermark.mark_new(&mut entries, at("2024-01-01 10:45:00"));
+        assert!(entries[0].is_new);
+        assert!(!entries[1].is_new);
+    }
+
+    #[test]
+    fn test_flag_since_previous_keeps_flags_of_latest_search() {
+        let mut watermark = SearchWatermark::new();
+        watermark.mark_new(
+            &mut [entry_at("ls", "2024-01-01 10:00:00")],
+            at("2024-01-01 10:00:00"),
+        );
+
+        let mut entries = vec![entry_at("cargo test", "2024-01-01 10:05:00")];
+        watermark.mark_new(&mut entries, at("2024-01-01 10:06:00"));
+        assert!(entries[0].is_new);
+
+        entries[0].is_new = false;
+        watermark.flag_since_previous(&mut entries);
+        assert!(entries[0].is_new);
+    }
+
+    #[test]
+    fn test_mark_new_ignores_unparseable_times() {
+        let mut watermark = SearchWatermark::new();
+        watermark.mark_new(
+            &mut [entry_at("ls", "2024-01-01 10:00:00")],
+            at("2024-01-01 10:00:00"),
+        );
+
+        let mut entries = vec![entry_at("echo", "yesterday")];
+        watermark.mark_new(&mut entries, at("2024-01-01 10:05:00"));
+        assert!(!entries[0].is_new);
+    }
+}
@@ -0,0 +1,124 @@
+use crate::atuin::OutputLoss;
+
+/// One page of search results plus what the UI needs to render a windowed
+/// list without receiving every result
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct SearchPage<T> {
+    /// Newest first, in the order results are displayed
+    pub entries: Vec<T>,
+    /// Zero-based page index
+    pub page: usize,
+    pub page_size: usize,
+    /// Whether a later page has results
+    pub has_more: bool,
+}
+
+/// Up to `limit` results starting `offset` matches back from the newest,
+/// fetched from atuin rather than sliced from a cached result
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct ResultWindow<T> {
+    /// Oldest first, like `atuin search` output
+    pub entries: Vec<T>,
+    pub offset: u32,
+    /// Whether older results follow this window
+    pub has_more: bool,
+    #[serde(flatten)]
+    pub loss: OutputLoss,
+}
+
+/// Trim results fetched with one more than `limit` (oldest first) to the
+/// window, using the extra result to tell whether more follow
+pub fn result_window<T>(mut entries: Vec<T>, offset: u32, limit: u32) -> ResultWindow<T> {
+    let limit = limit as usize;
+    let has_more = entries.len() > limit;
+    if has_more {
+        entries.drain(..entries.len() - limit);
+    }
+    ResultWindow {
+        entries,
+        offset,
+        has_more,
+        loss: OutputLoss::default(),
+    }
+}
+
+/// Check a page size requested by the UI
+pub fn validate_page_size(page_size: usize) -> Result<(), String> {
+    if page_size == 0 {
+        return Err("Page size must be at least 1".to_string());
+    }
+    Ok(())
+}
+
+/// Index range of the zero-based `page`; a page past the end is empty
+fn page_range(total_count: usize, page: usize, page_size: usize) -> std::ops::Range<usize> {
+    let start = page.saturating_mul(page_size).min(total_count);
+    let end = start.saturating_add(page_size).min(total_count);
+    start..end
+}
+
+/// Slice `items` into the zero-based `page`
+pub fn paginate<T: Clone>(items: &[T], page: usize, page_size: usize) -> SearchPage<T> {
+    let range = page_range(items.len(), page, page_size);
+    SearchPage {
+        has_more: range.end < items.len(),
+        entries: items[range].to_vec(),
+        page,
+        page_size,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items() -> Vec<u32> {
+        (1..=25).collect()
+    }
+
+    #[test]
+    fn test_first_page() {
+        let page = paginate(&items(), 0, 10);
+        assert_eq!(page.entries, (1..=10).collect::<Vec<_>>());
+        assert!(page.has_more);
+    }
+
+    #[test]
+    fn test_middle_and_last_pages() {
+        let page = paginate(&items(), 1, 10);
+        assert_eq!(page.entries, (11..=20).collect::<Vec<_>>());
+        assert!(page.has_more);
+
+        let page = paginate(&items(), 2, 10);
+        assert_eq!(page.entries, (21..=25).collect::<Vec<_>>());
+        assert!(!page.has_more);
+    }
+
+    #[test]
+    fn test_page_past_the_end_is_empty() {
+        let page = paginate(&items(), 7, 10);
+        assert!(page.entries.is_empty());
+        assert!(!page.has_more);
+
+        let page = paginate(&items(), usize::MAX, usize::MAX);
+        assert!(page.entries.is_empty());
+    }
+
+    #[test]
+    fn test_result_window_drops_the_extra_oldest_result() {
+        let window = result_window(vec![1, 2, 3, 4], 10, 3);
+        assert_eq!(window.entries, vec![2, 3, 4]);
+        assert_eq!(window.offset, 10);
+        assert!(window.has_more);
+
+        let window = result_window(vec![1, 2], 0, 3);
+        assert_eq!(window.entries, vec![1, 2]);
+        assert!(!window.has_more);
+    }
+
+    #[test]
+    fn test_validate_page_size() {
+        assert!(validate_page_size(0).is_err());
+        assert!(validate_page_size(50).is_ok());
+    }
+}
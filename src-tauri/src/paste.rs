@@ -0,0 +1,68 @@
+use std::time::Duration;
+
+use enigo::{Enigo, Keyboard, Settings};
+
+use crate::permissions::{PermissionState, PermissionStatus};
+
+/// How long to wait after hiding the search window so focus can return to
+/// the window that had it before
+pub const PASTE_FOCUS_DELAY: Duration = Duration::from_millis(150);
+
+/// Check that pasting is enabled by `paste_on_select` and that the OS lets
+/// the app synthesize input. The error tells the user what to change.
+pub fn check_paste_allowed(enabled: bool, permissions: &PermissionStatus) -> Result<(), String> {
+    if !enabled {
+        return Err("Pasting is disabled; set paste_on_select = true in config.toml".to_string());
+    }
+    if permissions.state == PermissionState::Missing {
+        return Err(format!(
+            "Missing permissions to paste: {}",
+            permissions.guidance
+        ));
+    }
+    Ok(())
+}
+
+/// The text to type for `command`. A trailing newline is dropped since
+/// typing it would run the command straight away.
+pub fn paste_text(command: &str) -> &str {
+    let stripped = command.strip_suffix('\n').unwrap_or(command);
+    stripped.strip_suffix('\r').unwrap_or(stripped)
+}
+
+/// Type `text` into whichever window has focus
+pub fn type_text(text: &str) -> Result<(), String> {
+    let mut enigo = Enigo::new(&Settings::default())
+        .map_err(|e| format!("Failed to start input synthesis: {}", e))?;
+    enigo
+        .text(text)
+        .map_err(|e| format!("Failed to type command: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::permissions::macos_permission_status;
+
+    #[test]
+    fn test_paste_requires_paste_on_select() {
+        let granted = macos_permission_status(true, true);
+        let err = check_paste_allowed(false, &granted).unwrap_err();
+        assert!(err.contains("paste_on_select"));
+        assert!(check_paste_allowed(true, &granted).is_ok());
+    }
+
+    #[test]
+    fn test_paste_reports_missing_permissions() {
+        let missing = macos_permission_status(false, true);
+        let err = check_paste_allowed(true, &missing).unwrap_err();
+        assert!(err.contains("Accessibility"));
+    }
+
+    #[test]
+    fn test_paste_text_drops_trailing_newline() {
+        assert_eq!(paste_text("ls -la\n"), "ls -la");
+        assert_eq!(paste_text("ls -la\r\n"), "ls -la");
+        assert_eq!(paste_text("echo a\necho b"), "echo a\necho b");
+    }
+}
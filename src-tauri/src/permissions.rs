@@ -0,0 +1,144 @@
+/// Whether global shortcuts have the OS permissions they need
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PermissionState {
+    Granted,
+    /// At least one required permission is missing; the shortcut may
+    /// silently do nothing
+    Missing,
+    /// The platform doesn't gate global shortcuts behind permissions
+    NotApplicable,
+}
+
+/// Result of checking the permissions global shortcuts rely on
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct PermissionStatus {
+    pub state: PermissionState,
+    /// Names of the missing permissions, as shown in System Settings
+    pub missing: Vec<String>,
+    /// What the user should do, empty when nothing is needed
+    pub guidance: String,
+}
+
+/// Build a status from which macOS permissions are granted
+pub fn macos_permission_status(accessibility: bool, input_monitoring: bool) -> PermissionStatus {
+    let missing: Vec<String> = [
+        (accessibility, "Accessibility"),
+        (input_monitoring, "Input Monitoring"),
+    ]
+    .iter()
+    .filter(|(granted, _)| !granted)
+    .map(|(_, name)| name.to_string())
+    .collect();
+
+    if missing.is_empty() {
+        return PermissionStatus {
+            state: PermissionState::Granted,
+            missing,
+            guidance: String::new(),
+        };
+    }
+    PermissionStatus {
+        state: PermissionState::Missing,
+        guidance: format!(
+            "Open System Settings > Privacy & Security and allow Atuin Bar under {}, then restart the app.",
+            missing.join(" and ")
+        ),
+        missing,
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod macos {
+    #[link(name = "ApplicationServices", kind = "framework")]
+    extern "C" {
+        fn AXIsProcessTrusted() -> u8;
+    }
+
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        fn CGPreflightListenEventAccess() -> bool;
+    }
+
+    pub fn accessibility_granted() -> bool {
+        // SAFETY: takes no arguments and only reads the process's TCC state
+        unsafe { AXIsProcessTrusted() != 0 }
+    }
+
+    pub fn input_monitoring_granted() -> bool {
+        // SAFETY: takes no arguments and doesn't prompt the user
+        unsafe { CGPreflightListenEventAccess() }
+    }
+}
+
+/// Check whether global shortcuts can fire. Only macOS gates them behind
+/// permissions; elsewhere this reports `NotApplicable`.
+pub fn check_shortcut_permissions() -> PermissionStatus {
+    #[cfg(target_os = "macos")]
+    {
+        macos_permission_status(
+            macos::accessibility_granted(),
+            macos::input_monitoring_granted(),
+        )
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        PermissionStatus {
+            state: PermissionState::NotApplicable,
+            missing: Vec::new(),
+            guidance: String::new(),
+        }
+    }
+}
+
+/// Check whether commands can be typed into other apps, which macOS only
+/// allows with the Accessibility permission
+pub fn check_paste_permissions() -> PermissionStatus {
+    #[cfg(target_os = "macos")]
+    {
+        macos_permission_status(macos::accessibility_granted(), true)
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        PermissionStatus {
+            state: PermissionState::NotApplicable,
+            missing: Vec::new(),
+            guidance: String::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(not(target_os = "macos"))]
+    fn test_permissions_not_applicable_off_macos() {
+        let status = check_shortcut_permissions();
+        assert_eq!(status.state, PermissionState::NotApplicable);
+        assert!(status.missing.is_empty());
+        assert!(status.guidance.is_empty());
+    }
+
+    #[test]
+    fn test_macos_permission_status_lists_missing_permissions() {
+        assert_eq!(
+            macos_permission_status(true, true).state,
+            PermissionState::Granted
+        );
+
+        let status = macos_permission_status(false, true);
+        assert_eq!(status.state, PermissionState::Missing);
+        assert_eq!(status.missing, vec!["Accessibility".to_string()]);
+        assert!(status.guidance.contains("Accessibility"));
+
+        let status = macos_permission_status(false, false);
+        assert_eq!(status.missing.len(), 2);
+        assert!(status
+            .guidance
+            .contains("Accessibility and Input Monitoring"));
+    }
+}
@@ -0,0 +1,104 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::get_config_path;
+
+/// Recent searches file, kept beside the config file
+pub fn get_recent_searches_path() -> Option<PathBuf> {
+    get_config_path().and_then(|path| path.parent().map(|dir| dir.join("searches.json")))
+}
+
+/// Queries recently searched for in the bar, newest first
+#[derive(Debug, Default, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct RecentSearches {
+    queries: Vec<String>,
+}
+
+impl RecentSearches {
+    /// Load recent searches from a file, starting empty if it is missing or
+    /// invalid
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        let json = serde_json::to_string(self)
+            .map_err(|e| format!("Failed to serialize recent searches: {}", e))?;
+        fs::write(path, json).map_err(|e| format!("Failed to write recent searches: {}", e))
+    }
+
+    /// Put `query` at the front, moving it there if it was already listed,
+    /// and forget the oldest searches beyond `limit`. Blank queries are
+    /// ignored.
+    pub fn record(&mut self, query: &str, limit: usize) {
+        let query = query.trim();
+        if query.is_empty() {
+            return;
+        }
+        self.queries.retain(|recent| recent != query);
+        self.queries.insert(0, query.to_string());
+        self.queries.truncate(limit);
+    }
+
+    /// Up to `limit` of the most recent searches, newest first
+    pub fn recent(&self, limit: usize) -> Vec<String> {
+        self.queries.iter().take(limit).cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeated_search_moves_to_front() {
+        let mut searches = RecentSearches::default();
+        for query in ["git", "cargo", "docker", "cargo"] {
+            searches.record(query, 10);
+        }
+        assert_eq!(searches.recent(10), vec!["cargo", "docker", "git"]);
+
+        searches.record("  git ", 10);
+        assert_eq!(searches.recent(10), vec!["git", "cargo", "docker"]);
+
+        searches.record("   ", 10);
+        assert_eq!(searches.recent(10).len(), 3);
+    }
+
+    #[test]
+    fn test_oldest_searches_are_dropped_beyond_limit() {
+        let mut searches = RecentSearches::default();
+        for n in 0..5 {
+            searches.record(&format!("query {}", n), 3);
+        }
+        assert_eq!(searches.recent(10), vec!["query 4", "query 3", "query 2"]);
+        assert_eq!(searches.recent(2), vec!["query 4", "query 3"]);
+
+        searches.record("query 5", 0);
+        assert!(searches.recent(10).is_empty());
+    }
+
+    #[test]
+    fn test_recent_searches_round_trip_through_file() {
+        let path =
+            std::env::temp_dir().join(format!("atuin-bar-searches-{}.json", std::process::id()));
+        let mut searches = RecentSearches::default();
+        searches.record("git", 10);
+        searches.record("ls", 10);
+        searches.save(&path).unwrap();
+
+        assert_eq!(RecentSearches::load(&path), searches);
+        assert_eq!(fs::read_to_string(&path).unwrap(), r#"["ls","git"]"#);
+
+        fs::write(&path, "{ not json").unwrap();
+        assert_eq!(RecentSearches::load(&path), RecentSearches::default());
+        let _ = fs::remove_file(&path);
+    }
+}
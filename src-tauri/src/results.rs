@@ -0,0 +1,808 @@
+use std::collections::{HashMap, HashSet};
+
+/// A highlighted span of a command, in character offsets (end exclusive)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct MatchRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Where an entry's match ranges came from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchSource {
+    /// Reported by atuin itself
+    Atuin,
+    /// Computed locally from the query
+    #[default]
+    Local,
+}
+
+/// How bad an exit status is, for styling in the UI
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExitSeverity {
+    Success,
+    /// Stopped by the user or a benign signal (e.g. Ctrl-C, broken pipe)
+    Warning,
+    Error,
+    /// No exit code was recorded
+    #[default]
+    Unknown,
+}
+
+/// A human-readable description of an exit code
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ExitInfo {
+    pub label: String,
+    pub severity: ExitSeverity,
+}
+
+/// Describe common shell exit codes, including `128 + n` signal exits
+pub fn describe_exit_code(code: i32) -> ExitInfo {
+    use ExitSeverity::{Error, Success, Unknown, Warning};
+
+    let (label, severity) = match code {
+        0 => ("success".to_string(), Success),
+        c if c < 0 => ("no exit code recorded".to_string(), Unknown),
+        1 => ("general error".to_string(), Error),
+        2 => ("misuse of shell builtin".to_string(), Error),
+        126 => ("not executable".to_string(), Error),
+        127 => ("command not found".to_string(), Error),
+        129 => ("hung up (SIGHUP)".to_string(), Warning),
+        130 => ("interrupted (Ctrl-C)".to_string(), Warning),
+        131 => ("quit (Ctrl-\\)".to_string(), Warning),
+        134 => ("aborted (SIGABRT)".to_string(), Error),
+        137 => ("killed (SIGKILL)".to_string(), Error),
+        139 => ("segmentation fault".to_string(), Error),
+        141 => ("broken pipe".to_string(), Warning),
+        143 => ("terminated (SIGTERM)".to_string(), Warning),
+        c => (format!("failed ({})", c), Error),
+    };
+    ExitInfo { label, severity }
+}
+
+/// A single history entry parsed from `atuin search` output
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct HistoryEntry {
+    /// The command line as recorded by atuin
+    pub command: String,
+    /// Exit code (-1 when atuin did not record one)
+    pub exit: i32,
+    /// Meaning of the exit code; only filled in for verbose results
+    #[serde(default)]
+    pub exit_info: Option<ExitInfo>,
+    /// Duration as formatted by atuin (e.g. "1s", "230ms")
+    pub duration: String,
+    /// Working directory the command ran in
+    pub directory: String,
+    /// Timestamp as formatted by atuin
+    pub time: String,
+    /// Parts of the command matching the query
+    #[serde(default)]
+    pub match_ranges: Vec<MatchRange>,
+    #[serde(default)]
+    pub match_source: MatchSource,
+    /// Recorded since the previous search
+    #[serde(default)]
+    pub is_new: bool,
+    /// A favorite command, pinned above the search results
+    #[serde(default)]
+    pub is_favorite: bool,
+    /// How long ago the command ran, e.g. "5m ago"; only filled in for
+    /// verbose results
+    #[serde(default)]
+    pub relative_time: Option<String>,
+}
+
+/// Whether structured results include the extra metadata fields for the
+/// current session (default: off)
+#[derive(Debug, Default)]
+pub struct VerboseResults(pub bool);
+
+/// Commands from the most recent search, in display order, so they can be
+/// picked by position
+#[derive(Debug, Default)]
+pub struct LastResults {
+    commands: Option<Vec<String>>,
+}
+
+impl LastResults {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remember the results of a search
+    pub fn set(&mut self, entries: &[HistoryEntry]) {
+        self.commands = Some(entries.iter().map(|e| e.command.clone()).collect());
+    }
+
+    /// The command at a 1-based position in the last search's results
+    pub fn nth_command(&self, index: usize) -> Result<&str, String> {
+        let commands = self
+            .commands
+            .as_ref()
+            .ok_or_else(|| "No search has been run yet".to_string())?;
+        index
+            .checked_sub(1)
+            .and_then(|i| commands.get(i))
+            .map(String::as_str)
+            .ok_or_else(|| {
+                format!(
+                    "Result {} is out of range: the last search returned {} results",
+                    index,
+                    commands.len()
+                )
+            })
+    }
+}
+
+/// Describe how long before `now` a timestamp was, in the largest whole unit
+pub fn format_relative_time(time: i64, now: i64) -> String {
+    let elapsed = now - time;
+    match elapsed {
+        ..=59 => "just now".to_string(),
+        60..=3599 => format!("{}m ago", elapsed / 60),
+        3600..=86_399 => format!("{}h ago", elapsed / 3600),
+        _ => format!("{}d ago", elapsed / 86_400),
+    }
+}
+
+/// Fill in (verbose) or clear the metadata fields that are only needed for
+/// the detailed view. `now` is in the same frame as `parse_timestamp`.
+pub fn apply_verbose_metadata(entry: &mut HistoryEntry, verbose: bool, now: i64) {
+    if verbose {
+        entry.exit_info = Some(describe_exit_code(entry.exit));
+        entry.relative_time =
+            parse_timestamp(&entry.time).map(|time| format_relative_time(time, now));
+    } else {
+        entry.exit_info = None;
+        entry.relative_time = None;
+    }
+}
+
+/// Field names of a serialized `HistoryEntry`, in their default order
+pub const RESULT_FIELDS: &[&str] = &[
+    "command",
+    "exit",
+    "exit_info",
+    "duration",
+    "directory",
+    "time",
+    "match_ranges",
+    "match_source",
+    "is_new",
+    "is_favorite",
+    "relative_time",
+];
+
+/// Check that every configured result field is a known `HistoryEntry` field
+pub fn validate_result_fields(fields: &[String]) -> Result<(), String> {
+    match fields.iter().find(|f| !RESULT_FIELDS.contains(&f.as_str())) {
+        Some(unknown) => Err(format!(
+            "Unknown result field '{}'; expected one of: {}",
+            unknown,
+            RESULT_FIELDS.join(", ")
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Serialize an entry as a JSON object holding only `fields`, in that order.
+/// An empty field list keeps every field.
+pub fn project_entry(entry: &HistoryEntry, fields: &[String]) -> Result<serde_json::Value, String> {
+    let value =
+        serde_json::to_value(entry).map_err(|e| format!("Failed to serialize result: {}", e))?;
+    if fields.is_empty() {
+        return Ok(value);
+    }
+
+    let serde_json::Value::Object(mut all) = value else {
+        return Err("Failed to serialize result: not an object".to_string());
+    };
+    let projected = fields
+        .iter()
+        .filter_map(|field| all.remove(field).map(|v| (field.clone(), v)))
+        .collect();
+    Ok(serde_json::Value::Object(projected))
+}
+
+/// Number of `|`-separated fields that follow the command in each line
+const TRAILING_FIELDS: usize = 4;
+
+/// Parse one line of `{command}|{exit}|{duration}|{directory}|{time}` output.
+///
+/// The command itself may contain `|`, so the line is split from the right.
+/// Returns None for lines that don't match (e.g. the first line of a
+/// multi-line command; see `atuin_records`).
+pub fn parse_atuin_line(line: &str) -> Option<HistoryEntry> {
+    let mut parts = line.rsplitn(TRAILING_FIELDS + 1, '|');
+    let time = parts.next()?;
+    let directory = parts.next()?;
+    let duration = parts.next()?;
+    let exit: i32 = parts.next()?.trim().parse().ok()?;
+    let command = parts.next()?;
+
+    Some(HistoryEntry {
+        command: command.to_string(),
+        exit,
+        exit_info: None,
+        duration: duration.to_string(),
+        directory: directory.to_string(),
+        time: time.to_string(),
+        match_ranges: Vec::new(),
+        match_source: MatchSource::Local,
+        is_new: false,
+        is_favorite: false,
+        relative_time: None,
+    })
+}
+
+/// Parse a duration as formatted by atuin (e.g. "230ms", "12s", "1m 5s",
+/// "2h 3m") into milliseconds. Returns None for unrecognized input.
+pub fn parse_duration_ms(duration: &str) -> Option<u64> {
+    let duration = duration.trim();
+    if duration.is_empty() {
+        return None;
+    }
+
+    let mut total_ns: u128 = 0;
+    for part in duration.split_whitespace() {
+        let split = part.find(|c: char| !c.is_ascii_digit() && c != '.')?;
+        let (number, unit) = part.split_at(split);
+        let value: f64 = number.parse().ok()?;
+        let ns_per_unit: f64 = match unit {
+            "ns" => 1.0,
+            "us" | "µs" => 1e3,
+            "ms" => 1e6,
+            "s" => 1e9,
+            "m" => 60e9,
+            "h" => 3600e9,
+            "d" => 86400e9,
+            _ => return None,
+        };
+        total_ns += (value * ns_per_unit) as u128;
+    }
+    u64::try_from(total_ns / 1_000_000).ok()
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Parse a `YYYY-MM-DD HH:MM:SS` timestamp as printed by atuin into seconds
+/// since the Unix epoch. The timezone is ignored, which is fine for ordering
+/// and bucketing entries from the same machine.
+pub fn parse_timestamp(time: &str) -> Option<i64> {
+    let time = time.trim();
+    let (date, clock) = time.split_once([' ', 'T'])?;
+
+    let mut date_parts = date.splitn(3, '-').map(|p| p.parse::<i64>().ok());
+    let (year, month, day) = (
+        date_parts.next()??,
+        date_parts.next()??,
+        date_parts.next()??,
+    );
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    let clock = clock.get(..8)?;
+    let mut clock_parts = clock.splitn(3, ':').map(|p| p.parse::<i64>().ok());
+    let (hour, minute, second) = (
+        clock_parts.next()??,
+        clock_parts.next()??,
+        clock_parts.next()??,
+    );
+    if hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    Some(days_from_civil(year, month, day) * 86_400 + hour * 3600 + minute * 60 + second)
+}
+
+/// Supported values for `SearchFilters::sort`; "relevance" keeps atuin's order
+pub const SORT_ORDERS: &[&str] = &["relevance", "recent", "oldest"];
+
+/// Sort `items` by when their entry ran, newest or oldest first. Entries
+/// whose time doesn't parse go last, in their original order.
+pub fn sort_by_time<T>(items: &mut [T], entry: impl Fn(&T) -> &HistoryEntry, newest_first: bool) {
+    items.sort_by_key(|item| {
+        let time = parse_timestamp(&entry(item).time);
+        (
+            time.is_none(),
+            time.map(|t| if newest_first { -t } else { t }),
+        )
+    });
+}
+
+/// Format an entry as a line of `{command}|{exit}|{duration}|{directory}|{time}`
+/// output, the inverse of `parse_atuin_line`
+pub fn format_atuin_line(entry: &HistoryEntry) -> String {
+    format!(
+        "{}|{}|{}|{}|{}",
+        entry.command, entry.exit, entry.duration, entry.directory, entry.time
+    )
+}
+
+/// Split the output of `atuin search` into records, each with its raw text.
+///
+/// A multi-line command spans several lines of output, so lines are joined
+/// until they form a complete record. Text that never completes one is
+/// dropped.
+pub fn atuin_records(output: &str) -> Vec<(&str, HistoryEntry)> {
+    let mut records = Vec::new();
+    let mut start = None;
+    let mut offset = 0;
+
+    for line in output.split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+        if start.is_none() && line.trim().is_empty() {
+            continue;
+        }
+
+        let record_start = *start.get_or_insert(line_start);
+        let text = output[record_start..offset].trim_end_matches(['\n', '\r']);
+        if let Some(entry) = parse_atuin_line(text) {
+            records.push((text, entry));
+            start = None;
+        }
+    }
+    records
+}
+
+/// Collapse records of the same command to their most recent run, keeping
+/// the survivors in their original order. With `by_directory`, runs of a
+/// command in different directories are kept apart. Records whose time
+/// can't be parsed count as older than any that can; among equal times the
+/// later record wins, as atuin lists newer entries last.
+pub fn dedup_records(
+    records: Vec<(&str, HistoryEntry)>,
+    by_directory: bool,
+) -> Vec<(&str, HistoryEntry)> {
+    let mut newest: HashMap<(&str, &str), (Option<i64>, usize)> = HashMap::new();
+    for (index, (_, entry)) in records.iter().enumerate() {
+        let directory = if by_directory {
+            entry.directory.as_str()
+        } else {
+            ""
+        };
+        let candidate = (parse_timestamp(&entry.time), index);
+        newest
+            .entry((entry.command.as_str(), directory))
+            .and_modify(|best| *best = (*best).max(candidate))
+            .or_insert(candidate);
+    }
+    let keep: HashSet<usize> = newest.into_values().map(|(_, index)| index).collect();
+
+    records
+        .into_iter()
+        .enumerate()
+        .filter(|(index, _)| keep.contains(index))
+        .map(|(_, record)| record)
+        .collect()
+}
+
+/// Parse the full output of `atuin search`, skipping text that doesn't parse
+pub fn parse_atuin_output(output: &str) -> Vec<HistoryEntry> {
+    atuin_records(output)
+        .into_iter()
+        .map(|(_, entry)| entry)
+        .collect()
+}
+
+/// Parse match positions reported by atuin, formatted as `start-end` pairs
+/// separated by commas (e.g. "0-3,8-11"). Returns None if the field is empty
+/// or malformed.
+pub fn parse_match_positions(field: &str) -> Option<Vec<MatchRange>> {
+    let field = field.trim();
+    if field.is_empty() {
+        return None;
+    }
+    field
+        .split(',')
+        .map(|pair| {
+            let (start, end) = pair.trim().split_once('-')?;
+            let start = start.parse().ok()?;
+            let end = end.parse().ok()?;
+            (start < end).then_some(MatchRange { start, end })
+        })
+        .collect()
+}
+
+/// Find the case-insensitive matches of `query` in a command, the way the
+/// search mode matches: only at the start for "prefix", the whole query
+/// anywhere for "fulltext", and each whitespace-separated term anywhere for
+/// the fuzzy modes (negated `!terms` are skipped). Overlapping ranges are
+/// merged.
+pub fn compute_match_ranges(command: &str, query: &str, search_mode: &str) -> Vec<MatchRange> {
+    let haystack: Vec<char> = command.chars().flat_map(char::to_lowercase).collect();
+    // Lowercasing can change the length of some characters; fall back to no
+    // highlighting rather than produce misaligned offsets.
+    if haystack.len() != command.chars().count() {
+        return Vec::new();
+    }
+
+    let terms: Vec<&str> = match search_mode {
+        "prefix" | "fulltext" => vec![query],
+        _ => query
+            .split_whitespace()
+            .filter(|term| !term.starts_with('!'))
+            .map(|term| term.trim_start_matches(['^', '\'']).trim_end_matches('$'))
+            .collect(),
+    };
+
+    let mut ranges = Vec::new();
+    for term in terms {
+        let needle: Vec<char> = term.chars().flat_map(char::to_lowercase).collect();
+        if needle.is_empty() || needle.len() > haystack.len() {
+            continue;
+        }
+        let last_start = if search_mode == "prefix" {
+            0
+        } else {
+            haystack.len() - needle.len()
+        };
+        for start in 0..=last_start {
+            if haystack[start..start + needle.len()] == needle[..] {
+                ranges.push(MatchRange {
+                    start,
+                    end: start + needle.len(),
+                });
+            }
+        }
+    }
+
+    ranges.sort_by_key(|range| range.start);
+    let mut merged: Vec<MatchRange> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+/// Set an entry's match ranges, preferring positions reported by atuin and
+/// falling back to computing them from `query` searched with `search_mode`
+pub fn apply_match_ranges(
+    entry: &mut HistoryEntry,
+    query: &str,
+    search_mode: &str,
+    atuin_positions: Option<&str>,
+) {
+    match atuin_positions.and_then(parse_match_positions) {
+        Some(ranges) => {
+            entry.match_ranges = ranges;
+            entry.match_source = MatchSource::Atuin;
+        }
+        None => {
+            entry.match_ranges = compute_match_ranges(&entry.command, query, search_mode);
+            entry.match_source = MatchSource::Local;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn test_project_entry_keeps_only_configured_fields_in_order() {
+        let entry = parse_atuin_line("git status|0|12ms|/repo|2024-01-01 10:00:00").unwrap();
+        let value = project_entry(&entry, &fields(&["directory", "command"])).unwrap();
+
+        let object = value.as_object().unwrap();
+        let keys: Vec<_> = object.keys().map(String::as_str).collect();
+        assert_eq!(keys, vec!["directory", "command"]);
+        assert_eq!(object["command"], "git status");
+        assert_eq!(object["directory"], "/repo");
+    }
+
+    #[test]
+    fn test_project_entry_without_fields_keeps_everything() {
+        let entry = parse_atuin_line("ls|0|1ms|/tmp|2024-01-01 10:00:00").unwrap();
+        let value = project_entry(&entry, &[]).unwrap();
+        assert_eq!(value.as_object().unwrap().len(), RESULT_FIELDS.len());
+    }
+
+    #[test]
+    fn test_validate_result_fields_rejects_unknown_names() {
+        assert!(validate_result_fields(&fields(&["command", "exit", "is_new"])).is_ok());
+        let error = validate_result_fields(&fields(&["command", "hostname"])).unwrap_err();
+        assert!(error.contains("hostname"));
+    }
+
+    #[test]
+    fn test_parse_atuin_line() {
+        let result = parse_atuin_line("git status|0|12ms|/home/user/repo|2024-01-01 10:00:00")
+            .expect("line should parse");
+        assert_eq!(result.command, "git status");
+        assert_eq!(result.exit, 0);
+        assert_eq!(result.duration, "12ms");
+        assert_eq!(result.directory, "/home/user/repo");
+        assert_eq!(result.time, "2024-01-01 10:00:00");
+    }
+
+    #[test]
+    fn test_parse_atuin_line_command_with_pipes() {
+        let result = parse_atuin_line("cat log | grep err|1|3ms|/tmp|2024-01-01 10:00:00")
+            .expect("line should parse");
+        assert_eq!(result.command, "cat log | grep err");
+        assert_eq!(result.exit, 1);
+    }
+
+    #[test]
+    fn test_apply_match_ranges_prefers_atuin_positions() {
+        let mut entry = parse_atuin_line("git push origin|0|1s|/tmp|2024-01-01 10:00:00").unwrap();
+        apply_match_ranges(&mut entry, "push", "fuzzy", Some("0-3"));
+
+        assert_eq!(entry.match_source, MatchSource::Atuin);
+        assert_eq!(entry.match_ranges, vec![MatchRange { start: 0, end: 3 }]);
+    }
+
+    #[test]
+    fn test_apply_match_ranges_falls_back_to_local() {
+        let mut entry = parse_atuin_line("git push origin|0|1s|/tmp|2024-01-01 10:00:00").unwrap();
+        apply_match_ranges(&mut entry, "PUSH", "fuzzy", None);
+
+        assert_eq!(entry.match_source, MatchSource::Local);
+        assert_eq!(entry.match_ranges, vec![MatchRange { start: 4, end: 8 }]);
+
+        // Malformed passthrough data is ignored too
+        apply_match_ranges(&mut entry, "git origin", "fuzzy", Some("garbage"));
+        assert_eq!(entry.match_source, MatchSource::Local);
+        assert_eq!(
+            entry.match_ranges,
+            vec![
+                MatchRange { start: 0, end: 3 },
+                MatchRange { start: 9, end: 15 }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compute_match_ranges_follows_search_mode() {
+        let command = "git push origin git";
+        assert_eq!(
+            compute_match_ranges(command, "git", "prefix"),
+            vec![MatchRange { start: 0, end: 3 }]
+        );
+        assert!(compute_match_ranges(command, "push", "prefix").is_empty());
+        assert!(compute_match_ranges(command, "origin push", "fulltext").is_empty());
+        assert_eq!(
+            compute_match_ranges(command, "push origin", "fulltext"),
+            vec![MatchRange { start: 4, end: 15 }]
+        );
+        assert_eq!(
+            compute_match_ranges(command, "^git !push origin$", "fuzzy"),
+            vec![
+                MatchRange { start: 0, end: 3 },
+                MatchRange { start: 9, end: 15 },
+                MatchRange { start: 16, end: 19 }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compute_match_ranges_merges_overlaps() {
+        let ranges = compute_match_ranges("cargo", "car arg", "fuzzy");
+        assert_eq!(ranges, vec![MatchRange { start: 0, end: 4 }]);
+    }
+
+    #[test]
+    fn test_describe_well_known_exit_codes() {
+        let cases = [
+            (0, "success", ExitSeverity::Success),
+            (1, "general error", ExitSeverity::Error),
+            (126, "not executable", ExitSeverity::Error),
+            (127, "command not found", ExitSeverity::Error),
+            (130, "interrupted (Ctrl-C)", ExitSeverity::Warning),
+            (137, "killed (SIGKILL)", ExitSeverity::Error),
+            (-1, "no exit code recorded", ExitSeverity::Unknown),
+        ];
+        for (code, label, severity) in cases {
+            let info = describe_exit_code(code);
+            assert_eq!(info.label, label, "label for {}", code);
+            assert_eq!(info.severity, severity, "severity for {}", code);
+        }
+    }
+
+    #[test]
+    fn test_describe_unknown_exit_code() {
+        let info = describe_exit_code(42);
+        assert_eq!(info.label, "failed (42)");
+        assert_eq!(info.severity, ExitSeverity::Error);
+    }
+
+    #[test]
+    fn test_verbose_metadata_populates_extra_fields() {
+        let mut entry = parse_atuin_line("nope|127|1ms|/tmp|2024-01-01 10:00:00").unwrap();
+        assert_eq!(entry.exit_info, None);
+        assert_eq!(entry.relative_time, None);
+
+        let now = parse_timestamp("2024-01-01 12:30:00").unwrap();
+        apply_verbose_metadata(&mut entry, true, now);
+        assert_eq!(entry.exit_info.as_ref().unwrap().label, "command not found");
+        assert_eq!(entry.relative_time.as_deref(), Some("2h ago"));
+
+        apply_verbose_metadata(&mut entry, false, now);
+        assert_eq!(entry.exit_info, None);
+        assert_eq!(entry.relative_time, None);
+    }
+
+    #[test]
+    fn test_last_results_nth_command() {
+        let entries = parse_atuin_output(
+            "git status|0|1ms|/repo|2024-01-01 10:00:00\n\
+             cargo test|0|1s|/repo|2024-01-01 10:01:00\n",
+        );
+        let mut last = LastResults::new();
+        last.set(&entries);
+
+        assert_eq!(last.nth_command(1), Ok("git status"));
+        assert_eq!(last.nth_command(2), Ok("cargo test"));
+    }
+
+    #[test]
+    fn test_last_results_out_of_range() {
+        let mut last = LastResults::new();
+        last.set(&parse_atuin_output("ls|0|1ms|/tmp|2024-01-01 10:00:00\n"));
+
+        assert!(last.nth_command(2).unwrap_err().contains("out of range"));
+        assert!(last.nth_command(0).unwrap_err().contains("out of range"));
+    }
+
+    #[test]
+    fn test_last_results_without_prior_search() {
+        let last = LastResults::new();
+        assert_eq!(
+            last.nth_command(1),
+            Err("No search has been run yet".to_string())
+        );
+    }
+
+    #[test]
+    fn test_format_relative_time() {
+        assert_eq!(format_relative_time(1_000, 1_030), "just now");
+        assert_eq!(format_relative_time(1_000, 1_000 + 5 * 60), "5m ago");
+        assert_eq!(format_relative_time(1_000, 1_000 + 3 * 86_400), "3d ago");
+        // Timestamps slightly in the future (clock skew) aren't negative
+        assert_eq!(format_relative_time(1_000, 900), "just now");
+    }
+
+    #[test]
+    fn test_parse_duration_ms() {
+        assert_eq!(parse_duration_ms("230ms"), Some(230));
+        assert_eq!(parse_duration_ms("12s"), Some(12_000));
+        assert_eq!(parse_duration_ms("1m 5s"), Some(65_000));
+        assert_eq!(parse_duration_ms("2h 3m"), Some(7_380_000));
+        assert_eq!(parse_duration_ms("1.5s"), Some(1_500));
+        assert_eq!(parse_duration_ms("850µs"), Some(0));
+        assert_eq!(parse_duration_ms(""), None);
+        assert_eq!(parse_duration_ms("fast"), None);
+        assert_eq!(parse_duration_ms("12 parsecs"), None);
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(parse_timestamp("1970-01-01 00:00:00"), Some(0));
+        assert_eq!(parse_timestamp("2024-01-01 10:00:00"), Some(1_704_103_200));
+        assert_eq!(parse_timestamp("2024-02-29T23:59:59"), Some(1_709_251_199));
+        assert_eq!(parse_timestamp("yesterday"), None);
+        assert_eq!(parse_timestamp("2024-13-01 00:00:00"), None);
+    }
+
+    #[test]
+    fn test_parse_atuin_output_joins_multi_line_commands() {
+        let output = "echo one \\\ntwo|0|1ms|/tmp|2024-01-01 10:00:00\n\nls|0|1ms|/tmp|2024-01-01 10:00:01\n";
+        let results = parse_atuin_output(output);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].command, "echo one \\\ntwo");
+        assert_eq!(results[1].command, "ls");
+    }
+
+    #[test]
+    fn test_parse_atuin_output_drops_incomplete_trailing_text() {
+        let output = "ls|0|1ms|/tmp|2024-01-01 10:00:00\nnot a record\n";
+        let results = parse_atuin_output(output);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].command, "ls");
+    }
+
+    fn timed_entries() -> Vec<HistoryEntry> {
+        [
+            "b|0|1ms|/|2024-01-02 00:00:00",
+            "broken|0|1ms|/|not a time",
+            "a|0|1ms|/|2024-01-01 00:00:00",
+            "c|0|1ms|/|2024-01-03T00:00:00",
+            "unknown|0|1ms|/|",
+        ]
+        .iter()
+        .map(|line| parse_atuin_line(line).unwrap())
+        .collect()
+    }
+
+    fn commands(entries: &[HistoryEntry]) -> Vec<&str> {
+        entries.iter().map(|e| e.command.as_str()).collect()
+    }
+
+    #[test]
+    fn test_sort_by_time_newest_first() {
+        let mut entries = timed_entries();
+        sort_by_time(&mut entries, |e| e, true);
+        assert_eq!(commands(&entries), vec!["c", "b", "a", "broken", "unknown"]);
+    }
+
+    #[test]
+    fn test_sort_by_time_oldest_first() {
+        let mut entries = timed_entries();
+        sort_by_time(&mut entries, |e| e, false);
+        assert_eq!(commands(&entries), vec!["a", "b", "c", "broken", "unknown"]);
+    }
+
+    #[test]
+    fn test_dedup_records_keeps_most_recent_run() {
+        let output = "git status|0|5ms|/a|2024-01-01 10:00:00\n\
+                      ls|0|1ms|/a|2024-01-01 10:01:00\n\
+                      git status|0|5ms|/b|2024-01-01 10:02:00\n\
+                      git status|1|5ms|/a|2024-01-01 10:03:00\n";
+        let deduped = dedup_records(atuin_records(output), false);
+        let commands: Vec<_> = deduped.iter().map(|(_, e)| e.command.as_str()).collect();
+
+        assert_eq!(commands, vec!["ls", "git status"]);
+        assert_eq!(deduped[1].1.time, "2024-01-01 10:03:00");
+        assert_eq!(deduped[1].0, "git status|1|5ms|/a|2024-01-01 10:03:00");
+    }
+
+    #[test]
+    fn test_dedup_records_by_directory_keeps_directories_apart() {
+        let output = "make|0|5ms|/a|2024-01-01 10:00:00\n\
+                      make|0|5ms|/b|2024-01-01 10:01:00\n\
+                      make|0|5ms|/a|2024-01-01 10:02:00\n";
+        let deduped = dedup_records(atuin_records(output), true);
+
+        let places: Vec<_> = deduped
+            .iter()
+            .map(|(_, e)| (e.directory.as_str(), e.time.as_str()))
+            .collect();
+        assert_eq!(
+            places,
+            vec![("/b", "2024-01-01 10:01:00"), ("/a", "2024-01-01 10:02:00")]
+        );
+    }
+
+    #[test]
+    fn test_dedup_records_prefers_parsed_times() {
+        // Out of order output: the newest run is listed first
+        let output = "ls|0|1ms|/a|2024-01-02 10:00:00\n\
+                      ls|0|1ms|/a|2024-01-01 10:00:00\n\
+                      ls|0|1ms|/a|not a time\n";
+        let deduped = dedup_records(atuin_records(output), false);
+        assert_eq!(deduped.len(), 1);
+        assert_eq!(deduped[0].1.time, "2024-01-02 10:00:00");
+    }
+
+    #[test]
+    fn test_format_atuin_line_round_trips_pipes_and_newlines() {
+        for command in ["grep foo | wc -l", "for f in *; do\n  echo \"$f|x\"\ndone"] {
+            let entry =
+                parse_atuin_line(&format!("{}|1|5ms|/tmp|2024-01-01 10:00:00", command)).unwrap();
+            let output = format!("{}\n", format_atuin_line(&entry));
+            assert_eq!(parse_atuin_output(&output), vec![entry.clone()]);
+            assert_eq!(entry.command, command);
+        }
+    }
+}
@@ -0,0 +1,270 @@
+use std::env;
+use std::io;
+use std::process::Command;
+use std::time::Duration;
+
+use crate::atuin::output_with_timeout;
+
+/// Result of running one command
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct CommandOutput {
+    pub command: String,
+    /// Exit code, or -1 if the process was killed by a signal
+    pub exit_code: i32,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// Runs shell command lines
+pub trait CommandExecutor {
+    fn execute(&mut self, command: &str, directory: Option<&str>) -> Result<CommandOutput, String>;
+}
+
+/// How long a command may run before it's killed, so an interactive or
+/// never-ending one (`vim`, `ssh`, `sleep 100`) can't tie up the app
+pub const COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Executes commands with the user's `$SHELL -c` (or `/bin/sh -c`), with no
+/// input, killing them after `timeout`
+pub struct ShellExecutor {
+    pub timeout: Duration,
+}
+
+impl Default for ShellExecutor {
+    fn default() -> Self {
+        ShellExecutor {
+            timeout: COMMAND_TIMEOUT,
+        }
+    }
+}
+
+impl CommandExecutor for ShellExecutor {
+    fn execute(&mut self, command: &str, directory: Option<&str>) -> Result<CommandOutput, String> {
+        let shell = env::var("SHELL")
+            .ok()
+            .filter(|s| !s.is_empty())
+            .unwrap_or_else(|| "/bin/sh".to_string());
+
+        let mut cmd = Command::new(&shell);
+        cmd.arg("-c").arg(command);
+        if let Some(dir) = directory.filter(|d| !d.is_empty()) {
+            cmd.current_dir(dir);
+        }
+
+        let output = output_with_timeout(&mut cmd, Some(self.timeout), None).map_err(|e| {
+            if e.kind() == io::ErrorKind::TimedOut {
+                format!(
+                    "'{}' was stopped after running for {}s",
+                    command,
+                    self.timeout.as_secs_f32()
+                )
+            } else {
+                format!("Failed to run '{}' with {}: {}", command, shell, e)
+            }
+        })?;
+        Ok(CommandOutput {
+            command: command.to_string(),
+            exit_code: output.status.code().unwrap_or(-1),
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        })
+    }
+}
+
+/// Patterns that mark a command as destructive enough to need confirmation
+const DANGEROUS_PATTERNS: &[&str] = &[
+    "rm -rf",
+    "rm -fr",
+    "rm -r",
+    "sudo ",
+    "mkfs",
+    "dd if=",
+    "shutdown",
+    "reboot",
+    "> /dev/sd",
+    "chmod -r 777",
+    ":(){",
+    "git push --force",
+    "git push -f",
+    "git reset --hard",
+    "drop database",
+    "drop table",
+];
+
+/// Whether a command looks destructive (deletes data, needs root, etc.)
+pub fn is_dangerous(command: &str) -> bool {
+    let normalized = command
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+    normalized.starts_with("sudo") || DANGEROUS_PATTERNS.iter().any(|p| normalized.contains(p))
+}
+
+/// Check the run policy before executing anything: running must be enabled,
+/// and dangerous commands need explicit confirmation when required
+pub fn check_run_policy(
+    commands: &[String],
+    allow_run: bool,
+    confirm_dangerous: bool,
+    confirmed: bool,
+) -> Result<(), String> {
+    if !allow_run {
+        return Err(
+            "Running commands is disabled: set allow_run = true in config.toml".to_string(),
+        );
+    }
+    if confirm_dangerous && !confirmed {
+        if let Some(command) = commands.iter().find(|c| is_dangerous(c)) {
+            return Err(format!(
+                "Confirmation required to run dangerous command: {}",
+                command
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Run commands in order, returning the output of each one that ran.
+/// With `stop_on_error`, execution halts after the first nonzero exit.
+pub fn run_queue(
+    executor: &mut impl CommandExecutor,
+    commands: &[String],
+    directory: Option<&str>,
+    stop_on_error: bool,
+) -> Result<Vec<CommandOutput>, String> {
+    let mut outputs = Vec::with_capacity(commands.len());
+    for command in commands {
+        let output = executor.execute(command, directory)?;
+        let failed = output.exit_code != 0;
+        outputs.push(output);
+        if failed && stop_on_error {
+            break;
+        }
+    }
+    Ok(outputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Records executed commands; commands containing "fail" exit with 1
+    #[derive(Default)]
+    struct StubExecutor {
+        executed: Vec<(String, Option<String>)>,
+    }
+
+    impl CommandExecutor for StubExecutor {
+        fn execute(
+            &mut self,
+            command: &str,
+            directory: Option<&str>,
+        ) -> Result<CommandOutput, String> {
+            self.executed
+                .push((command.to_string(), directory.map(str::to_string)));
+            Ok(CommandOutput {
+                command: command.to_string(),
+                exit_code: if command.contains("fail") { 1 } else { 0 },
+                stdout: format!("ran {}", command),
+                stderr: String::new(),
+            })
+        }
+    }
+
+    fn commands(list: &[&str]) -> Vec<String> {
+        list.iter().map(|c| c.to_string()).collect()
+    }
+
+    #[test]
+    fn test_run_queue_runs_all_commands() {
+        let mut executor = StubExecutor::default();
+        let queue = commands(&["cargo build", "cargo fail", "cargo test"]);
+
+        let outputs = run_queue(&mut executor, &queue, Some("/repo"), false).unwrap();
+
+        assert_eq!(outputs.len(), 3);
+        assert_eq!(outputs[1].exit_code, 1);
+        assert_eq!(outputs[2].stdout, "ran cargo test");
+        assert!(executor
+            .executed
+            .iter()
+            .all(|(_, dir)| dir.as_deref() == Some("/repo")));
+    }
+
+    #[test]
+    fn test_run_queue_stops_on_error() {
+        let mut executor = StubExecutor::default();
+        let queue = commands(&["cargo build", "cargo fail", "cargo test"]);
+
+        let outputs = run_queue(&mut executor, &queue, None, true).unwrap();
+
+        assert_eq!(outputs.len(), 2);
+        assert_eq!(outputs[1].command, "cargo fail");
+        assert_eq!(executor.executed.len(), 2);
+    }
+
+    #[test]
+    fn test_shell_executor_captures_output() {
+        let output = ShellExecutor::default()
+            .execute("echo hello; echo oops >&2", None)
+            .unwrap();
+
+        assert_eq!(output.command, "echo hello; echo oops >&2");
+        assert_eq!(output.exit_code, 0);
+        assert_eq!(output.stdout, "hello\n");
+        assert_eq!(output.stderr, "oops\n");
+    }
+
+    #[test]
+    fn test_shell_executor_reports_failing_exit_code() {
+        let output = ShellExecutor::default()
+            .execute("echo failing >&2; exit 3", Some("/"))
+            .unwrap();
+
+        assert_eq!(output.exit_code, 3);
+        assert_eq!(output.stdout, "");
+        assert_eq!(output.stderr, "failing\n");
+    }
+
+    #[test]
+    fn test_shell_executor_kills_long_running_commands() {
+        let mut executor = ShellExecutor {
+            timeout: Duration::from_millis(100),
+        };
+        let started = std::time::Instant::now();
+        let err = executor.execute("sleep 10", None).unwrap_err();
+
+        assert!(err.contains("was stopped"), "got: {}", err);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_shell_executor_gives_no_input() {
+        let output = ShellExecutor::default()
+            .execute("read line; echo \"read: $line\"", None)
+            .unwrap();
+        assert_eq!(output.stdout, "read: \n");
+    }
+
+    #[test]
+    fn test_is_dangerous() {
+        assert!(is_dangerous("rm -rf /tmp/build"));
+        assert!(is_dangerous("sudo apt upgrade"));
+        assert!(is_dangerous("git  push   --force origin main"));
+        assert!(!is_dangerous("ls -la"));
+        assert!(!is_dangerous("git push origin main"));
+    }
+
+    #[test]
+    fn test_check_run_policy() {
+        let safe = commands(&["ls", "pwd"]);
+        let risky = commands(&["ls", "rm -rf build"]);
+
+        assert!(check_run_policy(&safe, false, true, false).is_err());
+        assert!(check_run_policy(&safe, true, true, false).is_ok());
+        assert!(check_run_policy(&risky, true, true, false).is_err());
+        assert!(check_run_policy(&risky, true, true, true).is_ok());
+        assert!(check_run_policy(&risky, true, false, false).is_ok());
+    }
+}
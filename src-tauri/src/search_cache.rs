@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::{SearchError, SearchFilters, SearchOutput};
+
+/// Upper bound on cached queries before expired entries are pruned
+const MAX_CACHE_ENTRIES: usize = 128;
+
+/// Cache of raw `atuin search` output keyed by query and filters.
+///
+/// Entries remember when they were inserted so they can expire after the
+/// configured TTL as new history is recorded.
+#[derive(Debug, Default)]
+pub struct SearchCache {
+    entries: HashMap<(String, SearchFilters), (Instant, SearchOutput)>,
+}
+
+impl SearchCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the cached output for a search if it is younger than `ttl`.
+    /// A zero TTL never hits.
+    pub fn get(
+        &self,
+        query: &str,
+        filters: &SearchFilters,
+        ttl: Duration,
+        now: Instant,
+    ) -> Option<&SearchOutput> {
+        if ttl.is_zero() {
+            return None;
+        }
+        let (inserted_at, output) = self.entries.get(&(query.to_string(), filters.clone()))?;
+        if now.saturating_duration_since(*inserted_at) < ttl {
+            Some(output)
+        } else {
+            None
+        }
+    }
+
+    /// Store the output for a search, pruning expired entries when full.
+    /// Nothing is stored when the TTL is zero.
+    pub fn insert(
+        &mut self,
+        query: &str,
+        filters: &SearchFilters,
+        output: SearchOutput,
+        ttl: Duration,
+        now: Instant,
+    ) {
+        if ttl.is_zero() {
+            return;
+        }
+        if self.entries.len() >= MAX_CACHE_ENTRIES {
+            self.entries
+                .retain(|_, (inserted_at, _)| now.saturating_duration_since(*inserted_at) < ttl);
+            if self.entries.len() >= MAX_CACHE_ENTRIES {
+                self.entries.clear();
+            }
+        }
+        self.entries
+            .insert((query.to_string(), filters.clone()), (now, output));
+    }
+
+    /// Return the cached output for a search, or run `fetch` and cache its
+    /// result on a miss. Errors are never cached.
+    pub fn get_or_fetch<F>(
+        &mut self,
+        query: &str,
+        filters: &SearchFilters,
+        ttl: Duration,
+        now: Instant,
+        fetch: F,
+    ) -> Result<SearchOutput, SearchError>
+    where
+        F: FnOnce() -> Result<SearchOutput, SearchError>,
+    {
+        if let Some(output) = self.get(query, filters, ttl, now) {
+            return Ok(output.clone());
+        }
+        let output = fetch()?;
+        self.insert(query, filters, output.clone(), ttl, now);
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TTL: Duration = Duration::from_millis(1000);
+
+    fn found(output: &str) -> SearchOutput {
+        SearchOutput {
+            output: output.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_fresh_entry_is_a_hit() {
+        let mut cache = SearchCache::new();
+        let filters = SearchFilters::default();
+        let start = Instant::now();
+
+        let first = cache.get_or_fetch("git", &filters, TTL, start, || Ok(found("first")));
+        assert_eq!(first.unwrap(), found("first"));
+
+        let second = cache.get_or_fetch(
+            "git",
+            &filters,
+            TTL,
+            start + Duration::from_millis(500),
+            || panic!("fresh entry should not be refetched"),
+        );
+        assert_eq!(second.unwrap(), found("first"));
+    }
+
+    #[test]
+    fn test_expired_entry_is_refreshed() {
+        let mut cache = SearchCache::new();
+        let filters = SearchFilters::default();
+        let start = Instant::now();
+
+        cache
+            .get_or_fetch("git", &filters, TTL, start, || Ok(found("stale")))
+            .unwrap();
+
+        let later = start + TTL;
+        let refreshed = cache.get_or_fetch("git", &filters, TTL, later, || Ok(found("fresh")));
+        assert_eq!(refreshed.unwrap(), found("fresh"));
+        assert_eq!(
+            cache.get("git", &filters, TTL, later),
+            Some(&found("fresh"))
+        );
+    }
+
+    #[test]
+    fn test_zero_ttl_bypasses_cache() {
+        let mut cache = SearchCache::new();
+        let filters = SearchFilters::default();
+        let now = Instant::now();
+
+        cache
+            .get_or_fetch("git", &filters, Duration::ZERO, now, || Ok(found("first")))
+            .unwrap();
+        let second =
+            cache.get_or_fetch("git", &filters, Duration::ZERO, now, || Ok(found("second")));
+        assert_eq!(second.unwrap(), found("second"));
+        assert!(cache.entries.is_empty());
+    }
+
+    #[test]
+    fn test_entries_are_keyed_by_filters() {
+        let mut cache = SearchCache::new();
+        let now = Instant::now();
+        let failures = SearchFilters {
+            exit_filter: Some("failure".to_string()),
+            ..Default::default()
+        };
+
+        cache.insert("git", &SearchFilters::default(), found("all"), TTL, now);
+        assert_eq!(cache.get("git", &failures, TTL, now), None);
+
+        // Every field counts, down to the percentile and sort order
+        let slowest = SearchFilters {
+            duration_percentile: Some(95.0),
+            sort: Some("recent".to_string()),
+            ..Default::default()
+        };
+        cache.insert("git", &slowest, found("slowest"), TTL, now);
+        assert_eq!(
+            cache.get("git", &slowest.clone(), TTL, now),
+            Some(&found("slowest"))
+        );
+        let oldest = SearchFilters {
+            sort: Some("oldest".to_string()),
+            ..slowest.clone()
+        };
+        assert_eq!(cache.get("git", &oldest, TTL, now), None);
+        let slower = SearchFilters {
+            duration_percentile: Some(90.0),
+            ..slowest
+        };
+        assert_eq!(cache.get("git", &slower, TTL, now), None);
+        let unparsed = SearchFilters {
+            duration_percentile: Some(f32::NAN),
+            ..Default::default()
+        };
+        assert_ne!(unparsed, SearchFilters::default());
+        assert_eq!(cache.get("git", &unparsed, TTL, now), None);
+    }
+}
@@ -0,0 +1,83 @@
+use crate::SearchError;
+
+/// Orders overlapping searches, so a slow search for "g" can't replace the
+/// results of a later search for "git".
+///
+/// Each search is registered with an increasing id; once a newer one has
+/// started, the older one's results are dropped.
+#[derive(Debug, Default)]
+pub struct SearchSequence {
+    latest: u64,
+}
+
+impl SearchSequence {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a search under the id the frontend gave it, or the next id
+    /// when it gave none, and return that id
+    pub fn start(&mut self, id: Option<u64>) -> u64 {
+        let id = id.unwrap_or(self.latest + 1);
+        self.latest = self.latest.max(id);
+        id
+    }
+
+    /// Number searches afresh, for a reloaded frontend whose ids start over
+    pub fn reset(&mut self) {
+        self.latest = 0;
+    }
+
+    /// Fail with `SearchError::Superseded` if a newer search has started
+    /// since search `id`
+    pub fn ensure_current(&self, id: u64) -> Result<(), SearchError> {
+        if id < self.latest {
+            Err(SearchError::Superseded { request_id: id })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_older_search_is_superseded() {
+        let mut sequence = SearchSequence::new();
+        let first = sequence.start(None);
+        let second = sequence.start(None);
+        assert!(second > first);
+
+        assert_eq!(
+            sequence.ensure_current(first),
+            Err(SearchError::Superseded { request_id: first })
+        );
+        assert_eq!(sequence.ensure_current(second), Ok(()));
+    }
+
+    #[test]
+    fn test_frontend_ids_arriving_out_of_order() {
+        let mut sequence = SearchSequence::new();
+        assert_eq!(sequence.start(Some(7)), 7);
+        // A request sent earlier but received later doesn't move the
+        // sequence back
+        assert_eq!(sequence.start(Some(5)), 5);
+
+        assert!(sequence.ensure_current(5).is_err());
+        assert!(sequence.ensure_current(7).is_ok());
+        assert_eq!(sequence.start(None), 8);
+        assert!(sequence.ensure_current(7).is_err());
+    }
+
+    #[test]
+    fn test_reset_accepts_ids_from_a_reloaded_frontend() {
+        let mut sequence = SearchSequence::new();
+        sequence.start(Some(40));
+
+        sequence.reset();
+        assert_eq!(sequence.start(Some(1)), 1);
+        assert!(sequence.ensure_current(1).is_ok());
+    }
+}
@@ -0,0 +1,108 @@
+use crate::Config;
+
+/// A named window size
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SizePreset {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Presets available without any configuration
+pub const BUILTIN_SIZE_PRESETS: &[(&str, SizePreset)] = &[
+    (
+        "compact",
+        SizePreset {
+            width: 500,
+            height: 300,
+        },
+    ),
+    (
+        "comfortable",
+        SizePreset {
+            width: 700,
+            height: 500,
+        },
+    ),
+    (
+        "wide",
+        SizePreset {
+            width: 1000,
+            height: 600,
+        },
+    ),
+];
+
+/// Look up a preset by name. Presets from the config take precedence over
+/// built-in presets with the same name.
+pub fn find_size_preset(config: &Config, name: &str) -> Option<SizePreset> {
+    config.size_presets.get(name).copied().or_else(|| {
+        BUILTIN_SIZE_PRESETS
+            .iter()
+            .find(|(builtin, _)| *builtin == name)
+            .map(|(_, preset)| *preset)
+    })
+}
+
+/// Make a preset the active one: record its name and adopt its size.
+/// Unknown names are rejected and leave the config untouched.
+pub fn select_size_preset(config: &mut Config, name: &str) -> Result<SizePreset, String> {
+    let preset =
+        find_size_preset(config, name).ok_or_else(|| format!("Unknown size preset '{}'", name))?;
+    config.size_preset = name.to_string();
+    config.window_width = preset.width;
+    config.window_height = preset.height;
+    Ok(preset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_builtin_preset() {
+        let mut config = Config::default();
+        let preset = select_size_preset(&mut config, "wide").expect("built-in preset");
+
+        assert_eq!(
+            preset,
+            SizePreset {
+                width: 1000,
+                height: 600
+            }
+        );
+        assert_eq!(config.size_preset, "wide");
+        assert_eq!(config.window_width, 1000);
+        assert_eq!(config.window_height, 600);
+    }
+
+    #[test]
+    fn test_custom_preset_overrides_builtin() {
+        let mut config = Config::default();
+        config.size_presets.insert(
+            "compact".to_string(),
+            SizePreset {
+                width: 450,
+                height: 250,
+            },
+        );
+
+        let preset = select_size_preset(&mut config, "compact").unwrap();
+        assert_eq!(
+            preset,
+            SizePreset {
+                width: 450,
+                height: 250
+            }
+        );
+    }
+
+    #[test]
+    fn test_unknown_preset_is_rejected() {
+        let mut config = Config::default();
+        let err = select_size_preset(&mut config, "huge").unwrap_err();
+
+        assert!(err.contains("Unknown size preset"), "got: {}", err);
+        assert_eq!(config.size_preset, "");
+        assert_eq!(config.window_width, 700);
+    }
+}
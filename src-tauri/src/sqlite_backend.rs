@@ -0,0 +1,709 @@
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rusqlite::types::Value;
+use rusqlite::{params_from_iter, Connection, OpenFlags};
+
+use crate::atuin::{HistoryBackend, SearchError};
+use crate::results::parse_timestamp;
+
+/// Environment variable atuin reads the history database path from
+pub const DB_PATH_VAR: &str = "ATUIN_DB_PATH";
+
+/// Placeholders the database can fill in for `--format`
+const FORMAT_FIELDS: &[&str] = &[
+    "command",
+    "exit",
+    "duration",
+    "directory",
+    "time",
+    "host",
+    "user",
+    "session",
+];
+
+/// Path of atuin's history database: `ATUIN_DB_PATH` from `atuin_env` or
+/// the environment, else atuin's default of `$XDG_DATA_HOME/atuin` or
+/// `~/.local/share/atuin` (on every platform)
+pub fn history_db_path(atuin_env: &BTreeMap<String, String>) -> Option<PathBuf> {
+    let configured = atuin_env
+        .get(DB_PATH_VAR)
+        .cloned()
+        .or_else(|| env::var(DB_PATH_VAR).ok())
+        .filter(|path| !path.is_empty());
+    if let Some(path) = configured {
+        return Some(PathBuf::from(path));
+    }
+
+    let data_dir = env::var("XDG_DATA_HOME")
+        .ok()
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".local").join("share")))?;
+    Some(data_dir.join("atuin").join("history.db"))
+}
+
+/// Open atuin's history database read-only, checking it has the columns
+/// searches need
+pub fn open_history_db(path: &Path) -> Result<Connection, String> {
+    let conn = Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    conn.prepare(
+        "SELECT timestamp, duration, exit, command, cwd, session, hostname, deleted_at
+         FROM history LIMIT 0",
+    )
+    .map_err(|e| format!("{} is not an atuin history database: {}", path.display(), e))?;
+    Ok(conn)
+}
+
+/// A `--after` or `--before` value
+#[derive(Debug, PartialEq)]
+enum TimeBound {
+    /// Seconds since the Unix epoch
+    Epoch(i64),
+    /// A local `YYYY-MM-DD HH:MM:SS` time, converted by SQLite
+    Local(String),
+}
+
+/// Parse the forms of `--after`/`--before` used by the bar: "N units ago"
+/// and dates with an optional time. Anything else is left to atuin.
+fn parse_time_bound(value: &str, now: i64) -> Option<TimeBound> {
+    let value = value.trim();
+    if let Some(ago) = value.strip_suffix(" ago") {
+        let (amount, unit) = ago.trim().split_once(' ')?;
+        let amount: i64 = amount.parse().ok()?;
+        let seconds = match unit.trim().trim_end_matches('s') {
+            "second" => 1,
+            "minute" => 60,
+            "hour" => 3_600,
+            "day" => 86_400,
+            "week" => 604_800,
+            "month" => 30 * 86_400,
+            "year" => 365 * 86_400,
+            _ => return None,
+        };
+        return Some(TimeBound::Epoch(now - amount * seconds));
+    }
+
+    let local = if value.len() == "YYYY-MM-DD".len() {
+        format!("{} 00:00:00", value)
+    } else {
+        value.to_string()
+    };
+    parse_timestamp(&local)?;
+    Some(TimeBound::Local(local))
+}
+
+/// An `atuin search` invocation the database can answer
+#[derive(Debug, Default)]
+struct Search {
+    mode: String,
+    limit: Option<u32>,
+    offset: u32,
+    format: String,
+    cwd: Option<String>,
+    exit: Option<i64>,
+    exclude_exit: Option<i64>,
+    after: Option<TimeBound>,
+    before: Option<TimeBound>,
+    query: String,
+}
+
+/// Whether every `{placeholder}` in `format` is one of `FORMAT_FIELDS`
+fn is_supported_format(format: &str) -> bool {
+    let mut rest = format;
+    while let Some(start) = rest.find('{') {
+        let Some(len) = rest[start..].find('}') else {
+            return true;
+        };
+        if !FORMAT_FIELDS.contains(&&rest[start + 1..start + len]) {
+            return false;
+        }
+        rest = &rest[start + len + 1..];
+    }
+    true
+}
+
+/// Translate `atuin search` arguments, or None if the database can't answer
+/// them the way atuin would
+fn parse_search(args: &[String], now: i64) -> Option<Search> {
+    let (subcommand, rest) = args.split_first()?;
+    if subcommand != "search" {
+        return None;
+    }
+
+    // atuin's own default search mode
+    let mut search = Search {
+        mode: "fuzzy".to_string(),
+        ..Default::default()
+    };
+    let mut format = None;
+    let mut words = Vec::new();
+    let mut rest = rest.iter();
+    while let Some(arg) = rest.next() {
+        if arg == "--" {
+            words.extend(rest.by_ref().map(String::as_str));
+            break;
+        }
+        if !arg.starts_with("--") {
+            words.push(arg.as_str());
+            continue;
+        }
+        let value = rest.next()?;
+        match arg.as_str() {
+            "--search-mode" => search.mode = value.clone(),
+            "--limit" => search.limit = Some(value.parse().ok()?),
+            "--offset" => search.offset = value.parse().ok()?,
+            "--format" => format = Some(value.clone()),
+            "--cwd" => search.cwd = Some(value.clone()),
+            "--exit" => search.exit = Some(value.parse().ok()?),
+            "--exclude-exit" => search.exclude_exit = Some(value.parse().ok()?),
+            "--after" => search.after = Some(parse_time_bound(value, now)?),
+            "--before" => search.before = Some(parse_time_bound(value, now)?),
+            _ => return None,
+        }
+    }
+
+    if !["prefix", "fulltext", "fuzzy", "skim"].contains(&search.mode.as_str()) {
+        return None;
+    }
+    search.format = format.filter(|f| is_supported_format(f))?;
+    search.query = words.join(" ");
+    Some(search)
+}
+
+/// Escape `%`, `_` and `\` for a `LIKE ... ESCAPE '\'` pattern
+fn escape_like(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// SQL condition and pattern for one term of a fuzzy query, following
+/// atuin's fzf-style syntax: `^prefix`, `suffix$`, `'exact` and `!negated`
+fn fuzzy_term(term: &str) -> Option<(&'static str, String)> {
+    let (negated, term) = match term.strip_prefix('!') {
+        Some(term) => (true, term),
+        None => (false, term),
+    };
+    let pattern = if let Some(prefix) = term.strip_prefix('^') {
+        format!("{}%", escape_like(prefix))
+    } else if let Some(suffix) = term.strip_suffix('$') {
+        format!("%{}", escape_like(suffix))
+    } else {
+        format!("%{}%", escape_like(term.strip_prefix('\'').unwrap_or(term)))
+    };
+    if pattern.trim_matches('%').is_empty() {
+        return None;
+    }
+
+    let condition = if negated {
+        "command NOT LIKE ? ESCAPE '\\'"
+    } else {
+        "command LIKE ? ESCAPE '\\'"
+    };
+    Some((condition, pattern))
+}
+
+/// Nanoseconds since the epoch for a time bound, as SQL and its parameter
+fn time_bound_sql(bound: &TimeBound) -> (&'static str, Value) {
+    match bound {
+        TimeBound::Epoch(seconds) => ("?", Value::Integer(seconds * 1_000_000_000)),
+        TimeBound::Local(time) => (
+            "CAST(strftime('%s', ?, 'utc') AS INTEGER) * 1000000000",
+            Value::Text(time.clone()),
+        ),
+    }
+}
+
+/// The query for a search, newest entries first, and its parameters
+fn search_sql(search: &Search) -> (String, Vec<Value>) {
+    let mut conditions = vec!["deleted_at IS NULL".to_string()];
+    let mut params = Vec::new();
+
+    match search.mode.as_str() {
+        _ if search.query.is_empty() => {}
+        "prefix" => {
+            conditions.push("command LIKE ? ESCAPE '\\'".to_string());
+            params.push(Value::Text(format!("{}%", escape_like(&search.query))));
+        }
+        "fulltext" => {
+            conditions.push("command LIKE ? ESCAPE '\\'".to_string());
+            params.push(Value::Text(format!("%{}%", escape_like(&search.query))));
+        }
+        _ => {
+            for (condition, pattern) in search.query.split_whitespace().filter_map(fuzzy_term) {
+                conditions.push(condition.to_string());
+                params.push(Value::Text(pattern));
+            }
+        }
+    }
+
+    let mut equal = |column: &str, value: Value| {
+        conditions.push(format!("{} = ?", column));
+        params.push(value);
+    };
+    if let Some(ref cwd) = search.cwd {
+        equal("cwd", Value::Text(cwd.clone()));
+    }
+    if let Some(exit) = search.exit {
+        equal("exit", Value::Integer(exit));
+    }
+    if let Some(exit) = search.exclude_exit {
+        conditions.push("exit != ?".to_string());
+        params.push(Value::Integer(exit));
+    }
+    for (bound, operator) in [(&search.after, ">"), (&search.before, "<")] {
+        let Some(bound) = bound else {
+            continue;
+        };
+        let (sql, value) = time_bound_sql(bound);
+        conditions.push(format!("timestamp {} {}", operator, sql));
+        params.push(value);
+    }
+
+    // A negative limit means no limit to SQLite
+    params.push(Value::Integer(search.limit.map_or(-1, i64::from)));
+    params.push(Value::Integer(i64::from(search.offset)));
+    let sql = format!(
+        "SELECT command, exit, duration, cwd,
+                datetime(timestamp / 1000000000, 'unixepoch', 'localtime'), hostname, session
+         FROM history WHERE {} ORDER BY timestamp DESC LIMIT ? OFFSET ?",
+        conditions.join(" AND ")
+    );
+    (sql, params)
+}
+
+/// Format a duration in nanoseconds the way atuin prints it, e.g. "1m 30s"
+fn format_duration(nanos: i64) -> String {
+    if nanos < 0 {
+        // Still running, or never recorded
+        return "-".to_string();
+    }
+    let seconds = nanos / 1_000_000_000;
+    if seconds >= 3_600 {
+        format!("{}h {}m", seconds / 3_600, seconds % 3_600 / 60)
+    } else if seconds >= 60 {
+        format!("{}m {}s", seconds / 60, seconds % 60)
+    } else if seconds >= 1 {
+        format!("{}s", seconds)
+    } else if nanos >= 1_000_000 {
+        format!("{}ms", nanos / 1_000_000)
+    } else if nanos >= 1_000 {
+        format!("{}us", nanos / 1_000)
+    } else {
+        format!("{}ns", nanos)
+    }
+}
+
+/// One row of the history table
+struct Row {
+    command: String,
+    exit: i64,
+    duration: i64,
+    cwd: String,
+    time: String,
+    hostname: String,
+    session: String,
+}
+
+impl Row {
+    fn field(&self, name: &str) -> String {
+        let (host, user) = self
+            .hostname
+            .split_once(':')
+            .unwrap_or((self.hostname.as_str(), ""));
+        match name {
+            "command" => self.command.clone(),
+            "exit" => self.exit.to_string(),
+            "duration" => format_duration(self.duration),
+            "directory" => self.cwd.clone(),
+            "time" => self.time.clone(),
+            "host" => host.to_string(),
+            "user" => user.to_string(),
+            "session" => self.session.clone(),
+            _ => format!("{{{}}}", name),
+        }
+    }
+
+    /// Fill in the placeholders of a `--format` string
+    fn render(&self, format: &str) -> String {
+        let mut line = String::new();
+        let mut rest = format;
+        while let Some(start) = rest.find('{') {
+            let Some(len) = rest[start..].find('}') else {
+                break;
+            };
+            line.push_str(&rest[..start]);
+            line.push_str(&self.field(&rest[start + 1..start + len]));
+            rest = &rest[start + len + 1..];
+        }
+        line.push_str(rest);
+        line
+    }
+}
+
+/// Answers `atuin search` from atuin's SQLite database in-process, which is
+/// much faster than starting atuin on every keystroke. Invocations it can't
+/// translate, and failed queries, go to `fallback`.
+pub struct SqliteBackend<B> {
+    conn: Connection,
+    fallback: B,
+    /// Whether the last invocation went to `fallback`
+    fell_back: Cell<bool>,
+}
+
+impl<B: HistoryBackend> SqliteBackend<B> {
+    pub fn new(conn: Connection, fallback: B) -> Self {
+        Self {
+            conn,
+            fallback,
+            fell_back: Cell::new(false),
+        }
+    }
+
+    /// Run a search, printing matches oldest first like `atuin search`
+    fn search(&self, search: &Search) -> rusqlite::Result<String> {
+        let (sql, params) = search_sql(search);
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt
+            .query_map(params_from_iter(params), |row| {
+                Ok(Row {
+                    command: row.get(0)?,
+                    exit: row.get(1)?,
+                    duration: row.get(2)?,
+                    cwd: row.get(3)?,
+                    time: row.get(4)?,
+                    hostname: row.get(5)?,
+                    session: row.get(6)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<Row>>>()?;
+
+        Ok(rows
+            .iter()
+            .rev()
+            .map(|row| format!("{}\n", row.render(&search.format)))
+            .collect())
+    }
+}
+
+impl<B: HistoryBackend> HistoryBackend for SqliteBackend<B> {
+    fn run(&self, args: &[String]) -> Result<String, SearchError> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs() as i64);
+        self.fell_back.set(true);
+        let Some(search) = parse_search(args, now) else {
+            return self.fallback.run(args);
+        };
+        match self.search(&search) {
+            Ok(output) => {
+                self.fell_back.set(false);
+                Ok(output)
+            }
+            Err(e) => {
+                eprintln!("Searching with the atuin CLI, database query failed: {}", e);
+                self.fallback.run(args)
+            }
+        }
+    }
+
+    fn last_skipped_lines(&self) -> usize {
+        if self.fell_back.get() {
+            self.fallback.last_skipped_lines()
+        } else {
+            0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{search_with_backend, SearchFilters};
+    use std::cell::RefCell;
+
+    /// Records the invocations it is handed
+    #[derive(Default)]
+    struct RecordingBackend {
+        calls: RefCell<Vec<Vec<String>>>,
+    }
+
+    impl HistoryBackend for RecordingBackend {
+        fn run(&self, args: &[String]) -> Result<String, SearchError> {
+            self.calls.borrow_mut().push(args.to_vec());
+            Ok(String::new())
+        }
+    }
+
+    const SECOND: i64 = 1_000_000_000;
+
+    /// `(command, exit, duration_ns, cwd, timestamp_s, hostname, deleted)`
+    type HistoryRow<'a> = (&'a str, i64, i64, &'a str, i64, &'a str, bool);
+
+    fn backend(rows: &[HistoryRow]) -> SqliteBackend<RecordingBackend> {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE history (
+                id TEXT PRIMARY KEY, timestamp INTEGER NOT NULL, duration INTEGER NOT NULL,
+                exit INTEGER NOT NULL, command TEXT NOT NULL, cwd TEXT NOT NULL,
+                session TEXT NOT NULL, hostname TEXT NOT NULL, deleted_at INTEGER
+            )",
+        )
+        .unwrap();
+        for (i, (command, exit, duration, cwd, time, hostname, deleted)) in rows.iter().enumerate()
+        {
+            conn.execute(
+                "INSERT INTO history VALUES (?, ?, ?, ?, ?, ?, 'session-1', ?, ?)",
+                rusqlite::params![
+                    i.to_string(),
+                    time * SECOND,
+                    duration,
+                    exit,
+                    command,
+                    cwd,
+                    hostname,
+                    deleted.then_some(time * SECOND),
+                ],
+            )
+            .unwrap();
+        }
+        SqliteBackend::new(conn, RecordingBackend::default())
+    }
+
+    fn search(
+        backend: &SqliteBackend<RecordingBackend>,
+        query: &str,
+        filters: SearchFilters,
+    ) -> Vec<String> {
+        search_with_backend(query, Some(filters), backend)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.command)
+            .collect()
+    }
+
+    #[test]
+    fn test_search_returns_newest_matches_oldest_first() {
+        let backend = backend(&[
+            (
+                "git status",
+                0,
+                20 * 1_000_000,
+                "/repo",
+                100,
+                "laptop:me",
+                false,
+            ),
+            ("ls", 0, 1_000_000, "/repo", 200, "laptop:me", false),
+            ("git push", 1, 3 * SECOND, "/repo", 300, "laptop:me", false),
+            ("git log", 0, 1_000_000, "/repo", 400, "laptop:me", true),
+        ]);
+
+        let ignore_case = SearchFilters {
+            case_sensitive: Some(false),
+            ..Default::default()
+        };
+        let entries = search_with_backend("GIT", Some(ignore_case), &backend).unwrap();
+        let commands: Vec<_> = entries.iter().map(|e| e.command.as_str()).collect();
+        assert_eq!(commands, vec!["git status", "git push"]);
+        assert_eq!(entries[1].exit, 1);
+        assert_eq!(entries[1].duration, "3s");
+        assert_eq!(entries[1].directory, "/repo");
+        assert_eq!(entries[1].time.len(), "YYYY-MM-DD HH:MM:SS".len());
+
+        let filters = SearchFilters {
+            limit: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(search(&backend, "git", filters), vec!["git push"]);
+
+        let filters = SearchFilters {
+            limit: Some(1),
+            offset: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(search(&backend, "git", filters), vec!["git status"]);
+        assert!(backend.fallback.calls.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_search_applies_filters() {
+        let backend = backend(&[
+            ("make", 0, SECOND, "/a", 100, "laptop:me", false),
+            ("make", 2, SECOND, "/b", 200, "server:me", false),
+            ("make test", 127, SECOND, "/a", 300, "laptop:me", false),
+        ]);
+
+        let failures = SearchFilters {
+            exit_filter: Some("failure".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            search(&backend, "make", failures),
+            vec!["make", "make test"]
+        );
+
+        let exact = SearchFilters {
+            exit_filter: Some("127".to_string()),
+            directory: Some("/a".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(search(&backend, "make", exact), vec!["make test"]);
+
+        let host = SearchFilters {
+            host: Some("server".to_string()),
+            session: Some("session-1".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(search(&backend, "", host), vec!["make"]);
+
+        let recent = SearchFilters {
+            time_range: Some("24h".to_string()),
+            ..Default::default()
+        };
+        assert!(search(&backend, "", recent).is_empty());
+
+        let older = SearchFilters {
+            before: Some("2 days ago".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(search(&backend, "", older).len(), 3);
+        assert!(backend.fallback.calls.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_search_modes() {
+        let backend = backend(&[
+            ("cargo build --release", 0, SECOND, "/a", 100, "h:u", false),
+            ("cargo test", 0, SECOND, "/a", 200, "h:u", false),
+            ("echo 100%", 0, SECOND, "/a", 300, "h:u", false),
+            ("echo 1000", 0, SECOND, "/a", 400, "h:u", false),
+        ]);
+        let mode = |mode: &str| SearchFilters {
+            search_mode: Some(mode.to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            search(&backend, "release", mode("prefix")),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            search(&backend, "release", mode("fulltext")),
+            vec!["cargo build --release"]
+        );
+        assert_eq!(
+            search(&backend, "100%", mode("fulltext")),
+            vec!["echo 100%"]
+        );
+        assert_eq!(
+            search(&backend, "car !test", mode("fuzzy")),
+            vec!["cargo build --release"]
+        );
+        assert_eq!(
+            search(&backend, "^echo 0$", mode("fuzzy")),
+            vec!["echo 1000"]
+        );
+        assert_eq!(
+            search(&backend, "--release", mode("fulltext")),
+            vec!["cargo build --release"]
+        );
+        assert!(backend.fallback.calls.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_unsupported_invocations_fall_back() {
+        let backend = backend(&[]);
+        let args = |list: &[&str]| list.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+
+        for invocation in [
+            args(&["stats", "--count", "5"]),
+            args(&["search", "--interactive", "true", "git"]),
+            args(&["search", "--format", "{relativetime}", "git"]),
+            args(&[
+                "search",
+                "--format",
+                "{command}",
+                "--after",
+                "last tuesday",
+                "git",
+            ]),
+            args(&["search", "git"]),
+        ] {
+            backend.run(&invocation).unwrap();
+            assert_eq!(backend.fallback.calls.borrow().last(), Some(&invocation));
+        }
+    }
+
+    #[test]
+    fn test_parse_time_bound() {
+        assert_eq!(
+            parse_time_bound("1 hour ago", 10_000),
+            Some(TimeBound::Epoch(6_400))
+        );
+        assert_eq!(
+            parse_time_bound("7 days ago", 1_000_000),
+            Some(TimeBound::Epoch(395_200))
+        );
+        assert_eq!(
+            parse_time_bound("2024-01-01", 0),
+            Some(TimeBound::Local("2024-01-01 00:00:00".to_string()))
+        );
+        assert_eq!(parse_time_bound("yesterday", 0), None);
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(500), "500ns");
+        assert_eq!(format_duration(42_000_000), "42ms");
+        assert_eq!(format_duration(3 * SECOND + 400_000_000), "3s");
+        assert_eq!(format_duration(90 * SECOND), "1m 30s");
+        assert_eq!(format_duration(3_725 * SECOND), "1h 2m");
+        assert_eq!(format_duration(-1), "-");
+    }
+
+    #[test]
+    fn test_open_history_db_rejects_missing_and_foreign_databases() {
+        let dir = env::temp_dir().join(format!("atuin-bar-sqlite-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        assert!(open_history_db(&dir.join("missing.db")).is_err());
+
+        let other = dir.join("other.db");
+        Connection::open(&other)
+            .unwrap()
+            .execute_batch("CREATE TABLE notes (text TEXT)")
+            .unwrap();
+        let err = open_history_db(&other).unwrap_err();
+        assert!(
+            err.contains("not an atuin history database"),
+            "got: {}",
+            err
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_history_db_path_prefers_atuin_env() {
+        let mut atuin_env = BTreeMap::new();
+        atuin_env.insert(DB_PATH_VAR.to_string(), "/data/history.db".to_string());
+        assert_eq!(
+            history_db_path(&atuin_env),
+            Some(PathBuf::from("/data/history.db"))
+        );
+    }
+}
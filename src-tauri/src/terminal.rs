@@ -0,0 +1,123 @@
+use std::env;
+use std::process::Command;
+
+/// Default terminal command template for the current platform, if any.
+///
+/// On Linux there is no universal terminal, so `$TERMINAL` is used when set.
+pub fn default_terminal_cmd() -> Option<String> {
+    if cfg!(target_os = "macos") {
+        Some("open -a Terminal {dir}".to_string())
+    } else if cfg!(target_os = "windows") {
+        Some("cmd /C start cmd /K cd /d {dir}".to_string())
+    } else {
+        env::var("TERMINAL").ok().filter(|t| !t.trim().is_empty())
+    }
+}
+
+/// Build the process that opens a terminal.
+///
+/// `template` is split on whitespace into a program and arguments; in each
+/// piece `{dir}` is replaced with the working directory and `{command}` with
+/// the command to pre-fill, for terminals that can run one on startup. The
+/// process is also started in the directory, so templates without `{dir}`
+/// still open there.
+pub fn build_terminal_command(
+    template: &str,
+    directory: &str,
+    command: &str,
+) -> Result<Command, String> {
+    let mut parts = template.split_whitespace().map(|part| {
+        part.replace("{dir}", directory)
+            .replace("{command}", command)
+    });
+    let program = parts
+        .next()
+        .ok_or_else(|| "Terminal command is empty".to_string())?;
+
+    let mut cmd = Command::new(program);
+    cmd.args(parts);
+    if !directory.is_empty() {
+        cmd.current_dir(directory);
+    }
+    Ok(cmd)
+}
+
+/// Launch the configured (or default) terminal in `directory`
+pub fn open_in_terminal(
+    terminal_cmd: &str,
+    command: &str,
+    directory: Option<&str>,
+) -> Result<(), String> {
+    let template = Some(terminal_cmd.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .or_else(default_terminal_cmd)
+        .ok_or_else(|| {
+            "No terminal configured: set terminal_cmd in config.toml (e.g. \
+             \"kitty --directory {dir}\") or the TERMINAL environment variable"
+                .to_string()
+        })?;
+
+    let directory = match directory.map(str::trim).filter(|d| !d.is_empty()) {
+        Some(dir) => dir.to_string(),
+        None => dirs::home_dir()
+            .map(|p| p.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+    };
+
+    let mut child = build_terminal_command(&template, &directory, command)?
+        .spawn()
+        .map_err(|e| format!("Failed to launch terminal '{}': {}", template, e))?;
+    // Reap the terminal when it exits so it doesn't linger as a zombie
+    std::thread::spawn(move || {
+        let _ = child.wait();
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsStr;
+    use std::path::Path;
+
+    #[test]
+    fn test_build_terminal_command_substitutes_placeholders() {
+        let cmd = build_terminal_command(
+            "kitty --directory {dir} --hold sh -c {command}",
+            "/home/user/my project",
+            "cargo test",
+        )
+        .unwrap();
+
+        assert_eq!(cmd.get_program(), "kitty");
+        let args: Vec<&OsStr> = cmd.get_args().collect();
+        assert_eq!(
+            args,
+            vec![
+                "--directory",
+                "/home/user/my project",
+                "--hold",
+                "sh",
+                "-c",
+                "cargo test"
+            ]
+        );
+        assert_eq!(
+            cmd.get_current_dir(),
+            Some(Path::new("/home/user/my project"))
+        );
+    }
+
+    #[test]
+    fn test_build_terminal_command_without_placeholders() {
+        let cmd = build_terminal_command("alacritty", "/tmp", "ls").unwrap();
+        assert_eq!(cmd.get_program(), "alacritty");
+        assert_eq!(cmd.get_args().count(), 0);
+        assert_eq!(cmd.get_current_dir(), Some(Path::new("/tmp")));
+    }
+
+    #[test]
+    fn test_build_terminal_command_rejects_empty_template() {
+        assert!(build_terminal_command("   ", "/tmp", "ls").is_err());
+    }
+}
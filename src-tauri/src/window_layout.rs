@@ -0,0 +1,165 @@
+/// Height of the search input row, in logical pixels
+const BASE_HEIGHT: u32 = 38;
+/// Height of the filter panel when it is shown
+const FILTER_PANEL_HEIGHT: u32 = 56;
+/// Height of one result row
+const RESULT_HEIGHT: u32 = 32;
+/// Padding around the result list when it isn't empty
+const CONTAINER_PADDING: u32 = 8;
+
+/// Height of the search window for `count` results.
+///
+/// At most `max_results` rows are counted and the height never exceeds
+/// `window_height`. Without `auto_resize` the window stays at `window_height`.
+pub fn results_window_height(
+    count: u32,
+    max_results: u32,
+    window_height: u32,
+    auto_resize: bool,
+    filters_visible: bool,
+) -> u32 {
+    if !auto_resize {
+        return window_height;
+    }
+
+    let rows = count.min(max_results);
+    let results = if rows > 0 {
+        rows.saturating_mul(RESULT_HEIGHT)
+            .saturating_add(CONTAINER_PADDING)
+    } else {
+        0
+    };
+    let filters = if filters_visible {
+        FILTER_PANEL_HEIGHT
+    } else {
+        0
+    };
+
+    BASE_HEIGHT
+        .saturating_add(filters)
+        .saturating_add(results)
+        .min(window_height)
+}
+
+/// A monitor's area, in physical pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MonitorArea {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl MonitorArea {
+    fn contains(&self, (x, y): (i32, i32)) -> bool {
+        let within = |value: i32, start: i32, extent: u32| {
+            value >= start && i64::from(value) < i64::from(start) + i64::from(extent)
+        };
+        within(x, self.x, self.width) && within(y, self.y, self.height)
+    }
+}
+
+/// Where to restore a window of `size` last seen at `position`: on the
+/// monitor containing its top-left corner, moved in so it fits on that
+/// monitor. None when the corner is off every monitor (e.g. the monitor was
+/// unplugged) and the window should be centered instead.
+pub fn restore_position(
+    position: (i32, i32),
+    size: (u32, u32),
+    monitors: &[MonitorArea],
+) -> Option<(i32, i32)> {
+    let monitor = monitors.iter().find(|monitor| monitor.contains(position))?;
+    let clamp = |value: i32, start: i32, extent: u32, length: u32| {
+        let end = i64::from(start) + i64::from(extent.saturating_sub(length));
+        i64::from(value).clamp(i64::from(start), end) as i32
+    };
+
+    Some((
+        clamp(position.0, monitor.x, monitor.width, size.0),
+        clamp(position.1, monitor.y, monitor.height, size.1),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_height_grows_with_results() {
+        assert_eq!(results_window_height(0, 20, 500, true, false), 38);
+        assert_eq!(results_window_height(1, 20, 500, true, false), 38 + 32 + 8);
+        assert_eq!(
+            results_window_height(3, 20, 500, true, true),
+            38 + 56 + 3 * 32 + 8
+        );
+    }
+
+    #[test]
+    fn test_height_is_bounded_by_max_results_and_window_height() {
+        // Only max_results rows are shown
+        assert_eq!(
+            results_window_height(50, 5, 1000, true, false),
+            38 + 5 * 32 + 8
+        );
+        // Never taller than the configured window height
+        assert_eq!(results_window_height(50, 50, 500, true, false), 500);
+    }
+
+    #[test]
+    fn test_fixed_height_without_auto_resize() {
+        assert_eq!(results_window_height(0, 20, 500, false, false), 500);
+        assert_eq!(results_window_height(10, 20, 500, false, true), 500);
+    }
+
+    const MONITORS: &[MonitorArea] = &[
+        MonitorArea {
+            x: 0,
+            y: 0,
+            width: 1920,
+            height: 1080,
+        },
+        MonitorArea {
+            x: 1920,
+            y: -200,
+            width: 1280,
+            height: 1024,
+        },
+    ];
+
+    #[test]
+    fn test_restore_position_on_a_monitor() {
+        assert_eq!(
+            restore_position((100, 200), (700, 60), MONITORS),
+            Some((100, 200))
+        );
+        assert_eq!(
+            restore_position((2000, -100), (700, 60), MONITORS),
+            Some((2000, -100))
+        );
+    }
+
+    #[test]
+    fn test_restore_position_moves_window_back_onto_its_monitor() {
+        // Hanging off the right edge of the first monitor
+        assert_eq!(
+            restore_position((1800, 1070), (700, 60), MONITORS),
+            Some((1220, 1020))
+        );
+        // Larger than the monitor: pinned to its top-left corner
+        assert_eq!(
+            restore_position((2500, 0), (4000, 2000), MONITORS),
+            Some((1920, -200))
+        );
+    }
+
+    #[test]
+    fn test_restore_position_off_all_monitors_falls_back_to_centering() {
+        // e.g. the second monitor was unplugged
+        assert_eq!(
+            restore_position((2000, 100), (700, 60), &MONITORS[..1]),
+            None
+        );
+        assert_eq!(restore_position((-500, -500), (700, 60), MONITORS), None);
+        assert_eq!(restore_position((100, 100), (700, 60), &[]), None);
+    }
+}
@@ -0,0 +1,835 @@
+use std::cell::RefCell;
+
+use atuin_bar_lib::{
+    build_atuin_command, search_output_with_fetch_limit, search_window_with_backend,
+    search_with_backend, verify_in_history_with_backend, HistoryBackend, ParsedHistoryEntry,
+    SearchError, SearchFilters,
+};
+
+// Scripted stand-in for atuin: records the arguments of each invocation and
+// replies with a canned result
+struct MockBackend {
+    reply: Result<String, SearchError>,
+    calls: RefCell<Vec<Vec<String>>>,
+}
+
+impl MockBackend {
+    fn with_output(output: &str) -> Self {
+        MockBackend {
+            reply: Ok(output.to_string()),
+            calls: RefCell::new(Vec::new()),
+        }
+    }
+
+    fn failing(error: SearchError) -> Self {
+        MockBackend {
+            reply: Err(error),
+            calls: RefCell::new(Vec::new()),
+        }
+    }
+
+    fn last_args(&self) -> Vec<String> {
+        self.calls
+            .borrow()
+            .last()
+            .cloned()
+            .expect("backend was not called")
+    }
+}
+
+impl HistoryBackend for MockBackend {
+    fn run(&self, args: &[String]) -> Result<String, SearchError> {
+        self.calls.borrow_mut().push(args.to_vec());
+        self.reply.clone()
+    }
+}
+
+const HISTORY: &str = "\
+git status|0|12ms|/repo|2024-01-01 10:00:00
+cargo test|101|3s|/repo|2024-01-01 10:01:00
+git push|1|1s|/repo|2024-01-01 10:02:00
+ls -la|0|5ms|/tmp|2024-01-01 10:03:00
+";
+
+fn args_after(args: &[String], flag: &str) -> Option<String> {
+    let position = args.iter().position(|arg| arg == flag)?;
+    args.get(position + 1).cloned()
+}
+
+#[test]
+fn test_search_without_filters_parses_all_entries() {
+    let backend = MockBackend::with_output(HISTORY);
+    let entries = search_with_backend("git", None, &backend).unwrap();
+
+    assert_eq!(entries.len(), 4);
+    assert_eq!(entries[1].command, "cargo test");
+    assert_eq!(entries[1].exit, 101);
+
+    let args = backend.last_args();
+    assert_eq!(args[0], "search");
+    assert_eq!(args.last().map(String::as_str), Some("git"));
+    assert!(!args.contains(&"--cwd".to_string()));
+    assert!(!args.contains(&"--exit".to_string()));
+    assert!(!args.contains(&"--after".to_string()));
+}
+
+#[test]
+fn test_directory_filter() {
+    let backend = MockBackend::with_output(HISTORY);
+    let filters = SearchFilters {
+        directory: Some("/repo".to_string()),
+        ..Default::default()
+    };
+    search_with_backend("", Some(filters), &backend).unwrap();
+    assert_eq!(
+        args_after(&backend.last_args(), "--cwd").as_deref(),
+        Some("/repo")
+    );
+
+    let filters = SearchFilters {
+        directory: Some(String::new()),
+        ..Default::default()
+    };
+    search_with_backend("", Some(filters), &backend).unwrap();
+    assert!(!backend.last_args().contains(&"--cwd".to_string()));
+}
+
+// Output of a search filtered by host or session, which prefixes each line
+// with the entry's host and session
+const HOST_SESSION_HISTORY: &str = "\
+laptop|0195f3a2|git status|0|12ms|/repo|2024-01-01 10:00:00
+build-server|0195f3a2|cargo test|101|3s|/repo|2024-01-01 10:01:00
+laptop|77aa01bc|git push|1|1s|/repo|2024-01-01 10:02:00
+";
+
+#[test]
+fn test_session_filter_with_exit_and_time_filters() {
+    let backend = MockBackend::with_output(HOST_SESSION_HISTORY);
+    let filters = SearchFilters {
+        session: Some("0195f3a2".to_string()),
+        exit_filter: Some("failure".to_string()),
+        time_range: Some("24h".to_string()),
+        ..Default::default()
+    };
+    let entries = search_with_backend("", Some(filters), &backend).unwrap();
+    let commands: Vec<_> = entries.iter().map(|e| e.command.as_str()).collect();
+    assert_eq!(commands, ["git status", "cargo test"]);
+
+    // atuin has no session option, so the session is filtered from the output
+    let args = backend.last_args();
+    let format = args.iter().position(|arg| arg == "--format").unwrap();
+    assert_eq!(
+        args[format + 1],
+        "{host}|{session}|{command}|{exit}|{duration}|{directory}|{time}"
+    );
+    assert_eq!(
+        &args[format + 2..],
+        ["--exclude-exit", "0", "--after", "1 day ago", "--", ""]
+    );
+
+    let backend = MockBackend::with_output(HISTORY);
+    let filters = SearchFilters {
+        session: Some(String::new()),
+        ..Default::default()
+    };
+    search_with_backend("", Some(filters), &backend).unwrap();
+    assert_eq!(
+        args_after(&backend.last_args(), "--format").as_deref(),
+        Some("{command}|{exit}|{duration}|{directory}|{time}")
+    );
+}
+
+#[test]
+fn test_host_filter() {
+    let backend = MockBackend::with_output(HOST_SESSION_HISTORY);
+    let filters = SearchFilters {
+        host: Some("build-server".to_string()),
+        ..Default::default()
+    };
+    let entries = search_with_backend("", Some(filters), &backend).unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].command, "cargo test");
+    assert!(!backend.last_args().contains(&"--host".to_string()));
+
+    let filters = SearchFilters {
+        host: Some("laptop".to_string()),
+        session: Some("77aa01bc".to_string()),
+        ..Default::default()
+    };
+    let entries = search_with_backend("", Some(filters), &backend).unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].command, "git push");
+}
+
+#[test]
+fn test_exit_filters() {
+    let backend = MockBackend::with_output(HISTORY);
+
+    let success = SearchFilters {
+        exit_filter: Some("success".to_string()),
+        ..Default::default()
+    };
+    search_with_backend("", Some(success), &backend).unwrap();
+    assert_eq!(
+        args_after(&backend.last_args(), "--exit").as_deref(),
+        Some("0")
+    );
+
+    let failure = SearchFilters {
+        exit_filter: Some("failure".to_string()),
+        ..Default::default()
+    };
+    search_with_backend("", Some(failure), &backend).unwrap();
+    assert_eq!(
+        args_after(&backend.last_args(), "--exclude-exit").as_deref(),
+        Some("0")
+    );
+
+    let code = SearchFilters {
+        exit_filter: Some("127".to_string()),
+        ..Default::default()
+    };
+    search_with_backend("", Some(code), &backend).unwrap();
+    let args = backend.last_args();
+    assert_eq!(args_after(&args, "--exit").as_deref(), Some("127"));
+    assert!(!args.contains(&"--exclude-exit".to_string()));
+
+    let unknown = SearchFilters {
+        exit_filter: Some("sometimes".to_string()),
+        ..Default::default()
+    };
+    search_with_backend("", Some(unknown), &backend).unwrap();
+    let args = backend.last_args();
+    assert!(!args.contains(&"--exit".to_string()));
+    assert!(!args.contains(&"--exclude-exit".to_string()));
+}
+
+#[test]
+fn test_time_range_filters() {
+    let backend = MockBackend::with_output(HISTORY);
+    let cases = [
+        ("1h", Some("1 hour ago")),
+        ("24h", Some("1 day ago")),
+        ("7d", Some("7 days ago")),
+        ("30d", Some("30 days ago")),
+        // Anything else is passed through for atuin to interpret
+        ("3 months ago", Some("3 months ago")),
+        ("  ", None),
+    ];
+
+    for (range, expected) in cases {
+        let filters = SearchFilters {
+            time_range: Some(range.to_string()),
+            ..Default::default()
+        };
+        search_with_backend("", Some(filters), &backend).unwrap();
+        assert_eq!(
+            args_after(&backend.last_args(), "--after").as_deref(),
+            expected,
+            "{}",
+            range
+        );
+    }
+}
+
+#[test]
+fn test_custom_after_and_before() {
+    let backend = MockBackend::with_output(HISTORY);
+    let filters = SearchFilters {
+        time_range: Some("2024-01-01".to_string()),
+        before: Some("2024-02-01".to_string()),
+        ..Default::default()
+    };
+    search_with_backend("", Some(filters), &backend).unwrap();
+
+    let args = backend.last_args();
+    assert_eq!(args_after(&args, "--after").as_deref(), Some("2024-01-01"));
+    assert_eq!(args_after(&args, "--before").as_deref(), Some("2024-02-01"));
+
+    // Only an end: everything up to it
+    let filters = SearchFilters {
+        before: Some("7 days ago".to_string()),
+        ..Default::default()
+    };
+    search_with_backend("", Some(filters), &backend).unwrap();
+    let args = backend.last_args();
+    assert!(!args.contains(&"--after".to_string()));
+    assert_eq!(args_after(&args, "--before").as_deref(), Some("7 days ago"));
+}
+
+#[test]
+fn test_boolean_query_post_filters_results() {
+    let backend = MockBackend::with_output(HISTORY);
+    let filters = SearchFilters {
+        boolean_query: true,
+        ..Default::default()
+    };
+    let entries = search_with_backend("git AND NOT push", Some(filters), &backend).unwrap();
+
+    // The expression is evaluated locally, so atuin gets an empty query
+    assert_eq!(backend.last_args().last().map(String::as_str), Some(""));
+    let commands: Vec<_> = entries.iter().map(|e| e.command.as_str()).collect();
+    assert_eq!(commands, vec!["git status"]);
+}
+
+#[test]
+fn test_invalid_boolean_query_searches_literally() {
+    let backend = MockBackend::with_output(HISTORY);
+    let filters = SearchFilters {
+        boolean_query: true,
+        ..Default::default()
+    };
+    let entries = search_with_backend("git AND (", Some(filters), &backend).unwrap();
+
+    assert_eq!(
+        backend.last_args().last().map(String::as_str),
+        Some("git AND (")
+    );
+    assert_eq!(entries.len(), 4);
+}
+
+#[test]
+fn test_backend_errors_are_propagated() {
+    let backend = MockBackend::failing(SearchError::CommandFailed {
+        stderr: "no database".to_string(),
+    });
+    let result = search_with_backend("git", None, &backend);
+
+    let error = result.unwrap_err();
+    assert_eq!(
+        error,
+        SearchError::CommandFailed {
+            stderr: "no database".to_string()
+        }
+    );
+    assert_eq!(error.to_string(), "atuin command failed: no database");
+}
+
+#[test]
+fn test_duration_percentile_keeps_slowest_entries() {
+    let output: String = (1..=10)
+        .map(|n| format!("cmd{}|0|{}ms|/tmp|2024-01-01 10:00:{:02}\n", n, n * 100, n))
+        .collect();
+    let backend = MockBackend::with_output(&output);
+    let filters = SearchFilters {
+        duration_percentile: Some(90.0),
+        ..Default::default()
+    };
+    let entries = search_with_backend("", Some(filters), &backend).unwrap();
+
+    let commands: Vec<_> = entries.iter().map(|e| e.command.as_str()).collect();
+    assert_eq!(commands, vec!["cmd9", "cmd10"]);
+    // A broad sample is fetched to compute the distribution from
+    assert_eq!(
+        args_after(&backend.last_args(), "--limit").as_deref(),
+        Some("1000")
+    );
+}
+
+#[test]
+fn test_duration_bounds_filter_parsed_durations() {
+    let output = "ls|0|12ms|/tmp|2024-01-01 10:00:00\n\
+                  cargo build|0|42s|/src|2024-01-01 10:01:00\n\
+                  make|0|1m 30s|/src|2024-01-01 10:02:00\n\
+                  sleep 10|0|10s|/tmp|2024-01-01 10:03:00\n\
+                  weird|0|soon|/tmp|2024-01-01 10:04:00\n";
+    let backend = MockBackend::with_output(output);
+
+    let slow = SearchFilters {
+        min_duration_ms: Some(10_000),
+        ..Default::default()
+    };
+    let entries = search_with_backend("", Some(slow), &backend).unwrap();
+    let commands: Vec<_> = entries.iter().map(|e| e.command.as_str()).collect();
+    assert_eq!(commands, vec!["cargo build", "make", "sleep 10"]);
+    assert_eq!(
+        args_after(&backend.last_args(), "--limit").as_deref(),
+        Some("1000")
+    );
+
+    let between = SearchFilters {
+        min_duration_ms: Some(10_000),
+        max_duration_ms: Some(60_000),
+        ..Default::default()
+    };
+    let entries = search_with_backend("", Some(between), &backend).unwrap();
+    let commands: Vec<_> = entries.iter().map(|e| e.command.as_str()).collect();
+    assert_eq!(commands, vec!["cargo build", "sleep 10"]);
+
+    let fast = SearchFilters {
+        max_duration_ms: Some(100),
+        ..Default::default()
+    };
+    let entries = search_with_backend("", Some(fast), &backend).unwrap();
+    let commands: Vec<_> = entries.iter().map(|e| e.command.as_str()).collect();
+    assert_eq!(commands, vec!["ls"]);
+}
+
+#[test]
+fn test_dedup_collapses_repeated_commands() {
+    let output = "git pull|0|1s|/repo|2024-01-01 10:00:00\n\
+                  git pull|0|1s|/other|2024-01-01 10:01:00\n\
+                  cargo test|0|5s|/repo|2024-01-01 10:02:00\n\
+                  git pull|0|1s|/repo|2024-01-01 10:03:00\n";
+    let backend = MockBackend::with_output(output);
+
+    let entries = search_with_backend("", None, &backend).unwrap();
+    assert_eq!(entries.len(), 4, "Results are not deduplicated by default");
+
+    let unique = SearchFilters {
+        dedup: true,
+        ..Default::default()
+    };
+    let entries = search_with_backend("", Some(unique), &backend).unwrap();
+    let commands: Vec<_> = entries.iter().map(|e| e.command.as_str()).collect();
+    assert_eq!(commands, vec!["cargo test", "git pull"]);
+    assert_eq!(entries[1].time, "2024-01-01 10:03:00");
+
+    let per_directory = SearchFilters {
+        dedup: true,
+        dedup_by: Some("directory".to_string()),
+        ..Default::default()
+    };
+    let entries = search_with_backend("", Some(per_directory), &backend).unwrap();
+    let places: Vec<_> = entries
+        .iter()
+        .map(|e| (e.command.as_str(), e.directory.as_str()))
+        .collect();
+    assert_eq!(
+        places,
+        vec![
+            ("git pull", "/other"),
+            ("cargo test", "/repo"),
+            ("git pull", "/repo")
+        ]
+    );
+}
+
+/// Output for a case-insensitive search for "git", as atuin would return it
+const MIXED_CASE_OUTPUT: &str = "Git push|0|1s|/repo|2024-01-01 10:00:00\n\
+                                 git pull|0|1s|/repo|2024-01-01 10:01:00\n\
+                                 GIT log|0|1s|/repo|2024-01-01 10:02:00\n";
+
+fn commands(entries: &[ParsedHistoryEntry]) -> Vec<&str> {
+    entries.iter().map(|e| e.command.as_str()).collect()
+}
+
+#[test]
+fn test_lowercase_query_matches_any_case() {
+    let backend = MockBackend::with_output(MIXED_CASE_OUTPUT);
+
+    let entries = search_with_backend("git", None, &backend).unwrap();
+    assert_eq!(commands(&entries), vec!["Git push", "git pull", "GIT log"]);
+
+    let ignore_case = SearchFilters {
+        case_sensitive: Some(false),
+        ..Default::default()
+    };
+    let entries = search_with_backend("Git", Some(ignore_case), &backend).unwrap();
+    assert_eq!(entries.len(), 3);
+}
+
+#[test]
+fn test_uppercase_query_matches_exact_case() {
+    let backend = MockBackend::with_output(MIXED_CASE_OUTPUT);
+
+    let entries = search_with_backend("Git", None, &backend).unwrap();
+    assert_eq!(commands(&entries), vec!["Git push"]);
+
+    let match_case = SearchFilters {
+        case_sensitive: Some(true),
+        ..Default::default()
+    };
+    let entries = search_with_backend("git", Some(match_case), &backend).unwrap();
+    assert_eq!(commands(&entries), vec!["git pull"]);
+}
+
+// History of `cmd1` (oldest) to `cmd25` (newest) that honors `--limit` and
+// `--offset` like atuin: skipping the newest matches, printing oldest first
+struct WindowedBackend;
+
+impl HistoryBackend for WindowedBackend {
+    fn run(&self, args: &[String]) -> Result<String, SearchError> {
+        let number = |flag| args_after(args, flag).map_or(0, |v| v.parse::<usize>().unwrap());
+        let (limit, offset) = (number("--limit"), number("--offset"));
+        let mut newest_first: Vec<String> = (1..=25)
+            .rev()
+            .skip(offset)
+            .take(limit)
+            .map(|n| format!("cmd{}|0|1ms|/tmp|2024-01-01 10:00:{:02}\n", n, n))
+            .collect();
+        newest_first.reverse();
+        Ok(newest_first.concat())
+    }
+}
+
+#[test]
+fn test_search_windows_page_back_through_history() {
+    let window = |offset| {
+        let filters = SearchFilters {
+            limit: Some(10),
+            offset,
+            ..Default::default()
+        };
+        search_window_with_backend("", Some(filters), &WindowedBackend).unwrap()
+    };
+
+    let first = window(None);
+    let commands: Vec<_> = first.entries.iter().map(|e| e.command.as_str()).collect();
+    assert_eq!(
+        commands,
+        (16..=25).map(|n| format!("cmd{}", n)).collect::<Vec<_>>()
+    );
+    assert!(first.has_more);
+
+    let second = window(Some(10));
+    assert_eq!(second.offset, 10);
+    assert_eq!(
+        second.entries.first().map(|e| e.command.as_str()),
+        Some("cmd6")
+    );
+    assert_eq!(
+        second.entries.last().map(|e| e.command.as_str()),
+        Some("cmd15")
+    );
+    assert!(second.has_more);
+
+    let last = window(Some(20));
+    let commands: Vec<_> = last.entries.iter().map(|e| e.command.as_str()).collect();
+    assert_eq!(commands, vec!["cmd1", "cmd2", "cmd3", "cmd4", "cmd5"]);
+    assert!(!last.has_more);
+}
+
+#[test]
+fn test_offset_is_passed_to_atuin() {
+    let backend = MockBackend::with_output("");
+    let filters = SearchFilters {
+        limit: Some(10),
+        offset: Some(30),
+        ..Default::default()
+    };
+    search_window_with_backend("git", Some(filters), &backend).unwrap();
+
+    let args = backend.last_args();
+    assert_eq!(args_after(&args, "--offset").as_deref(), Some("30"));
+    // One extra result tells whether there are more
+    assert_eq!(args_after(&args, "--limit").as_deref(), Some("11"));
+}
+
+#[test]
+fn test_commands_with_pipes_and_newlines_survive_search() {
+    let output = "grep foo | wc -l|0|5ms|/tmp|2024-01-01 10:00:00\n\
+                  for f in *; do\n  echo \"$f\"\ndone|1|20ms|/home|2024-01-01 10:01:00\n";
+    let backend = MockBackend::with_output(output);
+    let filters = SearchFilters {
+        boolean_query: true,
+        ..Default::default()
+    };
+    let entries = search_with_backend("grep OR echo", Some(filters), &backend).unwrap();
+
+    let commands: Vec<_> = entries.iter().map(|e| e.command.as_str()).collect();
+    assert_eq!(
+        commands,
+        vec!["grep foo | wc -l", "for f in *; do\n  echo \"$f\"\ndone"]
+    );
+    assert_eq!(entries[1].exit, 1);
+    assert_eq!(entries[1].directory, "/home");
+    assert_eq!(
+        serde_json::from_str::<Vec<ParsedHistoryEntry>>(&serde_json::to_string(&entries).unwrap())
+            .unwrap(),
+        entries
+    );
+}
+
+#[test]
+fn test_search_mode_is_passed_to_atuin() {
+    let backend = MockBackend::with_output(HISTORY);
+    search_with_backend("git", None, &backend).unwrap();
+    assert_eq!(
+        args_after(&backend.last_args(), "--search-mode").as_deref(),
+        Some("prefix")
+    );
+
+    let filters = SearchFilters {
+        search_mode: Some("fuzzy".to_string()),
+        ..Default::default()
+    };
+    search_with_backend("git", Some(filters), &backend).unwrap();
+    assert_eq!(
+        args_after(&backend.last_args(), "--search-mode").as_deref(),
+        Some("fuzzy")
+    );
+
+    // A mode atuin would reject falls back to prefix
+    let filters = SearchFilters {
+        search_mode: Some("regex".to_string()),
+        ..Default::default()
+    };
+    search_with_backend("git", Some(filters), &backend).unwrap();
+    assert_eq!(
+        args_after(&backend.last_args(), "--search-mode").as_deref(),
+        Some("prefix")
+    );
+}
+
+#[test]
+fn test_limit_is_passed_to_atuin() {
+    let backend = MockBackend::with_output(HISTORY);
+    search_with_backend("git", None, &backend).unwrap();
+    assert_eq!(
+        args_after(&backend.last_args(), "--limit").as_deref(),
+        Some("50")
+    );
+
+    let filters = SearchFilters {
+        limit: Some(20),
+        ..Default::default()
+    };
+    search_with_backend("git", Some(filters), &backend).unwrap();
+    assert_eq!(
+        args_after(&backend.last_args(), "--limit").as_deref(),
+        Some("20")
+    );
+}
+
+// atuin output for `--format "{command}\u{1e}"`: each command is followed by
+// the separator and a newline
+fn records(commands: &[&str]) -> String {
+    commands.iter().map(|c| format!("{}\u{1e}\n", c)).collect()
+}
+
+#[test]
+fn test_verify_in_history_finds_exact_command() {
+    let backend = MockBackend::with_output(&records(&["git push --force", "git push"]));
+    assert!(verify_in_history_with_backend("git push", &backend).unwrap());
+    assert_eq!(
+        backend.last_args().last().map(String::as_str),
+        Some("git push")
+    );
+
+    let multi_line = "for f in *; do\n  echo $f\ndone";
+    let backend = MockBackend::with_output(&records(&[multi_line]));
+    assert!(verify_in_history_with_backend(multi_line, &backend).unwrap());
+}
+
+#[test]
+fn test_verify_in_history_rejects_substring_matches() {
+    let backend = MockBackend::with_output(&records(&["git push --force", "git push origin"]));
+    assert!(!verify_in_history_with_backend("git push", &backend).unwrap());
+}
+
+#[test]
+fn test_verify_in_history_passes_dashed_commands_as_the_query() {
+    let backend = MockBackend::with_output(&records(&["-c foo"]));
+    assert!(verify_in_history_with_backend("-c foo", &backend).unwrap());
+    assert!(backend
+        .last_args()
+        .ends_with(&["--".to_string(), "-c foo".to_string()]));
+}
+
+#[test]
+fn test_verify_in_history_absent_command() {
+    let backend = MockBackend::with_output("");
+    assert!(!verify_in_history_with_backend("cargo publish", &backend).unwrap());
+    assert!(!verify_in_history_with_backend("", &backend).unwrap());
+}
+
+#[test]
+fn test_dedup_over_fetch_limit_fills_max_results() {
+    // 100 runs cycling through 10 commands, oldest first
+    let history: String = (0..100)
+        .map(|n| {
+            format!(
+                "cmd{}|0|1ms|/tmp|2024-01-01 10:{:02}:{:02}\n",
+                n % 10,
+                n / 60,
+                n % 60
+            )
+        })
+        .collect();
+    let backend = MockBackend::with_output(&history);
+    let filters = SearchFilters {
+        dedup: true,
+        limit: Some(5),
+        ..Default::default()
+    };
+
+    let output = search_output_with_fetch_limit("", Some(filters), 100, &backend).unwrap();
+    // Dedup drops results, so a broad sample is fetched to draw from
+    assert_eq!(
+        args_after(&backend.last_args(), "--limit").as_deref(),
+        Some("1000")
+    );
+
+    let commands: Vec<&str> = output
+        .lines()
+        .map(|line| line.split('|').next().unwrap())
+        .collect();
+    assert_eq!(commands, vec!["cmd5", "cmd6", "cmd7", "cmd8", "cmd9"]);
+}
+
+// Replies with the `--limit` newest entries of a scripted history, like atuin
+struct HistoryBackendStub {
+    history: Vec<String>,
+    calls: RefCell<Vec<Vec<String>>>,
+}
+
+impl HistoryBackend for HistoryBackendStub {
+    fn run(&self, args: &[String]) -> Result<String, SearchError> {
+        self.calls.borrow_mut().push(args.to_vec());
+        let limit =
+            args_after(args, "--limit").map_or(self.history.len(), |limit| limit.parse().unwrap());
+        let start = self.history.len().saturating_sub(limit);
+        Ok(self.history[start..].concat())
+    }
+}
+
+#[test]
+fn test_boolean_query_finds_matches_beyond_the_newest_entries() {
+    // Two old matches buried under 5000 newer commands
+    let mut history = vec![
+        "git add a|0|1ms|/repo|2024-01-01 09:00:00\n".to_string(),
+        "git commit -m a|0|1ms|/repo|2024-01-01 09:01:00\n".to_string(),
+    ];
+    history.extend((0..5000).map(|n| format!("ls {}|0|1ms|/tmp|2024-01-02 10:00:00\n", n)));
+    let backend = HistoryBackendStub {
+        history,
+        calls: RefCell::new(Vec::new()),
+    };
+    let filters = SearchFilters {
+        boolean_query: true,
+        limit: Some(10),
+        ..Default::default()
+    };
+
+    let entries = search_with_backend("git AND NOT push", Some(filters), &backend).unwrap();
+    let commands: Vec<_> = entries.iter().map(|e| e.command.as_str()).collect();
+    assert_eq!(commands, ["git add a", "git commit -m a"]);
+
+    // The sample grew until history ran out
+    let limits: Vec<_> = backend
+        .calls
+        .borrow()
+        .iter()
+        .map(|call| args_after(call, "--limit").unwrap())
+        .collect();
+    assert_eq!(limits, ["1000", "4000", "16000"]);
+}
+
+#[test]
+fn test_boolean_query_stops_sampling_once_the_limit_is_filled() {
+    let history = (0..5000)
+        .map(|n| format!("git status {}|0|1ms|/repo|2024-01-02 10:00:00\n", n))
+        .collect();
+    let backend = HistoryBackendStub {
+        history,
+        calls: RefCell::new(Vec::new()),
+    };
+    let filters = SearchFilters {
+        boolean_query: true,
+        limit: Some(10),
+        ..Default::default()
+    };
+
+    let entries = search_with_backend("git AND status", Some(filters), &backend).unwrap();
+    assert_eq!(entries.len(), 10);
+    assert_eq!(entries[9].command, "git status 4999");
+    assert_eq!(backend.calls.borrow().len(), 1);
+}
+
+#[test]
+fn test_case_sensitive_query_pages_through_filtered_results() {
+    // "Make" (smart case) matches every other entry; atuin ignores case so
+    // returns both
+    let history = (0..20)
+        .map(|n| {
+            let command = if n % 2 == 0 { "Make" } else { "make" };
+            format!("{} {}|0|1ms|/repo|2024-01-01 10:00:{:02}\n", command, n, n)
+        })
+        .collect();
+    let backend = HistoryBackendStub {
+        history,
+        calls: RefCell::new(Vec::new()),
+    };
+    let page = |offset| {
+        let filters = SearchFilters {
+            limit: Some(3),
+            offset: Some(offset),
+            ..Default::default()
+        };
+        search_window_with_backend("Make", Some(filters), &backend).unwrap()
+    };
+    let commands = |entries: &[atuin_bar_lib::results::HistoryEntry]| -> Vec<String> {
+        entries.iter().map(|e| e.command.clone()).collect()
+    };
+
+    let first = page(0);
+    assert_eq!(commands(&first.entries), ["Make 14", "Make 16", "Make 18"]);
+    assert!(first.has_more);
+
+    // The offset counts displayed results, not atuin's rows
+    let second = page(3);
+    assert_eq!(commands(&second.entries), ["Make 8", "Make 10", "Make 12"]);
+    assert!(second.has_more);
+    assert!(backend
+        .calls
+        .borrow()
+        .iter()
+        .all(|call| !call.contains(&"--offset".to_string())));
+
+    let last = page(9);
+    assert_eq!(commands(&last.entries), ["Make 0"]);
+    assert!(!last.has_more);
+}
+
+#[test]
+fn test_oldest_first_sort_windows_every_match() {
+    let history = (0..10)
+        .map(|n| format!("cmd {}|0|1ms|/repo|2024-01-01 10:00:{:02}\n", n, n))
+        .collect();
+    let backend = HistoryBackendStub {
+        history,
+        calls: RefCell::new(Vec::new()),
+    };
+    let filters = SearchFilters {
+        sort: Some("oldest".to_string()),
+        limit: Some(3),
+        offset: Some(3),
+        ..Default::default()
+    };
+
+    let window = search_window_with_backend("", Some(filters), &backend).unwrap();
+    // Displayed in reverse, so the oldest entries come last in the output
+    let commands: Vec<_> = window.entries.iter().map(|e| e.command.as_str()).collect();
+    assert_eq!(commands, ["cmd 5", "cmd 4", "cmd 3"]);
+    assert!(window.has_more);
+    assert!(!backend.calls.borrow()[0].contains(&"--limit".to_string()));
+}
+
+#[test]
+fn test_search_runs_the_previewed_command() {
+    let filters = SearchFilters {
+        boolean_query: true,
+        search_mode: Some("fuzzy".to_string()),
+        exit_filter: Some("failure".to_string()),
+        limit: Some(10),
+        ..Default::default()
+    };
+    let backend = MockBackend::with_output(HISTORY);
+    search_with_backend("git AND push", Some(filters.clone()), &backend).unwrap();
+    assert_eq!(
+        backend.calls.borrow().first().cloned().unwrap(),
+        build_atuin_command("git AND push", &filters)
+    );
+
+    let unknown_mode = SearchFilters {
+        search_mode: Some("regex".to_string()),
+        ..Default::default()
+    };
+    let backend = MockBackend::with_output(HISTORY);
+    search_with_backend("git", Some(unknown_mode.clone()), &backend).unwrap();
+    assert_eq!(
+        backend.last_args(),
+        build_atuin_command("git", &unknown_mode)
+    );
+}
@@ -1,6 +1,8 @@
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { getCurrentWebviewWindow } from "@tauri-apps/api/webviewWindow";
 import { LogicalSize } from "@tauri-apps/api/dpi";
+import { openUrl } from "@tauri-apps/plugin-opener";
 
 function isTauri(): boolean {
   return typeof window !== "undefined" && "__TAURI_INTERNALS__" in window;
@@ -13,45 +15,79 @@ let filterPanelEl: HTMLElement | null;
 let filterDirectoryEl: HTMLInputElement | null;
 let filterExitEl: HTMLSelectElement | null;
 let filterTimeEl: HTMLSelectElement | null;
+let filterHostEl: HTMLSelectElement | null;
+let filterDedupEl: HTMLSelectElement | null;
+let filterCaseEl: HTMLSelectElement | null;
 let commandPopupEl: HTMLElement | null;
 let selectedIndex = -1;
 let currentResults: AtuinResult[] = [];
 let filtersVisible = false;
 let popupVisible = false;
+let pinned = false;
 
-const BASE_HEIGHT = 38;
-const FILTER_PANEL_HEIGHT = 56;
 const RESULT_HEIGHT = 32;
-const CONTAINER_PADDING = 8;
 
+interface ClickBehavior {
+  single: ClickAction;
+  double: ClickAction;
+}
+
+type ClickAction = "none" | "select" | "preview" | "copy" | "run";
+
+let clickBehavior: ClickBehavior = { single: "select", double: "copy" };
 let maxVisibleResults = 20;
 let windowWidth = 700;
+let windowHeight = 500;
+let pasteOnSelect = false;
 
 interface SearchFilters {
   directory?: string;
   exit_filter?: string;
   time_range?: string;
+  host?: string;
+  dedup?: boolean;
+  dedup_by?: string;
+  case_sensitive?: boolean;
+}
+
+interface DeepLinkSearch {
+  query: string;
+  filters: SearchFilters;
 }
 
 interface AtuinResult {
   command: string;
-  exit: string;
+  exit: number;
   duration: string;
   directory: string;
   time: string;
 }
 
-function parseAtuinLine(line: string): AtuinResult | null {
-  const parts = line.split("|");
-  if (parts.length >= 5) {
-    const command = parts.slice(0, -4).join("|");
-    const exit = parts[parts.length - 4];
-    const duration = parts[parts.length - 3];
-    const directory = parts[parts.length - 2];
-    const time = parts[parts.length - 1];
-    return { command, exit, duration, directory, time };
+// A window of results starting `offset` matches back from the newest
+interface ResultWindow {
+  entries: AtuinResult[];
+  offset: number;
+  has_more: boolean;
+  // atuin printed more than max_output_bytes, so only part was read
+  truncated: boolean;
+  // Lines of atuin's output dropped for not being valid UTF-8
+  skipped_lines: number;
+}
+
+// Whether older results follow the ones shown
+let hasMoreResults = false;
+// Why the results shown may be incomplete, if they may be
+let resultsNotice = "";
+
+function outputLossNotice(page: ResultWindow): string {
+  if (page.truncated) {
+    return "Some results were left out: atuin printed more than max_output_bytes";
+  }
+  if (page.skipped_lines > 0) {
+    const lines = page.skipped_lines === 1 ? "line" : "lines";
+    return `Skipped ${page.skipped_lines} ${lines} of history that weren't valid UTF-8`;
   }
-  return null;
+  return "";
 }
 
 function formatRelativeTime(timestamp: string): string {
@@ -89,6 +125,16 @@ function getFilters(): SearchFilters | undefined {
   if (filterTimeEl?.value) {
     filters.time_range = filterTimeEl.value;
   }
+  if (filterHostEl?.value) {
+    filters.host = filterHostEl.value;
+  }
+  if (filterDedupEl?.value) {
+    filters.dedup = true;
+    filters.dedup_by = filterDedupEl.value;
+  }
+  if (filterCaseEl?.value) {
+    filters.case_sensitive = filterCaseEl.value === "true";
+  }
 
   return Object.keys(filters).length > 0 ? filters : undefined;
 }
@@ -97,22 +143,30 @@ function hasActiveFilters(): boolean {
   return !!(
     filterDirectoryEl?.value ||
     filterExitEl?.value ||
-    filterTimeEl?.value
+    filterTimeEl?.value ||
+    filterHostEl?.value ||
+    filterDedupEl?.value ||
+    filterCaseEl?.value
   );
 }
 
+// Remember where the window is so it reopens there on the next launch
+async function saveWindowPosition() {
+  try {
+    await invoke("save_window_position");
+  } catch (error) {
+    console.error("Failed to save window position:", error);
+  }
+}
+
 async function resizeWindow(resultCount: number) {
   if (!isTauri()) return;
 
-  const visibleCount = Math.min(resultCount, maxVisibleResults);
-  const resultsHeight =
-    visibleCount > 0 ? visibleCount * RESULT_HEIGHT + CONTAINER_PADDING : 0;
-  const filterHeight = filtersVisible ? FILTER_PANEL_HEIGHT : 0;
-  const newHeight = BASE_HEIGHT + filterHeight + resultsHeight;
-
   try {
-    const window = getCurrentWebviewWindow();
-    await window.setSize(new LogicalSize(windowWidth, newHeight));
+    await invoke("resize_window_to_results", {
+      count: resultCount,
+      filtersVisible,
+    });
   } catch (error) {
     console.error("Failed to resize window:", error);
   }
@@ -137,7 +191,7 @@ function showPopup(result: AtuinResult, rowEl: Element) {
   }
 
   if (popupMeta) {
-    const exitClass = result.exit === "0" ? "exit-success" : "exit-failure";
+    const exitClass = result.exit === 0 ? "exit-success" : "exit-failure";
     const folderIcon = `<svg width="12" height="12" viewBox="0 0 16 16" fill="currentColor"><path d="M.54 3.87.5 3a2 2 0 0 1 2-2h3.672a2 2 0 0 1 1.414.586l.828.828A2 2 0 0 0 9.828 3H13.5a2 2 0 0 1 2 2v8a2 2 0 0 1-2 2H2.5a2 2 0 0 1-2-2V3.87z"/></svg>`;
     const clockIcon = `<svg width="12" height="12" viewBox="0 0 16 16" fill="currentColor"><path d="M8 3.5a.5.5 0 0 0-1 0V9a.5.5 0 0 0 .252.434l3.5 2a.5.5 0 0 0 .496-.868L8 8.71V3.5z"/><path d="M8 16A8 8 0 1 0 8 0a8 8 0 0 0 0 16zm7-8A7 7 0 1 1 1 8a7 7 0 0 1 14 0z"/></svg>`;
     popupMeta.innerHTML = `
@@ -170,13 +224,137 @@ async function resizeWindowForPopup() {
     const popupRect = commandPopupEl.getBoundingClientRect();
     const newHeight = popupRect.bottom + 12;
     await window.setSize(
-      new LogicalSize(windowWidth, Math.max(newHeight, 200)),
+      new LogicalSize(
+        windowWidth,
+        Math.min(Math.max(newHeight, 200), windowHeight),
+      ),
     );
   } catch (error) {
     console.error("Failed to resize window for popup:", error);
   }
 }
 
+interface CopyOutcome {
+  copied: boolean;
+  needs_confirmation: boolean;
+}
+
+// Run a copy command, asking whether to keep a trailing newline when the
+// backend wants confirmation. Returns whether anything was copied.
+async function copyWithConfirmation(
+  command: string,
+  args: Record<string, unknown>,
+): Promise<boolean> {
+  let outcome: CopyOutcome = await invoke(command, args);
+  if (outcome.needs_confirmation) {
+    const keepNewline = window.confirm(
+      "The command ends with a newline, which may run it as soon as it is pasted. Keep the newline?",
+    );
+    outcome = await invoke(command, { ...args, keepNewline });
+  }
+  return outcome.copied;
+}
+
+async function copyResultAndHide(result: AtuinResult) {
+  if (pasteOnSelect) {
+    await pasteResult(result);
+    return;
+  }
+  // Copies and hides in one call, leaving the window open if the copy fails
+  if (await copyWithConfirmation("copy_and_hide", { text: result.command })) {
+    await hideAfterCopy(result, true);
+  }
+}
+
+// Type a result into the previously focused window; the backend hides the
+// search window first. Falls back to copying when pasting isn't possible.
+async function pasteResult(result: AtuinResult) {
+  try {
+    await invoke("paste_to_active_window", { text: result.command });
+  } catch (error) {
+    window.alert(`${error}\n\nThe command was copied to the clipboard instead.`);
+    if (await copyWithConfirmation("copy_to_clipboard", { text: result.command })) {
+      await hideAfterCopy(result);
+    }
+    return;
+  }
+  await hideAfterCopy(result);
+}
+
+// Record a copied result and clear and hide the search window, unless the
+// backend already hid it
+async function hideAfterCopy(result: AtuinResult, hidden = false) {
+  invoke("record_selection", { command: result.command }).catch((error) =>
+    console.error("Failed to record selection:", error),
+  );
+  const query = atuinInputEl?.value.trim();
+  if (query) {
+    invoke("record_search", { query }).catch((error) =>
+      console.error("Failed to record search:", error),
+    );
+  }
+  const window = getCurrentWebviewWindow();
+  if (atuinInputEl) atuinInputEl.value = "";
+  if (atuinResultsEl) atuinResultsEl.innerHTML = "";
+  currentResults = [];
+  selectedIndex = -1;
+  await resizeWindow(0);
+  if (!hidden) {
+    await saveWindowPosition();
+    await window.hide();
+  }
+  // Offer the recent searches, including this one, next time the bar opens
+  searchAtuin();
+}
+
+async function runResult(result: AtuinResult) {
+  const args = {
+    commands: [result.command],
+    directory: result.directory,
+    stopOnError: true,
+  };
+  try {
+    await invoke("run_queue", args);
+  } catch (error) {
+    const message = String(error);
+    if (
+      message.startsWith("Confirmation required") &&
+      window.confirm(`Run "${result.command}"?`)
+    ) {
+      await invoke("run_queue", { ...args, confirmed: true });
+    } else {
+      throw error;
+    }
+  }
+}
+
+async function handleClick(action: ClickAction, index: number, row: Element) {
+  const result = currentResults[index];
+  if (!result) return;
+
+  try {
+    switch (action) {
+      case "select":
+        selectedIndex = index;
+        updateSelection();
+        break;
+      case "preview":
+        showPopup(result, row);
+        break;
+      case "copy":
+        await copyResultAndHide(result);
+        break;
+      case "run":
+        await runResult(result);
+        break;
+      case "none":
+        break;
+    }
+  } catch (error) {
+    console.error(`Failed to ${action} result:`, error);
+  }
+}
+
 function renderResults(results: AtuinResult[]) {
   if (!atuinResultsEl) return;
 
@@ -198,7 +376,7 @@ function renderResults(results: AtuinResult[]) {
     const metaEl = document.createElement("span");
     metaEl.className = "result-meta";
 
-    const exitClass = result.exit === "0" ? "exit-success" : "exit-failure";
+    const exitClass = result.exit === 0 ? "exit-success" : "exit-failure";
     const relativeTime = formatRelativeTime(result.time);
     metaEl.innerHTML = `<span class="${exitClass}">${result.duration}</span> <span class="result-time">${relativeTime}</span>`;
 
@@ -211,11 +389,150 @@ function renderResults(results: AtuinResult[]) {
     row.addEventListener("mouseleave", () => {
       hidePopup();
     });
+    row.addEventListener("click", () => {
+      handleClick(clickBehavior.single, index, row);
+    });
+    row.addEventListener("dblclick", () => {
+      handleClick(clickBehavior.double, index, row);
+    });
 
     resultsContainer.appendChild(row);
   });
 
-  resizeWindow(results.length);
+  if (hasMoreResults) {
+    const loadMore = document.createElement("button");
+    loadMore.type = "button";
+    loadMore.className = "load-more";
+    loadMore.textContent = "Load more";
+    loadMore.addEventListener("click", loadMoreResults);
+    resultsContainer.appendChild(loadMore);
+  }
+
+  if (resultsNotice) {
+    const notice = document.createElement("div");
+    notice.className = "results-notice";
+    notice.textContent = resultsNotice;
+    resultsContainer.appendChild(notice);
+  }
+
+  resizeWindow(
+    results.length + (hasMoreResults ? 1 : 0) + (resultsNotice ? 1 : 0),
+  );
+}
+
+// Fetch the next window of older results and add it below the current ones
+async function loadMoreResults() {
+  if (!atuinInputEl || !isTauri()) return;
+
+  const query = atuinInputEl.value.trim();
+  const requestId = ++searchRequestId;
+  try {
+    const page: ResultWindow = await invoke("atuin_search_window", {
+      query,
+      filters: getFilters(),
+      offset: currentResults.length,
+      requestId,
+    });
+    if (requestId !== searchRequestId) return;
+
+    const firstNew = currentResults.length;
+    hasMoreResults = page.has_more;
+    resultsNotice = resultsNotice || outputLossNotice(page);
+    renderResults(currentResults.concat(page.entries.reverse()));
+    selectedIndex = Math.min(firstNew, currentResults.length - 1);
+    updateSelection();
+  } catch (error) {
+    if (isSearchError(error) && error.kind === "superseded") return;
+    console.error("Failed to load more results:", error);
+  }
+}
+
+// Mirrors the backend's SearchError, tagged by `kind`
+type SearchError =
+  | { kind: "atuin_not_installed"; path: string }
+  | { kind: "command_failed"; stderr: string }
+  | { kind: "timed_out"; timeout_ms: number }
+  | {
+      kind:
+        | "spawn_failed"
+        | "invalid_filter"
+        | "invalid_config"
+        | "internal";
+      message: string;
+    }
+  | { kind: "superseded"; request_id: number };
+
+function isSearchError(error: unknown): error is SearchError {
+  return typeof error === "object" && error !== null && "kind" in error;
+}
+
+const ATUIN_INSTALL_URL = "https://docs.atuin.sh/guide/installation/";
+
+// Explain that atuin is missing, with a link to its install instructions
+function showAtuinMissing(message: string) {
+  if (!atuinResultsEl) return;
+
+  const row = document.createElement("div");
+  row.className = "result-row atuin-missing";
+  const textEl = document.createElement("span");
+  textEl.className = "result-command";
+  textEl.textContent = message;
+  textEl.title = message;
+  const link = document.createElement("a");
+  link.href = ATUIN_INSTALL_URL;
+  link.textContent = "Install atuin";
+  link.addEventListener("click", (e) => {
+    e.preventDefault();
+    openUrl(ATUIN_INSTALL_URL).catch((error) =>
+      console.error("Failed to open install instructions:", error),
+    );
+  });
+  row.append(textEl, link);
+
+  atuinResultsEl.innerHTML = "";
+  atuinResultsEl.appendChild(row);
+  currentResults = [];
+  selectedIndex = -1;
+  resizeWindow(1);
+}
+
+// Id of the latest search, so responses to older ones can be ignored
+let searchRequestId = 0;
+
+// With an empty query, list recent searches; clicking one searches again
+async function showRecentSearches(requestId: number) {
+  if (!atuinResultsEl || !isTauri()) return;
+
+  let queries: string[] = [];
+  try {
+    queries = await invoke("recent_searches");
+  } catch (error) {
+    console.error("Failed to load recent searches:", error);
+  }
+  if (requestId !== searchRequestId) return;
+
+  atuinResultsEl.innerHTML = "";
+  currentResults = [];
+  selectedIndex = -1;
+  hasMoreResults = false;
+  resultsNotice = "";
+  for (const query of queries) {
+    const row = document.createElement("div");
+    row.className = "result-row recent-search";
+    const textEl = document.createElement("span");
+    textEl.className = "result-command";
+    textEl.textContent = query;
+    textEl.title = query;
+    row.appendChild(textEl);
+    row.addEventListener("click", () => {
+      if (!atuinInputEl) return;
+      atuinInputEl.value = query;
+      atuinInputEl.focus();
+      searchAtuin();
+    });
+    atuinResultsEl.appendChild(row);
+  }
+  resizeWindow(queries.length);
 }
 
 async function searchAtuin() {
@@ -223,10 +540,12 @@ async function searchAtuin() {
 
   const query = atuinInputEl.value.trim();
   console.log("searchAtuin called with query:", query);
+  const requestId = ++searchRequestId;
 
   if (!query) {
     atuinResultsEl.innerHTML = "";
     resizeWindow(0);
+    showRecentSearches(requestId);
     return;
   }
 
@@ -236,40 +555,65 @@ async function searchAtuin() {
   }
 
   try {
-    console.log("Invoking atuin_search_command...");
+    console.log("Invoking atuin_search_window...");
     const filters = getFilters();
-    const output: string = await invoke("atuin_search_command", {
+    const page: ResultWindow = await invoke("atuin_search_window", {
       query,
       filters,
+      requestId,
     });
-    console.log("Got output:", output);
+    const results = page.entries;
+    console.log("Got results:", results.length);
+    if (requestId !== searchRequestId) return;
+    hasMoreResults = page.has_more;
+    resultsNotice = outputLossNotice(page);
 
-    if (!output || output.trim() === "") {
-      console.log("Empty output");
+    if (results.length === 0) {
       atuinResultsEl.innerHTML = "";
       resizeWindow(0);
       return;
     }
 
-    const lines = output.split("\n").filter((line) => line.trim() !== "");
-    const results: AtuinResult[] = [];
-
-    for (const line of lines) {
-      const parsed = parseAtuinLine(line);
-      if (parsed) {
-        results.push(parsed);
-      }
-    }
-
-    console.log("Parsed results:", results.length);
     renderResults(results.reverse());
   } catch (error) {
+    if (
+      requestId !== searchRequestId ||
+      (isSearchError(error) && error.kind === "superseded")
+    ) {
+      return;
+    }
     console.error("Atuin search error:", error);
+    if (isSearchError(error) && error.kind === "atuin_not_installed") {
+      showAtuinMissing(`atuin binary not found at ${error.path}`);
+      return;
+    }
     atuinResultsEl.innerHTML = "";
     resizeWindow(0);
   }
 }
 
+const DIRECTORY_COMPLETION_LIMIT = 8;
+
+async function updateDirectoryCompletions() {
+  const list = document.querySelector("#directory-completions");
+  if (!list || !filterDirectoryEl || !isTauri()) return;
+
+  try {
+    const directories: string[] = await invoke("complete_directory", {
+      prefix: filterDirectoryEl.value,
+      limit: DIRECTORY_COMPLETION_LIMIT,
+    });
+    list.innerHTML = "";
+    for (const directory of directories) {
+      const option = document.createElement("option");
+      option.value = directory;
+      list.appendChild(option);
+    }
+  } catch (error) {
+    console.error("Failed to complete directory:", error);
+  }
+}
+
 let searchTimeout: ReturnType<typeof setTimeout> | null = null;
 
 function debounceSearch() {
@@ -296,16 +640,80 @@ function updateFilterToggleState() {
   );
 }
 
-async function loadConfig() {
+function applyDeepLinkSearch(search: DeepLinkSearch) {
+  if (atuinInputEl) atuinInputEl.value = search.query;
+  if (filterDirectoryEl) filterDirectoryEl.value = search.filters.directory ?? "";
+  if (filterExitEl) filterExitEl.value = search.filters.exit_filter ?? "";
+  if (filterTimeEl) filterTimeEl.value = search.filters.time_range ?? "";
+  updateFilterToggleState();
+  saveLastFilters();
+  atuinInputEl?.focus();
+  searchAtuin();
+}
+
+// Remember the applied filters for the next launch (if remember_filters
+// is set); searches themselves don't touch the saved filters
+function saveLastFilters() {
+  if (!isTauri()) return;
+  invoke("set_last_filters", { filters: getFilters() ?? {} }).catch((error) =>
+    console.error("Failed to save filters:", error),
+  );
+}
+
+async function restoreLastFilters() {
   if (!isTauri()) return;
 
   try {
-    const theme: string = await invoke("get_theme");
-    if (theme === "light") {
-      document.documentElement.classList.add("light");
-    } else {
-      document.documentElement.classList.remove("light");
+    const filters: SearchFilters = await invoke("last_filters");
+    if (filterDirectoryEl) filterDirectoryEl.value = filters.directory ?? "";
+    if (filterExitEl) filterExitEl.value = filters.exit_filter ?? "";
+    if (filterTimeEl) filterTimeEl.value = filters.time_range ?? "";
+    if (filterHostEl) filterHostEl.value = filters.host ?? "";
+    if (filterDedupEl) {
+      filterDedupEl.value = filters.dedup ? (filters.dedup_by ?? "command") : "";
+    }
+    if (filterCaseEl) {
+      filterCaseEl.value =
+        filters.case_sensitive === undefined || filters.case_sensitive === null
+          ? ""
+          : String(filters.case_sensitive);
+    }
+    updateFilterToggleState();
+  } catch (error) {
+    console.error("Failed to restore filters:", error);
+  }
+}
+
+// Offer the host filter only when history is synced from several machines
+async function loadHosts() {
+  if (!filterHostEl || !isTauri()) return;
+
+  try {
+    const hosts: string[] = await invoke("list_hosts");
+    for (const host of hosts) {
+      const option = document.createElement("option");
+      option.value = host;
+      option.textContent = host;
+      filterHostEl.appendChild(option);
     }
+    document
+      .querySelector("#filter-host-group")
+      ?.classList.toggle("hidden", hosts.length < 2);
+  } catch (error) {
+    console.error("Failed to list hosts:", error);
+  }
+}
+
+async function applyTheme() {
+  const theme: string = await invoke("get_effective_theme");
+  document.documentElement.classList.toggle("light", theme === "light");
+}
+
+async function loadConfig() {
+  if (!isTauri()) return;
+
+  try {
+    await applyTheme();
 
     const configMaxResults: number = await invoke("get_max_results");
     maxVisibleResults = configMaxResults;
@@ -313,6 +721,19 @@ async function loadConfig() {
     const configWindowWidth: number = await invoke("get_window_width");
     windowWidth = configWindowWidth;
 
+    windowHeight = await invoke("get_window_height");
+
+    const fontSize: number = await invoke("get_font_size");
+    document.documentElement.style.setProperty("--result-font-size", `${fontSize}px`);
+
+    // The window is transparent, so fading its content fades the whole bar
+    const opacity: number = await invoke("get_opacity");
+    document.documentElement.style.opacity = opacity.toString();
+
+    clickBehavior = await invoke("get_click_behavior");
+
+    pasteOnSelect = await invoke("get_paste_on_select");
+
     if (atuinResultsEl) {
       atuinResultsEl.style.maxHeight = `${maxVisibleResults * RESULT_HEIGHT}px`;
     }
@@ -329,27 +750,91 @@ window.addEventListener("DOMContentLoaded", async () => {
   filterDirectoryEl = document.querySelector("#filter-directory");
   filterExitEl = document.querySelector("#filter-exit");
   filterTimeEl = document.querySelector("#filter-time");
+  filterHostEl = document.querySelector("#filter-host");
+  filterDedupEl = document.querySelector("#filter-dedup");
+  filterCaseEl = document.querySelector("#filter-case");
   commandPopupEl = document.querySelector("#command-popup");
 
+  // A reloaded page numbers its searches from 0 again
+  if (isTauri()) {
+    await invoke("reset_search_sequence").catch((error) =>
+      console.error("Failed to reset search sequence:", error),
+    );
+  }
+
   await loadConfig();
+  await loadHosts();
+  await restoreLastFilters();
 
   if (atuinInputEl) {
     atuinInputEl.addEventListener("input", debounceSearch);
     atuinInputEl.focus();
+    if (!atuinInputEl.value.trim()) showRecentSearches(++searchRequestId);
   }
 
   filterToggleEl?.addEventListener("click", toggleFilters);
 
+  if (isTauri()) {
+    await listen<DeepLinkSearch>("deep-link-search", (event) => {
+      applyDeepLinkSearch(event.payload);
+    });
+    // A deep link the app was launched with arrived before the listener
+    try {
+      const pending: DeepLinkSearch | null = await invoke("take_deep_link");
+      if (pending) applyDeepLinkSearch(pending);
+    } catch (error) {
+      console.error("Failed to take pending deep link:", error);
+    }
+    // Opened with the global shortcut: ready the query for typing
+    await listen<boolean>("window-shown", () => {
+      atuinInputEl?.focus();
+      atuinInputEl?.select();
+    });
+    // The window hid with clear_on_hide set; reopen to an empty query
+    await listen("reset-search", () => {
+      if (atuinInputEl) atuinInputEl.value = "";
+      showRecentSearches(++searchRequestId);
+    });
+    // config.toml was edited on disk; pick up theme, sizes and click behavior
+    await listen("config-reloaded", () => {
+      loadConfig();
+    });
+    // Follow OS appearance changes when the theme is "system"
+    await getCurrentWebviewWindow().onThemeChanged(() => {
+      applyTheme().catch((error) => console.error("Failed to apply theme:", error));
+    });
+  }
+
   filterDirectoryEl?.addEventListener("input", () => {
     updateFilterToggleState();
+    updateDirectoryCompletions();
     debounceSearch();
   });
+  // Saved once the directory is committed rather than on every keystroke
+  filterDirectoryEl?.addEventListener("change", saveLastFilters);
   filterExitEl?.addEventListener("change", () => {
     updateFilterToggleState();
+    saveLastFilters();
     debounceSearch();
   });
   filterTimeEl?.addEventListener("change", () => {
     updateFilterToggleState();
+    saveLastFilters();
+    debounceSearch();
+  });
+  filterHostEl?.addEventListener("change", () => {
+    updateFilterToggleState();
+    saveLastFilters();
+    debounceSearch();
+  });
+  filterDedupEl?.addEventListener("change", () => {
+    updateFilterToggleState();
+    saveLastFilters();
+    debounceSearch();
+  });
+  filterCaseEl?.addEventListener("change", () => {
+    updateFilterToggleState();
+    saveLastFilters();
     debounceSearch();
   });
 
@@ -368,20 +853,10 @@ window.addEventListener("DOMContentLoaded", async () => {
         return;
       }
 
+      // The query is cleared on hide unless clear_on_hide is off
       try {
-        const window = getCurrentWebviewWindow();
-        if (atuinInputEl) atuinInputEl.value = "";
-        if (atuinResultsEl) atuinResultsEl.innerHTML = "";
-        if (filterDirectoryEl) filterDirectoryEl.value = "";
-        if (filterExitEl) filterExitEl.value = "";
-        if (filterTimeEl) filterTimeEl.value = "";
-        if (filterPanelEl) filterPanelEl.classList.add("hidden");
-        filtersVisible = false;
-        updateFilterToggleState();
-        currentResults = [];
-        selectedIndex = -1;
-        await resizeWindow(0);
-        await window.hide();
+        await saveWindowPosition();
+        await invoke("hide_window");
       } catch (error) {
         console.error("Failed to hide window:", error);
       }
@@ -417,21 +892,59 @@ window.addEventListener("DOMContentLoaded", async () => {
     }
 
     if (
-      e.key === "Enter" &&
+      e.key === "t" &&
+      (e.metaKey || e.ctrlKey) &&
       selectedIndex >= 0 &&
       selectedIndex < currentResults.length
     ) {
       e.preventDefault();
       const selected = currentResults[selectedIndex];
       try {
-        await invoke("copy_to_clipboard", { text: selected.command });
-        const window = getCurrentWebviewWindow();
-        if (atuinInputEl) atuinInputEl.value = "";
-        if (atuinResultsEl) atuinResultsEl.innerHTML = "";
-        currentResults = [];
-        selectedIndex = -1;
-        await resizeWindow(0);
-        await window.hide();
+        await invoke("open_in_terminal", {
+          command: selected.command,
+          directory: selected.directory,
+        });
+      } catch (error) {
+        console.error("Failed to open terminal:", error);
+      }
+    }
+
+    if (e.key === "p" && (e.metaKey || e.ctrlKey) && isTauri()) {
+      e.preventDefault();
+      try {
+        await invoke("set_pinned", { pinned: !pinned });
+        pinned = !pinned;
+        document.body.classList.toggle("pinned", pinned);
+      } catch (error) {
+        console.error("Failed to pin window:", error);
+      }
+    }
+
+    if (
+      (e.metaKey || e.ctrlKey) &&
+      e.key >= "1" &&
+      e.key <= "9" &&
+      Number(e.key) <= currentResults.length
+    ) {
+      e.preventDefault();
+      const index = Number(e.key);
+      try {
+        if (await copyWithConfirmation("copy_nth_result", { index })) {
+          await hideAfterCopy(currentResults[index - 1]);
+        }
+      } catch (error) {
+        console.error("Failed to copy result:", error);
+      }
+    }
+
+    if (
+      e.key === "Enter" &&
+      selectedIndex >= 0 &&
+      selectedIndex < currentResults.length
+    ) {
+      e.preventDefault();
+      try {
+        await copyResultAndHide(currentResults[selectedIndex]);
       } catch (error) {
         console.error("Failed to copy to clipboard:", error);
       }
@@ -6,30 +6,165 @@ interface Config {
   theme: string;
   max_results: number;
   window_width: number;
+  font_size: number;
+  window_height: number;
+  search_mode: string;
+  atuin_path: string;
+}
+
+interface KeybindingInfo {
+  accelerator: string;
+  action: string;
+  description: string;
+  scope: "global" | "window";
 }
 
 let shortcutInput: HTMLInputElement | null;
 let themeSelect: HTMLSelectElement | null;
+let searchModeSelect: HTMLSelectElement | null;
+let atuinPathInput: HTMLInputElement | null;
 let maxResultsInput: HTMLInputElement | null;
 let windowWidthInput: HTMLInputElement | null;
+let fontSizeInput: HTMLInputElement | null;
+let windowHeightInput: HTMLInputElement | null;
 let saveButton: HTMLButtonElement | null;
 let cancelButton: HTMLButtonElement | null;
+interface PermissionStatus {
+  state: "granted" | "missing" | "not_applicable";
+  missing: string[];
+  guidance: string;
+}
+
 let messageDiv: HTMLElement | null;
+let permissionWarning: HTMLElement | null;
+let shortcutWarning: HTMLElement | null;
+let cheatsheetTable: HTMLTableElement | null;
+let configPathEl: HTMLElement | null;
+let configErrorEl: HTMLElement | null;
+let activeShortcut = "";
 
 async function loadConfig() {
   try {
     const config: Config = await invoke("get_config");
 
+    activeShortcut = config.shortcut;
     if (shortcutInput) shortcutInput.value = config.shortcut;
     if (themeSelect) themeSelect.value = config.theme;
+    if (searchModeSelect) searchModeSelect.value = config.search_mode;
+    if (atuinPathInput) atuinPathInput.value = config.atuin_path;
     if (maxResultsInput) maxResultsInput.value = config.max_results.toString();
     if (windowWidthInput) windowWidthInput.value = config.window_width.toString();
+    if (fontSizeInput) fontSizeInput.value = config.font_size.toString();
+    if (windowHeightInput) windowHeightInput.value = config.window_height.toString();
+
+    // Fields set by ATUIN_BAR_* variables can't be changed here
+    const overridden: string[] = await invoke("env_overridden_fields");
+    const inputs: Record<string, HTMLInputElement | HTMLSelectElement | null> = {
+      shortcut: shortcutInput,
+      theme: themeSelect,
+      max_results: maxResultsInput,
+      window_width: windowWidthInput,
+    };
+    for (const [field, input] of Object.entries(inputs)) {
+      if (!input) continue;
+      const isOverridden = overridden.includes(field);
+      input.disabled = isOverridden;
+      input.title = isOverridden
+        ? `Set by the ATUIN_BAR_${field.toUpperCase()} environment variable`
+        : "";
+    }
   } catch (error) {
     console.error("Failed to load config:", error);
     showMessage("Failed to load configuration", "error");
   }
 }
 
+async function loadCheatsheet() {
+  if (!cheatsheetTable) return;
+
+  try {
+    const bindings: KeybindingInfo[] = await invoke("keybindings_cheatsheet");
+    cheatsheetTable.innerHTML = "";
+    for (const binding of bindings) {
+      const row = cheatsheetTable.insertRow();
+      const keyCell = row.insertCell();
+      const kbd = document.createElement("kbd");
+      kbd.textContent = binding.accelerator;
+      keyCell.appendChild(kbd);
+      const descriptionCell = row.insertCell();
+      descriptionCell.textContent =
+        binding.scope === "global"
+          ? `${binding.description} (global)`
+          : binding.description;
+    }
+  } catch (error) {
+    console.error("Failed to load keybindings:", error);
+  }
+}
+
+async function loadConfigPath() {
+  if (!configPathEl) return;
+
+  try {
+    const path: string | null = await invoke("config_file_path");
+    if (path) configPathEl.textContent = path;
+  } catch (error) {
+    console.error("Failed to get config path:", error);
+  }
+}
+
+// Point out where a hand-edited config file fails to parse; the app is
+// running on defaults until it is fixed
+async function checkConfigFile() {
+  if (!configErrorEl) return;
+
+  try {
+    await invoke("validate_config");
+    configErrorEl.className = "message";
+  } catch (error) {
+    configErrorEl.textContent = `${error}. Defaults are in use until it is fixed.`;
+    configErrorEl.className = "message error";
+  }
+}
+
+async function openConfigFile(reveal: boolean) {
+  try {
+    await invoke("open_config_file", { reveal });
+  } catch (error) {
+    console.error("Failed to open config file:", error);
+    showMessage(`Failed to open config file: ${error}`, "error");
+  }
+}
+
+async function checkPermissions() {
+  if (!permissionWarning) return;
+
+  try {
+    const status: PermissionStatus = await invoke("check_shortcut_permissions");
+    if (status.state === "missing") {
+      permissionWarning.textContent = `The global shortcut may not work: ${status.guidance}`;
+      permissionWarning.style.display = "block";
+    }
+  } catch (error) {
+    console.error("Failed to check shortcut permissions:", error);
+  }
+}
+
+// Flag modifiers the platform's global shortcut backend may not support
+async function checkShortcutWarnings() {
+  if (!shortcutWarning || !shortcutInput) return;
+
+  try {
+    const warnings: string[] = await invoke("shortcut_warnings", {
+      accelerator: shortcutInput.value.trim(),
+    });
+    shortcutWarning.textContent = warnings.join("\n");
+    shortcutWarning.style.display = warnings.length > 0 ? "block" : "none";
+  } catch (error) {
+    console.error("Failed to check shortcut:", error);
+  }
+}
+
 function showMessage(text: string, type: "success" | "error") {
   if (!messageDiv) return;
 
@@ -53,6 +188,11 @@ async function saveConfig() {
   const theme = themeSelect.value;
   const maxResults = parseInt(maxResultsInput.value);
   const windowWidth = parseInt(windowWidthInput.value);
+  const windowHeight = windowHeightInput
+    ? parseInt(windowHeightInput.value)
+    : undefined;
+  const atuinPath = atuinPathInput?.value.trim() || "atuin";
+  const fontSize = fontSizeInput ? parseInt(fontSizeInput.value) : undefined;
 
   // Validate inputs
   if (!shortcut) {
@@ -70,17 +210,45 @@ async function saveConfig() {
     return;
   }
 
+  if (
+    windowHeight !== undefined &&
+    (isNaN(windowHeight) || windowHeight < 200 || windowHeight > 4000)
+  ) {
+    showMessage("Window height must be between 200 and 4000", "error");
+    return;
+  }
+
+  if (
+    fontSize !== undefined &&
+    (isNaN(fontSize) || fontSize < 8 || fontSize > 72)
+  ) {
+    showMessage("Font size must be between 8 and 72", "error");
+    return;
+  }
+
   if (saveButton) saveButton.disabled = true;
 
   try {
+    // Re-registers the global shortcut live before saving
     await invoke("update_config", {
-      shortcut,
-      theme,
-      maxResults,
-      windowWidth,
+      update: {
+        shortcut,
+        theme,
+        max_results: maxResults,
+        window_width: windowWidth,
+        window_height: windowHeight,
+        search_mode: searchModeSelect?.value,
+        atuin_path: atuinPath,
+        font_size: fontSize,
+      },
     });
 
-    showMessage("Settings saved successfully! Restart the app to apply shortcut changes.", "success");
+    if (shortcut !== activeShortcut) {
+      activeShortcut = shortcut;
+      await loadCheatsheet();
+    }
+
+    showMessage("Settings saved successfully!", "success");
 
     // Reload main window config if it exists
     // This will update theme and other settings without restart
@@ -102,13 +270,36 @@ function cancelSettings() {
 window.addEventListener("DOMContentLoaded", async () => {
   shortcutInput = document.querySelector("#shortcut");
   themeSelect = document.querySelector("#theme");
+  searchModeSelect = document.querySelector("#search_mode");
+  atuinPathInput = document.querySelector("#atuin_path");
   maxResultsInput = document.querySelector("#max_results");
   windowWidthInput = document.querySelector("#window_width");
+  fontSizeInput = document.querySelector("#font_size");
+  windowHeightInput = document.querySelector("#window_height");
   saveButton = document.querySelector("#save-button");
   cancelButton = document.querySelector("#cancel-button");
   messageDiv = document.querySelector("#message");
+  cheatsheetTable = document.querySelector("#cheatsheet");
+  permissionWarning = document.querySelector("#permission-warning");
+  shortcutWarning = document.querySelector("#shortcut-warning");
+  configPathEl = document.querySelector("#config-path");
+  configErrorEl = document.querySelector("#config-error");
 
   await loadConfig();
+  await loadConfigPath();
+  await checkConfigFile();
+  await loadCheatsheet();
+  await checkPermissions();
+  await checkShortcutWarnings();
+
+  shortcutInput?.addEventListener("change", checkShortcutWarnings);
+
+  document
+    .querySelector("#open-config-button")
+    ?.addEventListener("click", () => openConfigFile(false));
+  document
+    .querySelector("#reveal-config-button")
+    ?.addEventListener("click", () => openConfigFile(true));
 
   saveButton?.addEventListener("click", saveConfig);
   cancelButton?.addEventListener("click", cancelSettings);